@@ -0,0 +1,374 @@
+use crate::formatter::{DataFormat, StreamDataFormat, render};
+use crate::raw::Raw;
+use chrono::NaiveDateTime;
+use std::fmt::{self, Write};
+use wp_model_core::model::{DataField, DataRecord, DataType, Value, types::value::ObjectValue};
+
+/// Where a SIEM header slot (vendor, product, event id, ...) gets its value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderSource {
+    Fixed(String),
+    /// Taken from the named record field, which is then left out of the
+    /// extension. A missing field yields an empty slot.
+    Field(String),
+}
+
+impl HeaderSource {
+    pub fn fixed(value: impl Into<String>) -> Self {
+        HeaderSource::Fixed(value.into())
+    }
+    pub fn field(name: impl Into<String>) -> Self {
+        HeaderSource::Field(name.into())
+    }
+
+    pub(crate) fn field_name(&self) -> Option<&str> {
+        match self {
+            HeaderSource::Field(name) => Some(name),
+            HeaderSource::Fixed(_) => None,
+        }
+    }
+
+    pub(crate) fn resolve(&self, record: &DataRecord) -> String {
+        match self {
+            HeaderSource::Fixed(v) => v.clone(),
+            HeaderSource::Field(name) => record
+                .items
+                .iter()
+                .find(|f| f.get_name() == name.as_str())
+                .map(|f| render(|w| Raw::new().fmt_value_into(f.get_value(), w)))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl From<&str> for HeaderSource {
+    fn from(value: &str) -> Self {
+        HeaderSource::Fixed(value.to_string())
+    }
+}
+
+/// Calls `f` for every scalar under `value`, with nested object keys and
+/// array indices joined onto `prefix` by `sep`. Nulls are skipped.
+pub(crate) fn for_each_leaf(
+    prefix: &mut String,
+    sep: &str,
+    value: &Value,
+    f: &mut dyn FnMut(&str, &Value) -> fmt::Result,
+) -> fmt::Result {
+    let children: Vec<(String, &Value)> = match value {
+        Value::Null | Value::Ignore(_) => return Ok(()),
+        Value::Obj(obj) => obj
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.get_value()))
+            .collect(),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i.to_string(), v.get_value()))
+            .collect(),
+        _ => return f(prefix, value),
+    };
+    for (key, child) in children {
+        let len = prefix.len();
+        prefix.push_str(sep);
+        prefix.push_str(&key);
+        for_each_leaf(prefix, sep, child, f)?;
+        prefix.truncate(len);
+    }
+    Ok(())
+}
+
+/// Header text for pipe-delimited SIEM headers: `\` and `|` are escaped and
+/// line breaks, which would end the event, become spaces.
+pub(crate) fn write_pipe_header(out: &mut dyn Write, value: &str) -> fmt::Result {
+    for c in value.chars() {
+        match c {
+            '\\' | '|' => {
+                out.write_char('\\')?;
+                out.write_char(c)?;
+            }
+            '\r' | '\n' => out.write_char(' ')?,
+            c => out.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+/// ArcSight Common Event Format:
+/// `CEF:0|Vendor|Product|Version|SignatureID|Name|Severity|k=v k=v`.
+///
+/// Fields used by the header are left out of the extension. The time field
+/// (`time` by default) is written as the `rt` key. Extension keys keep only
+/// ASCII letters, digits and `_`; nested values are flattened with `_`.
+/// `Time` values are rendered as epoch milliseconds (UTC).
+#[derive(Debug, Clone)]
+pub struct Cef {
+    vendor: HeaderSource,
+    product: HeaderSource,
+    version: HeaderSource,
+    signature_id: HeaderSource,
+    name: HeaderSource,
+    severity: HeaderSource,
+    time_field: Option<String>,
+}
+
+impl Default for Cef {
+    fn default() -> Self {
+        Self {
+            vendor: HeaderSource::fixed("WarpParse"),
+            product: HeaderSource::fixed("warp-parse"),
+            version: HeaderSource::fixed(env!("CARGO_PKG_VERSION")),
+            signature_id: HeaderSource::fixed("0"),
+            name: HeaderSource::fixed("event"),
+            severity: HeaderSource::fixed("5"),
+            time_field: Some("time".to_string()),
+        }
+    }
+}
+
+impl Cef {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_vendor(mut self, src: impl Into<HeaderSource>) -> Self {
+        self.vendor = src.into();
+        self
+    }
+    pub fn with_product(mut self, src: impl Into<HeaderSource>) -> Self {
+        self.product = src.into();
+        self
+    }
+    pub fn with_version(mut self, src: impl Into<HeaderSource>) -> Self {
+        self.version = src.into();
+        self
+    }
+    pub fn with_signature_id(mut self, src: impl Into<HeaderSource>) -> Self {
+        self.signature_id = src.into();
+        self
+    }
+    pub fn with_name(mut self, src: impl Into<HeaderSource>) -> Self {
+        self.name = src.into();
+        self
+    }
+    /// `0`-`10` or `Low`/`Medium`/`High`/`Very-High`.
+    pub fn with_severity(mut self, src: impl Into<HeaderSource>) -> Self {
+        self.severity = src.into();
+        self
+    }
+    /// Field written as the `rt` (receipt time) extension key; `None` keeps
+    /// every field under its own name.
+    pub fn with_time_field(mut self, name: Option<String>) -> Self {
+        self.time_field = name;
+        self
+    }
+
+    fn headers(&self) -> [&HeaderSource; 6] {
+        [
+            &self.vendor,
+            &self.product,
+            &self.version,
+            &self.signature_id,
+            &self.name,
+            &self.severity,
+        ]
+    }
+
+    fn is_extension(&self, field: &DataField) -> bool {
+        *field.get_meta() != DataType::Ignore
+            && !self
+                .headers()
+                .iter()
+                .any(|h| h.field_name() == Some(field.get_name()))
+    }
+
+    fn write_extension(
+        &self,
+        field: &DataField,
+        first: &mut bool,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        let mut prefix = if self.time_field.as_deref() == Some(field.get_name()) {
+            "rt".to_string()
+        } else {
+            field.get_name().to_string()
+        };
+        for_each_leaf(&mut prefix, "_", field.get_value(), &mut |key, value| {
+            let key: String = key
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect();
+            if !*first {
+                out.write_char(' ')?;
+            }
+            *first = false;
+            write!(out, "{}=", key)?;
+            self.fmt_value_into(value, out)
+        })
+    }
+}
+
+/// Scalar methods render extension values: `\`, `=` and line breaks are
+/// escaped.
+impl StreamDataFormat for Cef {
+    fn format_null_into(&self, _out: &mut dyn Write) -> fmt::Result {
+        Ok(())
+    }
+    fn format_bool_into(&self, v: &bool, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_string_into(&self, v: &str, out: &mut dyn Write) -> fmt::Result {
+        for c in v.chars() {
+            match c {
+                '\\' => out.write_str("\\\\")?,
+                '=' => out.write_str("\\=")?,
+                '\n' => out.write_str("\\n")?,
+                '\r' => out.write_str("\\r")?,
+                c => out.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+    fn format_i64_into(&self, v: &i64, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_f64_into(&self, v: &f64, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_ip_into(&self, v: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_datetime_into(&self, v: &NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v.and_utc().timestamp_millis())
+    }
+    fn format_object_into(&self, value: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        let mut first = true;
+        for field in value.values() {
+            self.write_extension(field, &mut first, out)?;
+        }
+        Ok(())
+    }
+    fn format_array_into(&self, value: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        for (i, field) in value.iter().enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            self.fmt_value_into(field.get_value(), out)?;
+        }
+        Ok(())
+    }
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        let mut first = true;
+        self.write_extension(field, &mut first, out)
+    }
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        out.write_str("CEF:0")?;
+        for header in self.headers() {
+            out.write_char('|')?;
+            write_pipe_header(out, &header.resolve(record))?;
+        }
+        out.write_char('|')?;
+        let mut first = true;
+        for field in record.items.iter().filter(|f| self.is_extension(f)) {
+            self.write_extension(field, &mut first, out)?;
+        }
+        Ok(())
+    }
+}
+
+impl DataFormat for Cef {
+    type Output = String;
+    fn format_null(&self) -> String {
+        String::new()
+    }
+    fn format_bool(&self, v: &bool) -> String {
+        v.to_string()
+    }
+    fn format_string(&self, v: &str) -> String {
+        render(|out| self.format_string_into(v, out))
+    }
+    fn format_i64(&self, v: &i64) -> String {
+        v.to_string()
+    }
+    fn format_f64(&self, v: &f64) -> String {
+        v.to_string()
+    }
+    fn format_ip(&self, v: &std::net::IpAddr) -> String {
+        v.to_string()
+    }
+    fn format_datetime(&self, v: &NaiveDateTime) -> String {
+        render(|out| self.format_datetime_into(v, out))
+    }
+    fn format_object(&self, value: &ObjectValue) -> String {
+        render(|out| self.format_object_into(value, out))
+    }
+    fn format_array(&self, value: &[DataField]) -> String {
+        render(|out| self.format_array_into(value, out))
+    }
+    fn format_field(&self, field: &DataField) -> String {
+        render(|out| self.format_field_into(field, out))
+    }
+    fn format_record(&self, record: &DataRecord) -> String {
+        render(|out| self.format_record_into(record, out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(items: Vec<DataField>) -> DataRecord {
+        DataRecord { id: 0, items }
+    }
+
+    fn cef() -> Cef {
+        Cef::new()
+            .with_vendor("Acme")
+            .with_product("fw")
+            .with_version("1.0")
+    }
+
+    #[test]
+    fn test_header_escaping() {
+        let r = rec(vec![DataField::from_chars("sig", "a|b\\c\nd")]);
+        let f = cef().with_signature_id(HeaderSource::field("sig"));
+        assert_eq!(
+            f.format_record(&r),
+            "CEF:0|Acme|fw|1.0|a\\|b\\\\c d|event|5|"
+        );
+    }
+
+    #[test]
+    fn test_extension_escaping() {
+        let f = cef();
+        assert_eq!(f.format_string("a=b\\c\nd|e"), "a\\=b\\\\c\\nd|e");
+    }
+
+    #[test]
+    fn test_header_fields_excluded_from_extension() {
+        let r = rec(vec![
+            DataField::from_chars("rule", "R-1"),
+            DataField::from_digit("level", 8),
+            DataField::from_chars("user", "bob"),
+        ]);
+        let f = cef()
+            .with_name(HeaderSource::field("rule"))
+            .with_severity(HeaderSource::field("level"));
+        assert_eq!(f.format_record(&r), "CEF:0|Acme|fw|1.0|0|R-1|8|user=bob");
+    }
+
+    #[test]
+    fn test_time_and_keys() {
+        let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+        let mut obj = ObjectValue::new();
+        obj.insert("port", DataField::from_digit("port", 80));
+        let r = rec(vec![
+            DataField::from_time("time", ts),
+            DataField::from_obj("dst", obj),
+            DataField::from_digit("http/status", 200),
+        ]);
+        assert_eq!(
+            cef().format_record(&r),
+            "CEF:0|Acme|fw|1.0|0|event|5|rt=1565093539000 dst_port=80 http_status=200"
+        );
+    }
+}
@@ -1,11 +1,14 @@
-use crate::formatter::DataFormat;
-use std::fmt::Write;
+use crate::formatter::{DataFormat, StreamDataFormat, render};
+use crate::parse::{Cursor, DataParse, Node, ParseError, ParseHints, build_record};
+use crate::time_fmt::TimeFormat;
+use std::fmt::{self, Write};
 use wp_model_core::model::{DataField, DataRecord, DataType, types::value::ObjectValue};
 
 pub struct Csv {
     delimiter: char,
     quote_char: char,
     escape_char: char,
+    time_format: TimeFormat,
 }
 
 impl Default for Csv {
@@ -14,6 +17,7 @@ impl Default for Csv {
             delimiter: ',',
             quote_char: '"',
             escape_char: '"',
+            time_format: TimeFormat::default(),
         }
     }
 }
@@ -34,38 +38,122 @@ impl Csv {
         self.escape_char = escape_char;
         self
     }
+    pub fn with_time_format(mut self, time_format: impl Into<TimeFormat>) -> Self {
+        self.time_format = time_format.into();
+        self
+    }
 
-    fn escape_string(&self, value: &str, output: &mut String) {
+    fn write_record<'a>(
+        &self,
+        fields: impl Iterator<Item = &'a DataField>,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        let mut first = true;
+        for field in fields.filter(|f| *f.get_meta() != DataType::Ignore) {
+            if !first {
+                out.write_char(self.delimiter)?;
+            }
+            first = false;
+            self.format_field_into(field, out)?;
+        }
+        Ok(())
+    }
+
+    fn escape_string(&self, value: &str, output: &mut dyn Write) -> fmt::Result {
         let needs_quoting = value.contains(self.delimiter)
             || value.contains('\n')
             || value.contains('\r')
-            || value.contains(self.quote_char);
+            || value.contains(self.quote_char)
+            || value.contains(self.escape_char);
         if needs_quoting {
-            output.push(self.quote_char);
+            output.write_char(self.quote_char)?;
             for c in value.chars() {
-                if c == self.quote_char {
-                    output.push(self.escape_char);
+                // a distinct escape char escapes itself too, so a trailing
+                // one cannot swallow the closing quote
+                if c == self.quote_char || c == self.escape_char {
+                    output.write_char(self.escape_char)?;
                 }
-                output.push(c);
+                output.write_char(c)?;
             }
-            output.push(self.quote_char);
+            output.write_char(self.quote_char)
         } else {
-            output.push_str(value);
+            output.write_str(value)
+        }
+    }
+}
+
+impl StreamDataFormat for Csv {
+    fn format_null_into(&self, _out: &mut dyn Write) -> fmt::Result {
+        Ok(())
+    }
+    fn format_bool_into(&self, value: &bool, out: &mut dyn Write) -> fmt::Result {
+        out.write_str(if *value { "true" } else { "false" })
+    }
+    fn format_string_into(&self, value: &str, out: &mut dyn Write) -> fmt::Result {
+        self.escape_string(value, out)
+    }
+    fn format_i64_into(&self, value: &i64, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", value)
+    }
+    fn format_f64_into(&self, value: &f64, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", value)
+    }
+    fn format_ip_into(&self, value: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result {
+        self.escape_string(&value.to_string(), out)
+    }
+    fn format_datetime_into(
+        &self,
+        value: &chrono::NaiveDateTime,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        self.escape_string(&self.time_format.render(value), out)
+    }
+    fn format_object_into(&self, value: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        for (i, (k, v)) in value.iter().enumerate() {
+            if i > 0 {
+                out.write_str(", ")?;
+            }
+            write!(out, "{}:", k)?;
+            self.fmt_value_into(v.get_value(), out)?;
+        }
+        Ok(())
+    }
+    fn format_array_into(&self, value: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        // Quoting depends on the joined content, so the items are staged first.
+        let mut joined = String::new();
+        for (i, f) in value.iter().enumerate() {
+            if i > 0 {
+                joined.push_str(", ");
+            }
+            self.format_field_into(f, &mut joined)?;
         }
+        self.escape_string(&joined, out)
+    }
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        self.fmt_value_into(field.get_value(), out)
+    }
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        self.write_record(record.items.iter(), out)
+    }
+    fn format_fields_into(
+        &self,
+        fields: &[(&str, &DataField)],
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        self.write_record(fields.iter().map(|(_, f)| *f), out)
     }
 }
+
 impl DataFormat for Csv {
     type Output = String;
     fn format_null(&self) -> String {
         "".to_string()
     }
     fn format_bool(&self, value: &bool) -> String {
-        if *value { "true" } else { "false" }.to_string()
+        render(|out| self.format_bool_into(value, out))
     }
     fn format_string(&self, value: &str) -> String {
-        let mut o = String::new();
-        self.escape_string(value, &mut o);
-        o
+        render(|out| self.format_string_into(value, out))
     }
     fn format_i64(&self, value: &i64) -> String {
         value.to_string()
@@ -74,51 +162,175 @@ impl DataFormat for Csv {
         value.to_string()
     }
     fn format_ip(&self, value: &std::net::IpAddr) -> String {
-        self.format_string(&value.to_string())
+        render(|out| self.format_ip_into(value, out))
     }
     fn format_datetime(&self, value: &chrono::NaiveDateTime) -> String {
-        self.format_string(&value.to_string())
+        render(|out| self.format_datetime_into(value, out))
     }
     fn format_object(&self, value: &ObjectValue) -> String {
-        let mut output = String::new();
-        for (i, (k, v)) in value.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            write!(output, "{}:{}", k, self.fmt_value(v.get_value())).unwrap();
-        }
-        output
+        render(|out| self.format_object_into(value, out))
     }
     fn format_array(&self, value: &[DataField]) -> String {
-        let mut output = String::new();
-        self.escape_string(
-            &value
-                .iter()
-                .map(|f| self.format_field(f))
-                .collect::<Vec<_>>()
-                .join(", "),
-            &mut output,
-        );
-        output
+        render(|out| self.format_array_into(value, out))
     }
     fn format_field(&self, field: &DataField) -> String {
-        self.fmt_value(field.get_value())
+        render(|out| self.format_field_into(field, out))
     }
     fn format_record(&self, record: &DataRecord) -> String {
-        let mut output = String::new();
-        let mut first = true;
-        for field in record
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-        {
-            if !first {
-                output.push(self.delimiter);
+        render(|out| self.format_record_into(record, out))
+    }
+    fn format_fields(&self, fields: &[(&str, &DataField)]) -> String {
+        render(|out| self.format_fields_into(fields, out))
+    }
+}
+
+impl DataParse for Csv {
+    /// Reads one line; names come from [`ParseHints::with_columns`] and the
+    /// value count must match. Quoted values read as text, unquoted ones
+    /// are inferred, and empty ones read as `Null`. Objects and arrays are
+    /// flattened by the formatter and come back as text.
+    fn parse_record_with(&self, input: &str, hints: &ParseHints) -> Result<DataRecord, ParseError> {
+        let input = input.strip_suffix('\n').unwrap_or(input);
+        let input = input.strip_suffix('\r').unwrap_or(input);
+        let mut cur = Cursor::new(input);
+        let mut values = Vec::new();
+        loop {
+            values.push(self.read_value(&mut cur)?);
+            if cur.is_eof() {
+                break;
             }
-            first = false;
-            output.push_str(&self.format_field(field));
+            if cur.bump() != Some(self.delimiter) {
+                return Err(ParseError::at(cur.pos(), "expected a delimiter"));
+            }
+        }
+        let columns = hints.columns();
+        if values.len() != columns.len() {
+            return Err(ParseError::value(format!(
+                "{} values for {} columns",
+                values.len(),
+                columns.len()
+            )));
+        }
+        build_record(
+            columns.iter().cloned().zip(values).collect(),
+            hints,
+            &self.time_format,
+        )
+    }
+}
+
+impl Csv {
+    fn read_value(&self, cur: &mut Cursor) -> Result<Node, ParseError> {
+        if cur.peek() != Some(self.quote_char) {
+            let delimiter = self.delimiter;
+            return Ok(Node::Bare(cur.take_until(|c| c == delimiter).to_string()));
         }
-        output
+        cur.bump();
+        let mut s = String::new();
+        loop {
+            match cur.bump() {
+                Some(c)
+                    if c == self.escape_char
+                        && (cur.peek() == Some(self.quote_char)
+                            || cur.peek() == Some(self.escape_char)) =>
+                {
+                    s.extend(cur.bump());
+                }
+                Some(c) if c == self.quote_char => return Ok(Node::Str(s)),
+                Some(c) => s.push(c),
+                None => return Err(cur.error("unterminated quoted value")),
+            }
+        }
+    }
+}
+
+/// Schema-stable CSV output: emits a header line once, then one line per
+/// record with fields reordered to match the header.
+///
+/// The column set comes from [`CsvWriter::with_columns`] or, if none was
+/// given, is locked from the first record written. Fields missing from a
+/// record are written as [`Csv::format_null`]; fields not in the column set
+/// are dropped.
+pub struct CsvWriter {
+    csv: Csv,
+    columns: Option<Vec<String>>,
+    with_header: bool,
+    header_written: bool,
+}
+
+impl CsvWriter {
+    pub fn new(csv: Csv) -> Self {
+        Self {
+            csv,
+            columns: None,
+            with_header: true,
+            header_written: false,
+        }
+    }
+    pub fn with_columns<I, S>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.columns = Some(columns.into_iter().map(Into::into).collect());
+        self
+    }
+    pub fn with_header(mut self, with_header: bool) -> Self {
+        self.with_header = with_header;
+        self
+    }
+
+    /// The locked column set, if a schema was supplied or a record written.
+    pub fn columns(&self) -> Option<&[String]> {
+        self.columns.as_deref()
+    }
+
+    /// Makes the next write emit the header again (e.g. after a file
+    /// rotation); the column set stays locked.
+    pub fn reset(&mut self) {
+        self.header_written = false;
+    }
+
+    pub fn write_record(&mut self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        let columns = self.columns.get_or_insert_with(|| {
+            record
+                .items
+                .iter()
+                .filter(|f| *f.get_meta() != DataType::Ignore)
+                .map(|f| f.get_name().to_string())
+                .collect()
+        });
+        if self.with_header && !self.header_written {
+            for (i, name) in columns.iter().enumerate() {
+                if i > 0 {
+                    out.write_char(self.csv.delimiter)?;
+                }
+                self.csv.escape_string(name, out)?;
+            }
+            out.write_char('\n')?;
+            self.header_written = true;
+        }
+        for (i, name) in columns.iter().enumerate() {
+            if i > 0 {
+                out.write_char(self.csv.delimiter)?;
+            }
+            match record
+                .items
+                .iter()
+                .find(|f| f.get_name() == name && *f.get_meta() != DataType::Ignore)
+            {
+                Some(field) => self.csv.format_field_into(field, out)?,
+                None => self.csv.format_null_into(out)?,
+            }
+        }
+        out.write_char('\n')
+    }
+
+    /// Formats one record as CSV lines, prefixed by the header on first use.
+    pub fn format_record(&mut self, record: &DataRecord) -> String {
+        let mut out = String::new();
+        let _ = self.write_record(record, &mut out);
+        out
     }
 }
 
@@ -236,6 +448,7 @@ mod tests {
     fn test_format_record() {
         let csv = Csv::default();
         let record = DataRecord {
+            id: 0,
             items: vec![
                 DataField::from_chars("name", "Alice"),
                 DataField::from_digit("age", 30),
@@ -249,6 +462,7 @@ mod tests {
     fn test_format_record_with_custom_delimiter() {
         let csv = Csv::new().with_delimiter(';');
         let record = DataRecord {
+            id: 0,
             items: vec![
                 DataField::from_chars("a", "x"),
                 DataField::from_chars("b", "y"),
@@ -258,10 +472,54 @@ mod tests {
         assert_eq!(result, "x;y");
     }
 
+    fn rec(items: Vec<DataField>) -> DataRecord {
+        DataRecord { id: 0, items }
+    }
+
+    #[test]
+    fn test_writer_header_once_and_locked_columns() {
+        let mut w = CsvWriter::new(Csv::new());
+        let first = w.format_record(&rec(vec![
+            DataField::from_chars("a", "x"),
+            DataField::from_digit("b", 1),
+        ]));
+        assert_eq!(first, "a,b\nx,1\n");
+        // reordered, missing and extra fields keep the locked layout
+        let second = w.format_record(&rec(vec![
+            DataField::from_digit("b", 2),
+            DataField::from_chars("c", "dropped"),
+        ]));
+        assert_eq!(second, ",2\n");
+        assert_eq!(w.columns(), Some(&["a".to_string(), "b".to_string()][..]));
+    }
+
+    #[test]
+    fn test_writer_with_schema_and_no_header() {
+        let mut w = CsvWriter::new(Csv::new().with_delimiter(';'))
+            .with_columns(["b", "a;b"])
+            .with_header(false);
+        let out = w.format_record(&rec(vec![
+            DataField::from_chars("a;b", "v"),
+            DataField::from_chars("b", "w"),
+        ]));
+        assert_eq!(out, "w;v\n");
+    }
+
+    #[test]
+    fn test_writer_reset_repeats_header() {
+        let mut w = CsvWriter::new(Csv::new()).with_columns(["a,b"]);
+        let r = rec(vec![DataField::from_digit("a,b", 1)]);
+        assert_eq!(w.format_record(&r), "\"a,b\"\n1\n");
+        assert_eq!(w.format_record(&r), "1\n");
+        w.reset();
+        assert_eq!(w.format_record(&r), "\"a,b\"\n1\n");
+    }
+
     #[test]
     fn test_format_record_with_special_chars() {
         let csv = Csv::default();
         let record = DataRecord {
+            id: 0,
             items: vec![
                 DataField::from_chars("msg", "hello,world"),
                 DataField::from_digit("count", 5),
@@ -270,4 +528,33 @@ mod tests {
         let result = csv.format_record(&record);
         assert!(result.contains("\"hello,world\""));
     }
+
+    #[test]
+    fn test_csv_parse_quoting() {
+        use crate::parse::{DataParse, ParseHints};
+        let hints = ParseHints::new().with_columns(["a", "b", "c"]);
+        let r = Csv::new()
+            .parse_record_with("\"x,\"\"y\"\"\",,12\r\n", &hints)
+            .unwrap();
+        assert_eq!(r.items[0], DataField::from_chars("a", "x,\"y\""));
+        assert_eq!(*r.items[1].get_value(), wp_model_core::model::Value::Null);
+        assert_eq!(r.items[2], DataField::from_digit("c", 12));
+        assert!(Csv::new().parse_record_with("\"open,1,2", &hints).is_err());
+    }
+
+    #[test]
+    fn test_csv_backslash_escape_round_trip() {
+        use crate::parse::{DataParse, ParseHints};
+        let csv = Csv::new().with_escape_char('\\');
+        let hints = ParseHints::new().with_columns(["p", "n"]);
+        for v in ["C:\\temp\\", "a\\\"b", "\\", "end\\"] {
+            let r = DataRecord {
+                id: 0,
+                items: vec![DataField::from_chars("p", v), DataField::from_digit("n", 1)],
+            };
+            let out = csv.format_record(&r);
+            assert_eq!(csv.parse_record_with(&out, &hints).unwrap(), r, "{}", out);
+        }
+        assert_eq!(csv.format_string("a\\b"), r#""a\\b""#);
+    }
 }
@@ -0,0 +1,370 @@
+use crate::formatter::{DataFormat, StreamDataFormat, render};
+use crate::json::{Json, write_json_string};
+use chrono::{NaiveDateTime, Utc};
+use std::fmt::{self, Write};
+use wp_model_core::model::{DataField, DataRecord, DataType, Value, types::value::ObjectValue};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BulkAction {
+    /// Creates or replaces the document.
+    #[default]
+    Index,
+    /// Fails for an existing `_id`; required by data streams.
+    Create,
+}
+
+/// Elasticsearch / OpenSearch `_bulk` body: for every record an action line
+/// (`{"index":{"_index":..,"_id":..}}`) followed by the JSON document, each
+/// terminated by `\n`.
+///
+/// The index name is a strftime template (`logs-%Y.%m.%d`) filled from the
+/// record's time field, or from [`EsBulk::with_clock`] when the record has
+/// none, and lowercased as Elasticsearch requires.
+#[derive(Debug, Clone)]
+pub struct EsBulk {
+    action: BulkAction,
+    index: String,
+    time_field: Option<String>,
+    id_field: Option<String>,
+    max_bytes: Option<usize>,
+    max_docs: Option<usize>,
+    clock: Option<fn() -> NaiveDateTime>,
+}
+
+fn utc_now() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+impl EsBulk {
+    pub fn new(index: impl Into<String>) -> Self {
+        Self {
+            action: BulkAction::Index,
+            index: index.into(),
+            time_field: Some("time".to_string()),
+            id_field: None,
+            max_bytes: None,
+            max_docs: None,
+            clock: Some(utc_now),
+        }
+    }
+    pub fn with_action(mut self, action: BulkAction) -> Self {
+        self.action = action;
+        self
+    }
+    /// Field whose `Time` value fills the index template.
+    pub fn with_time_field(mut self, name: impl Into<String>) -> Self {
+        self.time_field = Some(name.into());
+        self
+    }
+    /// Field used as the document `_id`; without it Elasticsearch assigns one.
+    pub fn with_id_field(mut self, name: impl Into<String>) -> Self {
+        self.id_field = Some(name.into());
+        self
+    }
+    /// Upper bound on the size of one bulk body produced by
+    /// [`EsBulk::format_batch`]. A single document larger than the limit is
+    /// still sent, alone.
+    pub fn with_max_bytes(mut self, max: usize) -> Self {
+        self.max_bytes = Some(max);
+        self
+    }
+    pub fn with_max_docs(mut self, max: usize) -> Self {
+        self.max_docs = Some(max.max(1));
+        self
+    }
+    /// Time that fills the index template for a record without one; the
+    /// current UTC time by default. With `None` such a record is an error.
+    pub fn with_clock(mut self, clock: Option<fn() -> NaiveDateTime>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Formats records into bulk bodies that respect the configured byte and
+    /// document limits; each body can be posted to `_bulk` as-is. Fails on
+    /// the first record that cannot be formatted.
+    pub fn format_batch(&self, records: &[DataRecord]) -> Result<Vec<String>, fmt::Error> {
+        let mut bodies = Vec::new();
+        let mut body = String::new();
+        let mut docs = 0;
+        let mut entry = String::new();
+        for record in records {
+            entry.clear();
+            self.format_record_into(record, &mut entry)?;
+            let over_docs = self.max_docs.is_some_and(|max| docs >= max);
+            let over_bytes = self
+                .max_bytes
+                .is_some_and(|max| body.len() + entry.len() > max);
+            if docs > 0 && (over_docs || over_bytes) {
+                bodies.push(std::mem::take(&mut body));
+                docs = 0;
+            }
+            body.push_str(&entry);
+            docs += 1;
+        }
+        if docs > 0 {
+            bodies.push(body);
+        }
+        Ok(bodies)
+    }
+
+    /// Writes every record into one bulk body, ignoring the size limits.
+    pub fn format_batch_into(&self, records: &[DataRecord], out: &mut dyn Write) -> fmt::Result {
+        for record in records {
+            self.format_record_into(record, out)?;
+        }
+        Ok(())
+    }
+
+    fn lookup<'a>(&self, record: &'a DataRecord, name: &Option<String>) -> Option<&'a Value> {
+        let name = name.as_deref()?;
+        record
+            .items
+            .iter()
+            .find(|f| f.get_name() == name)
+            .map(|f| f.get_value())
+    }
+
+    fn index_name(&self, record: &DataRecord) -> Result<String, fmt::Error> {
+        if !self.index.contains('%') {
+            return Ok(self.index.clone());
+        }
+        let time = match (self.lookup(record, &self.time_field), self.clock) {
+            (Some(Value::Time(t)), _) => *t,
+            (_, Some(clock)) => clock(),
+            (_, None) => return Err(fmt::Error),
+        };
+        let mut name = String::new();
+        // an invalid specifier makes chrono's Display fail; keep the raw template
+        if write!(name, "{}", time.format(&self.index)).is_err() {
+            return Ok(self.index.to_lowercase());
+        }
+        Ok(name.to_lowercase())
+    }
+
+    fn write_action(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        out.write_str(match self.action {
+            BulkAction::Index => "{\"index\":{\"_index\":",
+            BulkAction::Create => "{\"create\":{\"_index\":",
+        })?;
+        write_json_string(out, &self.index_name(record)?)?;
+        match self.lookup(record, &self.id_field) {
+            None | Some(Value::Null) => {}
+            Some(Value::Chars(id)) => {
+                out.write_str(",\"_id\":")?;
+                write_json_string(out, id)?;
+            }
+            Some(id) => {
+                out.write_str(",\"_id\":")?;
+                write_json_string(out, &id.to_string())?;
+            }
+        }
+        out.write_str("}}\n")
+    }
+
+    fn write_document(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        out.write_char('{')?;
+        let mut first = true;
+        for field in record
+            .items
+            .iter()
+            .filter(|f| *f.get_meta() != DataType::Ignore)
+        {
+            if !first {
+                out.write_char(',')?;
+            }
+            first = false;
+            self.format_field_into(field, out)?;
+        }
+        out.write_str("}\n")
+    }
+}
+
+/// Values are rendered by [`Json`]; a record becomes the action line plus
+/// the document line.
+impl StreamDataFormat for EsBulk {
+    fn format_null_into(&self, out: &mut dyn Write) -> fmt::Result {
+        Json::new().format_null_into(out)
+    }
+    fn format_bool_into(&self, v: &bool, out: &mut dyn Write) -> fmt::Result {
+        Json::new().format_bool_into(v, out)
+    }
+    fn format_string_into(&self, v: &str, out: &mut dyn Write) -> fmt::Result {
+        Json::new().format_string_into(v, out)
+    }
+    fn format_i64_into(&self, v: &i64, out: &mut dyn Write) -> fmt::Result {
+        Json::new().format_i64_into(v, out)
+    }
+    fn format_f64_into(&self, v: &f64, out: &mut dyn Write) -> fmt::Result {
+        Json::new().format_f64_into(v, out)
+    }
+    fn format_ip_into(&self, v: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result {
+        Json::new().format_ip_into(v, out)
+    }
+    fn format_datetime_into(&self, v: &NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+        Json::new().format_datetime_into(v, out)
+    }
+    fn format_object_into(&self, value: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        Json::new().format_object_into(value, out)
+    }
+    fn format_array_into(&self, value: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        Json::new().format_array_into(value, out)
+    }
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        write_json_string(out, field.get_name())?;
+        out.write_char(':')?;
+        self.fmt_value_into(field.get_value(), out)
+    }
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        self.write_action(record, out)?;
+        self.write_document(record, out)
+    }
+}
+
+impl DataFormat for EsBulk {
+    type Output = String;
+    fn format_null(&self) -> String {
+        render(|out| self.format_null_into(out))
+    }
+    fn format_bool(&self, v: &bool) -> String {
+        render(|out| self.format_bool_into(v, out))
+    }
+    fn format_string(&self, v: &str) -> String {
+        render(|out| self.format_string_into(v, out))
+    }
+    fn format_i64(&self, v: &i64) -> String {
+        render(|out| self.format_i64_into(v, out))
+    }
+    fn format_f64(&self, v: &f64) -> String {
+        render(|out| self.format_f64_into(v, out))
+    }
+    fn format_ip(&self, v: &std::net::IpAddr) -> String {
+        render(|out| self.format_ip_into(v, out))
+    }
+    fn format_datetime(&self, v: &NaiveDateTime) -> String {
+        render(|out| self.format_datetime_into(v, out))
+    }
+    fn format_object(&self, value: &ObjectValue) -> String {
+        render(|out| self.format_object_into(value, out))
+    }
+    fn format_array(&self, value: &[DataField]) -> String {
+        render(|out| self.format_array_into(value, out))
+    }
+    fn format_field(&self, field: &DataField) -> String {
+        render(|out| self.format_field_into(field, out))
+    }
+    fn format_record(&self, record: &DataRecord) -> String {
+        render(|out| self.format_record_into(record, out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn rec(n: i64) -> DataRecord {
+        DataRecord {
+            id: 0,
+            items: vec![
+                DataField::from_time("time", ts("2019-08-06 12:12:19")),
+                DataField::from_digit("n", n),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_record_lines() {
+        let f = EsBulk::new("logs-%Y.%m.%d").with_id_field("n");
+        assert_eq!(
+            f.format_record(&rec(7)),
+            "{\"index\":{\"_index\":\"logs-2019.08.06\",\"_id\":\"7\"}}\n\
+             {\"time\":\"2019-08-06 12:12:19\",\"n\":7}\n"
+        );
+    }
+
+    #[test]
+    fn test_create_and_static_index() {
+        let f = EsBulk::new("Logs").with_action(BulkAction::Create);
+        assert!(
+            f.format_record(&rec(1))
+                .starts_with("{\"create\":{\"_index\":\"Logs\"}}\n")
+        );
+        let f = EsBulk::new("logs-%b");
+        assert!(f.format_record(&rec(1)).contains("\"logs-aug\""));
+        let f = EsBulk::new("logs-%Q");
+        assert!(f.format_record(&rec(1)).contains("\"logs-%q\""));
+    }
+
+    #[test]
+    fn test_document_escapes_names() {
+        let r = DataRecord {
+            id: 0,
+            items: vec![
+                DataField::from_chars("a\"b", "x"),
+                DataField::from_ignore("skip"),
+            ],
+        };
+        let out = EsBulk::new("i").format_record(&r);
+        assert_eq!(out.lines().nth(1), Some(r#"{"a\"b":"x"}"#));
+    }
+
+    #[test]
+    fn test_split_by_docs() {
+        let records: Vec<_> = (0..5).map(rec).collect();
+        let bodies = EsBulk::new("i")
+            .with_max_docs(2)
+            .format_batch(&records)
+            .unwrap();
+        assert_eq!(bodies.len(), 3);
+        assert_eq!(bodies[0].lines().count(), 4);
+        assert_eq!(bodies[2].lines().count(), 2);
+        let all = EsBulk::new("i").format_batch(&records).unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(
+            all[0],
+            render(|w| EsBulk::new("i").format_batch_into(&records, w))
+        );
+    }
+
+    #[test]
+    fn test_split_by_bytes() {
+        let records: Vec<_> = (0..4).map(rec).collect();
+        let one = EsBulk::new("i").format_record(&records[0]).len();
+        let bodies = EsBulk::new("i")
+            .with_max_bytes(one * 2 + 1)
+            .format_batch(&records)
+            .unwrap();
+        assert_eq!(bodies.len(), 2);
+        assert!(bodies.iter().all(|b| b.len() <= one * 2 + 1));
+
+        let tiny = EsBulk::new("i")
+            .with_max_bytes(1)
+            .format_batch(&records)
+            .unwrap();
+        assert_eq!(tiny.len(), 4);
+        assert!(EsBulk::new("i").format_batch(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_missing_time_uses_the_clock() {
+        let untimed = DataRecord {
+            id: 0,
+            items: vec![DataField::from_digit("n", 1)],
+        };
+        let f = EsBulk::new("logs-%Y.%m.%d").with_clock(Some(|| ts("2020-01-02 00:00:00")));
+        assert!(f.format_record(&untimed).contains("\"logs-2020.01.02\""));
+        let strict = EsBulk::new("logs-%Y.%m.%d").with_clock(None);
+        assert!(strict.format_batch(&[rec(1), untimed.clone()]).is_err());
+        assert!(strict.format_batch(&[rec(1)]).is_ok());
+        // a static index needs no time
+        assert!(
+            EsBulk::new("logs")
+                .with_clock(None)
+                .format_batch(&[untimed])
+                .is_ok()
+        );
+    }
+}
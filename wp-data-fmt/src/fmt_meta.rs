@@ -1,8 +1,14 @@
+use crate::{TimestampFormat, TimestampParser};
 use chrono::NaiveDateTime;
+use std::sync::LazyLock;
 
+/// Parses `%Y-%m-%d %H:%M:%S` only; kept for existing callers. Other layouts
+/// and offsets need a [`TimestampParser`].
 pub fn date_from(s: &str) -> Option<NaiveDateTime> {
-    // naive parser; keep parity with original helper
-    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").ok()
+    static PARSER: LazyLock<TimestampParser> = LazyLock::new(|| {
+        TimestampParser::new(vec![TimestampFormat::Custom("%Y-%m-%d %H:%M:%S".into())])
+    });
+    PARSER.parse(s).map(|t| t.utc)
 }
 
 #[cfg(test)]
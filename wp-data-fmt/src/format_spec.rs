@@ -0,0 +1,600 @@
+use crate::time_fmt::{TimeFormat, TimeStyle};
+use crate::{
+    Csv, FormatType, Json, KeyValue, LineEnding, Logfmt, Nesting, ProtoBin, ProtoSchema, ProtoTxt,
+    Raw, SqlDialect, SqlFormat, SqlInsert, Table, TableStyle, Template,
+};
+use chrono::FixedOffset;
+use chrono::format::{Item, StrftimeItems};
+use serde::{Deserialize, Serialize};
+use wp_model_core::model::fmt_def::TextFmt;
+
+fn default_delimiter() -> char {
+    ','
+}
+fn default_quote() -> char {
+    '"'
+}
+fn default_pair_separator() -> String {
+    ", ".to_string()
+}
+fn default_key_value_separator() -> String {
+    ": ".to_string()
+}
+fn default_key_separator() -> String {
+    ".".to_string()
+}
+fn default_true() -> bool {
+    true
+}
+fn default_indent() -> usize {
+    2
+}
+fn default_max_width() -> usize {
+    40
+}
+
+/// Declarative output format, as written in sink configs. Every option
+/// defaults to what [`FormatType::from`] a [`TextFmt`] builds.
+///
+/// ```toml
+/// format = { type = "csv", delimiter = ";" }
+/// format = { type = "sql", table = "access_log", dialect = "mysql" }
+/// format = { type = "json", time = "rfc3339", time_offset = "+08:00" }
+/// format = { type = "json", indent = 2, key_value_space = true }
+/// format = { type = "json", nest = { separator = "/", conflict = "first_wins" } }
+/// format = { type = "template", pattern = '{ip} - - [{time:%d/%b/%Y:%H:%M:%S %z}]' }
+/// ```
+///
+/// `time` is `naive`, `rfc3339`, `epoch_secs`, `epoch_millis`,
+/// `epoch_nanos` or `{ strftime = "%d/%b/%Y" }`; `time_offset` is the zone
+/// the parsed times were recorded in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum FormatSpec {
+    Json {
+        /// Spaces per level; unset writes single-line output.
+        #[serde(default)]
+        indent: Option<usize>,
+        #[serde(default)]
+        key_value_space: bool,
+        #[serde(default)]
+        line_ending: LineEnding,
+        #[serde(default)]
+        time: TimeStyle,
+        #[serde(default)]
+        time_offset: Option<String>,
+        /// Expands path-style field names into nested objects.
+        #[serde(default)]
+        nest: Option<Nesting>,
+    },
+    Csv {
+        #[serde(default = "default_delimiter")]
+        delimiter: char,
+        #[serde(default = "default_quote")]
+        quote_char: char,
+        #[serde(default = "default_quote")]
+        escape_char: char,
+        #[serde(default)]
+        time: TimeStyle,
+        #[serde(default)]
+        time_offset: Option<String>,
+    },
+    Kv {
+        #[serde(default = "default_pair_separator")]
+        pair_separator: String,
+        #[serde(default = "default_key_value_separator")]
+        key_value_separator: String,
+        #[serde(default = "default_true")]
+        quote_strings: bool,
+        #[serde(default)]
+        time: TimeStyle,
+        #[serde(default)]
+        time_offset: Option<String>,
+    },
+    Logfmt {
+        #[serde(default = "default_key_separator")]
+        key_separator: String,
+        #[serde(default)]
+        time: TimeStyle,
+        #[serde(default)]
+        time_offset: Option<String>,
+    },
+    Sql {
+        table: String,
+        #[serde(default)]
+        dialect: SqlDialect,
+        #[serde(default = "default_true")]
+        quote_identifiers: bool,
+        /// Rendering of object and array columns.
+        #[serde(default = "default_object_format")]
+        object_format: TextFmt,
+        #[serde(default)]
+        time: TimeStyle,
+        #[serde(default)]
+        time_offset: Option<String>,
+    },
+    Raw {
+        #[serde(default)]
+        time: TimeStyle,
+        #[serde(default)]
+        time_offset: Option<String>,
+    },
+    #[serde(alias = "proto-text")]
+    ProtoText {
+        #[serde(default)]
+        multiline: bool,
+        #[serde(default = "default_indent")]
+        indent: usize,
+        #[serde(default)]
+        sanitize_names: bool,
+        #[serde(default)]
+        time: TimeStyle,
+        #[serde(default)]
+        time_offset: Option<String>,
+    },
+    /// Preview output; the format [`TextFmt::Show`] builds.
+    Table {
+        #[serde(default)]
+        style: TableStyle,
+        /// Longest value shown, in graphemes; 0 shows values in full.
+        #[serde(default = "default_max_width")]
+        max_width: usize,
+        #[serde(default)]
+        time: TimeStyle,
+        #[serde(default)]
+        time_offset: Option<String>,
+    },
+    /// See [`Template`] for the pattern syntax.
+    Template {
+        pattern: String,
+        #[serde(default)]
+        time: TimeStyle,
+        #[serde(default)]
+        time_offset: Option<String>,
+    },
+    Proto {
+        #[serde(default)]
+        delimited: bool,
+        /// Field numbers; derived from the first record when unset.
+        #[serde(default)]
+        schema: Option<ProtoSchema>,
+    },
+}
+
+fn default_object_format() -> TextFmt {
+    TextFmt::Json
+}
+
+impl From<&TextFmt> for FormatSpec {
+    fn from(fmt: &TextFmt) -> Self {
+        let time = TimeStyle::default();
+        let time_offset = None;
+        match fmt {
+            TextFmt::Json => FormatSpec::Json {
+                indent: None,
+                key_value_space: false,
+                line_ending: LineEnding::default(),
+                time,
+                time_offset,
+                nest: None,
+            },
+            TextFmt::Csv => FormatSpec::Csv {
+                delimiter: default_delimiter(),
+                quote_char: default_quote(),
+                escape_char: default_quote(),
+                time,
+                time_offset,
+            },
+            TextFmt::Kv => FormatSpec::Kv {
+                pair_separator: default_pair_separator(),
+                key_value_separator: default_key_value_separator(),
+                quote_strings: true,
+                time,
+                time_offset,
+            },
+            TextFmt::Raw => FormatSpec::Raw { time, time_offset },
+            TextFmt::Show => FormatSpec::Table {
+                style: TableStyle::default(),
+                max_width: default_max_width(),
+                time,
+                time_offset,
+            },
+            TextFmt::ProtoText => FormatSpec::ProtoText {
+                multiline: false,
+                indent: default_indent(),
+                sanitize_names: false,
+                time,
+                time_offset,
+            },
+            TextFmt::Proto => FormatSpec::Proto {
+                delimited: false,
+                schema: None,
+            },
+        }
+    }
+}
+
+impl FormatSpec {
+    /// Checks the options without building anything.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            FormatSpec::Json {
+                time, time_offset, ..
+            }
+            | FormatSpec::Raw { time, time_offset }
+            | FormatSpec::ProtoText {
+                time, time_offset, ..
+            }
+            | FormatSpec::Table {
+                time, time_offset, ..
+            } => time_format(time, time_offset).map(|_| ()),
+            FormatSpec::Csv {
+                delimiter,
+                quote_char,
+                escape_char,
+                time,
+                time_offset,
+            } => {
+                if delimiter == quote_char {
+                    return Err(format!(
+                        "csv delimiter and quote char are both '{}'",
+                        delimiter
+                    ));
+                }
+                if escape_char == delimiter {
+                    return Err(format!(
+                        "csv delimiter and escape char are both '{}'",
+                        delimiter
+                    ));
+                }
+                for (what, c) in [
+                    ("delimiter", delimiter),
+                    ("quote char", quote_char),
+                    ("escape char", escape_char),
+                ] {
+                    if *c == '\n' || *c == '\r' {
+                        return Err(format!("csv {} cannot be a line break", what));
+                    }
+                }
+                time_format(time, time_offset).map(|_| ())
+            }
+            FormatSpec::Kv {
+                pair_separator,
+                key_value_separator,
+                time,
+                time_offset,
+                ..
+            } => {
+                if pair_separator.is_empty() || key_value_separator.is_empty() {
+                    return Err("kv separators cannot be empty".to_string());
+                }
+                if pair_separator == key_value_separator {
+                    return Err(format!(
+                        "kv pair and key-value separators are both '{}'",
+                        pair_separator
+                    ));
+                }
+                time_format(time, time_offset).map(|_| ())
+            }
+            FormatSpec::Logfmt {
+                key_separator,
+                time,
+                time_offset,
+            } => {
+                if key_separator.is_empty() {
+                    return Err("logfmt key separator cannot be empty".to_string());
+                }
+                time_format(time, time_offset).map(|_| ())
+            }
+            FormatSpec::Sql {
+                table,
+                object_format,
+                time,
+                time_offset,
+                ..
+            } => {
+                if table.trim().is_empty() {
+                    return Err("sql table name is required".to_string());
+                }
+                if matches!(object_format, TextFmt::Csv | TextFmt::Show | TextFmt::Proto) {
+                    return Err(format!(
+                        "sql object format must be json, kv, raw or proto-text, not {:?}",
+                        object_format
+                    ));
+                }
+                time_format(time, time_offset).map(|_| ())
+            }
+            FormatSpec::Template {
+                pattern,
+                time,
+                time_offset,
+            } => {
+                Template::compile(pattern)?;
+                time_format(time, time_offset).map(|_| ())
+            }
+            FormatSpec::Proto { .. } => Ok(()),
+        }
+    }
+
+    /// The configured formatter, or why the options are invalid.
+    pub fn build(&self) -> Result<FormatType, String> {
+        self.validate()?;
+        self.make()
+    }
+
+    /// Builds without the checks of [`FormatSpec::validate`] that the
+    /// formatters tolerate; a time option or template that cannot be
+    /// compiled is still an error.
+    pub(crate) fn make(&self) -> Result<FormatType, String> {
+        Ok(match self {
+            FormatSpec::Json {
+                indent,
+                key_value_space,
+                line_ending,
+                time,
+                time_offset,
+                nest,
+            } => {
+                let mut json = Json::new()
+                    .with_key_value_space(*key_value_space)
+                    .with_line_ending(*line_ending)
+                    .with_time_format(time_format(time, time_offset)?);
+                if let Some(indent) = indent {
+                    json = json.with_indent(*indent);
+                }
+                if let Some(nest) = nest {
+                    json = json.with_nesting(nest.clone());
+                }
+                FormatType::Json(json)
+            }
+            FormatSpec::Csv {
+                delimiter,
+                quote_char,
+                escape_char,
+                time,
+                time_offset,
+            } => FormatType::Csv(
+                Csv::new()
+                    .with_delimiter(*delimiter)
+                    .with_quote_char(*quote_char)
+                    .with_escape_char(*escape_char)
+                    .with_time_format(time_format(time, time_offset)?),
+            ),
+            FormatSpec::Kv {
+                pair_separator,
+                key_value_separator,
+                quote_strings,
+                time,
+                time_offset,
+            } => FormatType::Kv(
+                KeyValue::new()
+                    .with_pair_separator(pair_separator.as_str())
+                    .with_key_value_separator(key_value_separator.as_str())
+                    .with_quote_strings(*quote_strings)
+                    .with_time_format(time_format(time, time_offset)?),
+            ),
+            FormatSpec::Logfmt {
+                key_separator,
+                time,
+                time_offset,
+            } => FormatType::Logfmt(
+                Logfmt::new()
+                    .with_key_separator(key_separator.as_str())
+                    .with_time_format(time_format(time, time_offset)?),
+            ),
+            FormatSpec::Sql {
+                table,
+                dialect,
+                quote_identifiers,
+                object_format,
+                time,
+                time_offset,
+            } => {
+                let mut sql = SqlInsert::new_with_json(table.as_str()).with_dialect(*dialect);
+                sql.quote_identifiers = *quote_identifiers;
+                sql.obj_formatter = SqlFormat::from(object_format);
+                FormatType::Sql(sql.with_time_format(time_format(time, time_offset)?))
+            }
+            FormatSpec::Raw { time, time_offset } => {
+                FormatType::Raw(Raw::new().with_time_format(time_format(time, time_offset)?))
+            }
+            FormatSpec::ProtoText {
+                multiline,
+                indent,
+                sanitize_names,
+                time,
+                time_offset,
+            } => FormatType::ProtoText(
+                ProtoTxt::new()
+                    .with_multiline(*multiline)
+                    .with_indent(*indent)
+                    .with_sanitize_names(*sanitize_names)
+                    .with_time_format(time_format(time, time_offset)?),
+            ),
+            FormatSpec::Table {
+                style,
+                max_width,
+                time,
+                time_offset,
+            } => FormatType::Table(
+                Table::new()
+                    .with_style(*style)
+                    .with_max_width(*max_width)
+                    .with_time_format(time_format(time, time_offset)?),
+            ),
+            FormatSpec::Template {
+                pattern,
+                time,
+                time_offset,
+            } => FormatType::Template(
+                Template::compile(pattern)?.with_time_format(time_format(time, time_offset)?),
+            ),
+            FormatSpec::Proto { delimited, schema } => {
+                let proto = ProtoBin::new().with_delimited(*delimited);
+                FormatType::Proto(match schema {
+                    Some(schema) => proto.with_schema(schema.clone()),
+                    None => proto,
+                })
+            }
+        })
+    }
+}
+
+fn time_format(style: &TimeStyle, offset: &Option<String>) -> Result<TimeFormat, String> {
+    if let TimeStyle::Strftime(pattern) = style
+        && StrftimeItems::new(pattern).any(|item| matches!(item, Item::Error))
+    {
+        return Err(format!("invalid strftime pattern '{}'", pattern));
+    }
+    let format = TimeFormat::new(style.clone());
+    match offset {
+        None => Ok(format),
+        Some(offset) => offset
+            .parse::<FixedOffset>()
+            .map(|offset| format.with_offset(offset))
+            .map_err(|e| format!("invalid time offset '{}': {}", offset, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataFormat;
+    use wp_model_core::model::{DataField, DataRecord};
+
+    fn record() -> DataRecord {
+        DataRecord {
+            id: 0,
+            items: vec![
+                DataField::from_chars("name", "a;b"),
+                DataField::from_digit("n", 1),
+            ],
+        }
+    }
+
+    fn spec(toml_str: &str) -> Result<FormatSpec, toml::de::Error> {
+        #[derive(Deserialize)]
+        struct Sink {
+            format: FormatSpec,
+        }
+        toml::from_str::<Sink>(toml_str).map(|s| s.format)
+    }
+
+    #[test]
+    fn test_defaults_match_text_fmt() {
+        let spec = spec(r#"format = { type = "kv" }"#).unwrap();
+        assert_eq!(spec, FormatSpec::from(&TextFmt::Kv));
+        assert_eq!(
+            spec.build().unwrap().format_record(&record()),
+            FormatType::from(&TextFmt::Kv).format_record(&record())
+        );
+    }
+
+    #[test]
+    fn test_build_configured() {
+        let csv = spec(r#"format = { type = "csv", delimiter = ";" }"#).unwrap();
+        assert_eq!(csv.build().unwrap().format_record(&record()), "\"a;b\";1");
+        let kv = spec(r#"format = { type = "kv", quote_strings = false, pair_separator = " " }"#)
+            .unwrap();
+        assert_eq!(
+            kv.build().unwrap().format_record(&record()),
+            "name: a;b n: 1"
+        );
+        let sql = spec(r#"format = { type = "sql", table = "t", dialect = "mysql" }"#).unwrap();
+        assert_eq!(
+            sql.build().unwrap().format_record(&record()),
+            "INSERT INTO `t` (`name`, `n`) VALUES ('a;b', 1);"
+        );
+    }
+
+    #[test]
+    fn test_proto_text_and_logfmt_options() {
+        let proto = spec(r#"format = { type = "proto_text" }"#).unwrap();
+        assert_eq!(proto, FormatSpec::from(&TextFmt::ProtoText));
+        assert_eq!(spec(r#"format = { type = "proto-text" }"#).unwrap(), proto);
+        let logfmt = spec(r#"format = { type = "logfmt", time = "epoch_secs" }"#).unwrap();
+        let ts = chrono::NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S")
+            .unwrap();
+        let record = DataRecord {
+            id: 0,
+            items: vec![DataField::from_time("time", ts)],
+        };
+        assert_eq!(
+            logfmt.build().unwrap().format_record(&record),
+            "time=1565093539"
+        );
+    }
+
+    #[test]
+    fn test_json_nesting() {
+        let record = DataRecord {
+            id: 0,
+            items: vec![
+                DataField::from_digit("http.status", 200),
+                DataField::from_digit("n", 1),
+            ],
+        };
+        let json = spec(
+            r#"format = { type = "json", key_value_space = true, nest = { separator = "." } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            json.build().unwrap().format_record(&record),
+            r#"{"http": {"status": 200}, "n": 1}"#
+        );
+        assert!(spec(r#"format = { type = "json", nest = { conflict = "first_wins" } }"#).is_ok());
+        assert!(spec(r#"format = { type = "json", nest = { order = 1 } }"#).is_err());
+    }
+
+    #[test]
+    fn test_proto_schema() {
+        let proto = spec(
+            r#"format = { type = "proto", schema = { fields = { name = { number = 1, kind = "string" }, n = { number = 3 } } } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            proto
+                .build()
+                .unwrap()
+                .format_record_bytes(&record())
+                .unwrap(),
+            vec![0x0a, 0x03, b'a', b';', b'b', 0x18, 0x01]
+        );
+        let partial =
+            spec(r#"format = { type = "proto", schema = { fields = { n = { number = 3 } } } }"#)
+                .unwrap();
+        assert!(
+            partial
+                .build()
+                .unwrap()
+                .format_record_bytes(&record())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let invalid = [
+            r#"format = { type = "csv", delimiter = "\"" }"#,
+            r#"format = { type = "kv", pair_separator = "" }"#,
+            r#"format = { type = "sql", table = " " }"#,
+            r#"format = { type = "sql", table = "t", object_format = "csv" }"#,
+            r#"format = { type = "json", time_offset = "+25:00" }"#,
+            r#"format = { type = "raw", time = { strftime = "%Q" } }"#,
+            r#"format = { type = "csv", escape_char = "," }"#,
+            r#"format = { type = "csv", escape_char = "\n" }"#,
+            r#"format = { type = "logfmt", time_offset = "+25:00" }"#,
+            r#"format = { type = "template", pattern = "{ip" }"#,
+        ];
+        for case in invalid {
+            assert!(spec(case).unwrap().build().is_err(), "{}", case);
+        }
+        // a template that does not compile is never built as another format
+        let template = spec(r#"format = { type = "template", pattern = "{ip" }"#).unwrap();
+        assert!(template.make().is_err());
+        // unknown options and missing required ones fail to deserialize
+        assert!(spec(r#"format = { type = "csv", delimeter = ";" }"#).is_err());
+        assert!(spec(r#"format = { type = "sql" }"#).is_err());
+        assert!(spec(r#"format = { type = "xml" }"#).is_err());
+    }
+}
@@ -1,6 +1,8 @@
+use std::fmt::{self, Write};
+use std::io;
 use wp_model_core::model::{DataField, DataRecord, Value, types::value::ObjectValue};
 
-use crate::{FormatType, SqlFormat};
+use crate::FormatType;
 
 pub trait DataFormat {
     type Output;
@@ -33,6 +35,12 @@ pub trait DataFormat {
 
     fn format_field(&self, field: &DataField) -> Self::Output;
     fn format_record(&self, record: &DataRecord) -> Self::Output;
+
+    /// Formats `fields` as one record, each under the name paired with it.
+    /// See [`StreamDataFormat::format_fields_into`].
+    fn format_fields(&self, fields: &[(&str, &DataField)]) -> Self::Output {
+        self.format_record(&named_record(fields))
+    }
 }
 
 pub trait StaticDataFormatter {
@@ -68,65 +76,233 @@ pub trait StaticDataFormatter {
     fn stdfmt_record(record: &DataRecord) -> Self::Output;
 }
 
-trait AsDataFormatter {
-    fn as_formatter(&self) -> &dyn DataFormat<Output = String>;
+/// Write-oriented counterpart of [`DataFormat`]: renders straight into a
+/// caller-owned buffer so sinks can reuse one buffer across records.
+///
+/// Every text formatter implements both traits; its `DataFormat` methods are
+/// thin wrappers over the `*_into` methods and produce identical output.
+pub trait StreamDataFormat {
+    fn format_null_into(&self, out: &mut dyn Write) -> fmt::Result;
+    fn format_bool_into(&self, value: &bool, out: &mut dyn Write) -> fmt::Result;
+    fn format_string_into(&self, value: &str, out: &mut dyn Write) -> fmt::Result;
+    fn format_i64_into(&self, value: &i64, out: &mut dyn Write) -> fmt::Result;
+    fn format_f64_into(&self, value: &f64, out: &mut dyn Write) -> fmt::Result;
+    fn format_ip_into(&self, value: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result;
+    fn format_datetime_into(
+        &self,
+        value: &chrono::NaiveDateTime,
+        out: &mut dyn Write,
+    ) -> fmt::Result;
+
+    fn format_object_into(&self, value: &ObjectValue, out: &mut dyn Write) -> fmt::Result;
+    fn format_array_into(&self, value: &[DataField], out: &mut dyn Write) -> fmt::Result;
+
+    fn fmt_value_into(&self, value: &Value, out: &mut dyn Write) -> fmt::Result {
+        match value {
+            Value::Null => self.format_null_into(out),
+            Value::Bool(v) => self.format_bool_into(v, out),
+            Value::Chars(v) => self.format_string_into(v, out),
+            Value::Digit(v) => self.format_i64_into(v, out),
+            Value::Float(v) => self.format_f64_into(v, out),
+            Value::IpAddr(v) => self.format_ip_into(v, out),
+            Value::Time(v) => self.format_datetime_into(v, out),
+            Value::Obj(v) => self.format_object_into(v, out),
+            Value::Array(v) => self.format_array_into(v, out),
+            _ => self.format_string_into(&value.to_string(), out),
+        }
+    }
+
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result;
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result;
+
+    /// Formats `fields` as one record, each under the name paired with it,
+    /// so wrappers that select or rename fields need not build a record.
+    /// The default does copy them into one; formatters that write a record
+    /// field by field override it.
+    fn format_fields_into(
+        &self,
+        fields: &[(&str, &DataField)],
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        self.format_record_into(&named_record(fields), out)
+    }
+
+    /// Streams a record into an [`io::Write`] sink (file, socket, `Vec<u8>`).
+    fn format_record_to_io(&self, record: &DataRecord, out: &mut dyn io::Write) -> io::Result<()> {
+        write_to_io(out, |w| self.format_record_into(record, w))
+    }
+}
+
+fn named_record(fields: &[(&str, &DataField)]) -> DataRecord {
+    DataRecord {
+        id: 0,
+        items: fields
+            .iter()
+            .map(|(name, f)| DataField::new(f.get_meta().clone(), *name, f.get_value().clone()))
+            .collect(),
+    }
+}
+
+struct IoWriteAdapter<'a> {
+    inner: &'a mut dyn io::Write,
+    error: Option<io::Error>,
+}
+
+impl Write for IoWriteAdapter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+/// Runs a streaming formatter against an `io::Write`, surfacing the
+/// underlying io error rather than a bare `fmt::Error`.
+pub(crate) fn write_to_io<F>(out: &mut dyn io::Write, f: F) -> io::Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> fmt::Result,
+{
+    let mut adapter = IoWriteAdapter {
+        inner: out,
+        error: None,
+    };
+    f(&mut adapter).map_err(|_| {
+        adapter
+            .error
+            .take()
+            .unwrap_or_else(|| io::Error::other(fmt::Error))
+    })
+}
+
+/// Runs a streaming formatter against a fresh `String`; used by the
+/// `DataFormat` wrappers.
+pub(crate) fn render<F>(f: F) -> String
+where
+    F: FnOnce(&mut dyn Write) -> fmt::Result,
+{
+    let mut out = String::new();
+    // Writing into a String never fails; formatter errors leave partial output.
+    let _ = f(&mut out);
+    out
 }
-impl AsDataFormatter for FormatType {
-    fn as_formatter(&self) -> &dyn DataFormat<Output = String> {
+
+trait AsStreamFormatter {
+    fn as_stream_formatter(&self) -> &dyn StreamDataFormat;
+}
+impl AsStreamFormatter for FormatType {
+    fn as_stream_formatter(&self) -> &dyn StreamDataFormat {
         match self {
             FormatType::Csv(f) => f,
             FormatType::Json(f) => f,
             FormatType::Kv(f) => f,
+            FormatType::Logfmt(f) => f,
             FormatType::Sql(f) => f,
             FormatType::Raw(f) => f,
             FormatType::ProtoText(f) => f,
+            FormatType::Template(f) => f,
+            FormatType::Table(f) => f,
+            FormatType::Proto(f) => f,
         }
     }
 }
 
-impl AsDataFormatter for SqlFormat {
-    fn as_formatter(&self) -> &dyn DataFormat<Output = String> {
-        match self {
-            SqlFormat::Json(f) => f,
-            SqlFormat::Kv(f) => f,
-            SqlFormat::Raw(f) => f,
-            SqlFormat::ProtoText(f) => f,
-        }
-    }
-}
+/// Rendered through the `*_into` methods, which every text formatter keeps
+/// identical to its `DataFormat` output.
 impl DataFormat for FormatType {
     type Output = String;
     fn format_null(&self) -> Self::Output {
-        self.as_formatter().format_null()
+        render(|out| self.format_null_into(out))
     }
     fn format_bool(&self, value: &bool) -> Self::Output {
-        self.as_formatter().format_bool(value)
+        render(|out| self.format_bool_into(value, out))
     }
     fn format_string(&self, value: &str) -> Self::Output {
-        self.as_formatter().format_string(value)
+        render(|out| self.format_string_into(value, out))
     }
     fn format_i64(&self, value: &i64) -> Self::Output {
-        self.as_formatter().format_i64(value)
+        render(|out| self.format_i64_into(value, out))
     }
     fn format_f64(&self, value: &f64) -> Self::Output {
-        self.as_formatter().format_f64(value)
+        render(|out| self.format_f64_into(value, out))
     }
     fn format_ip(&self, value: &std::net::IpAddr) -> Self::Output {
-        self.as_formatter().format_ip(value)
+        render(|out| self.format_ip_into(value, out))
     }
     fn format_datetime(&self, value: &chrono::NaiveDateTime) -> Self::Output {
-        self.as_formatter().format_datetime(value)
+        render(|out| self.format_datetime_into(value, out))
     }
     fn format_object(&self, value: &ObjectValue) -> Self::Output {
-        self.as_formatter().format_object(value)
+        render(|out| self.format_object_into(value, out))
     }
     fn format_array(&self, value: &[DataField]) -> Self::Output {
-        self.as_formatter().format_array(value)
+        render(|out| self.format_array_into(value, out))
     }
     fn format_field(&self, field: &DataField) -> Self::Output {
-        self.as_formatter().format_field(field)
+        render(|out| self.format_field_into(field, out))
     }
     fn format_record(&self, record: &DataRecord) -> Self::Output {
-        self.as_formatter().format_record(record)
+        render(|out| self.format_record_into(record, out))
+    }
+    fn format_fields(&self, fields: &[(&str, &DataField)]) -> Self::Output {
+        render(|out| self.format_fields_into(fields, out))
+    }
+}
+
+impl StreamDataFormat for FormatType {
+    fn format_null_into(&self, out: &mut dyn Write) -> fmt::Result {
+        self.as_stream_formatter().format_null_into(out)
+    }
+    fn format_bool_into(&self, value: &bool, out: &mut dyn Write) -> fmt::Result {
+        self.as_stream_formatter().format_bool_into(value, out)
+    }
+    fn format_string_into(&self, value: &str, out: &mut dyn Write) -> fmt::Result {
+        self.as_stream_formatter().format_string_into(value, out)
+    }
+    fn format_i64_into(&self, value: &i64, out: &mut dyn Write) -> fmt::Result {
+        self.as_stream_formatter().format_i64_into(value, out)
+    }
+    fn format_f64_into(&self, value: &f64, out: &mut dyn Write) -> fmt::Result {
+        self.as_stream_formatter().format_f64_into(value, out)
+    }
+    fn format_ip_into(&self, value: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result {
+        self.as_stream_formatter().format_ip_into(value, out)
+    }
+    fn format_datetime_into(
+        &self,
+        value: &chrono::NaiveDateTime,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        self.as_stream_formatter().format_datetime_into(value, out)
+    }
+    fn format_object_into(&self, value: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        self.as_stream_formatter().format_object_into(value, out)
+    }
+    fn format_array_into(&self, value: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        self.as_stream_formatter().format_array_into(value, out)
+    }
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        self.as_stream_formatter().format_field_into(field, out)
+    }
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        self.as_stream_formatter().format_record_into(record, out)
+    }
+    fn format_fields_into(
+        &self,
+        fields: &[(&str, &DataField)],
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        self.as_stream_formatter().format_fields_into(fields, out)
+    }
+    fn format_record_to_io(&self, record: &DataRecord, out: &mut dyn io::Write) -> io::Result<()> {
+        match self {
+            FormatType::Proto(f) => {
+                let bytes = f
+                    .try_format_record(record)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                out.write_all(&bytes)
+            }
+            _ => write_to_io(out, |w| self.format_record_into(record, w)),
+        }
     }
 }
@@ -0,0 +1,382 @@
+use crate::cef::{HeaderSource, for_each_leaf};
+use crate::formatter::{DataFormat, StreamDataFormat, render};
+use crate::json::{Json, write_json_string};
+use crate::severity::parse_severity;
+use chrono::NaiveDateTime;
+use std::fmt::{self, Write};
+use wp_model_core::model::{DataField, DataRecord, DataType, Value, types::value::ObjectValue};
+
+/// Chunk size that fits a typical WAN MTU; LAN sinks may use up to 8192.
+pub const GELF_WAN_CHUNK_SIZE: usize = 1420;
+const GELF_CHUNK_MAGIC: [u8; 2] = [0x1e, 0x0f];
+const GELF_CHUNK_HEADER: usize = 12;
+const GELF_MAX_CHUNKS: usize = 128;
+
+/// Additional field names Graylog does not accept.
+const RESERVED: &[&str] = &["id"];
+
+/// What to do with a field whose name is reserved by GELF (`_id`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GelfReserved {
+    /// Write it as `_id_` instead.
+    #[default]
+    Rename,
+    Drop,
+}
+
+/// GELF 1.1 JSON output.
+///
+/// `host` and `short_message` come from [`HeaderSource`]s, `timestamp` from
+/// the time field as epoch seconds with millisecond fraction, and `level`
+/// optionally from a field holding a syslog severity. Every other field is
+/// written as an additional field with a `_` prefix; nested values are
+/// flattened with `_`, and characters outside `[A-Za-z0-9_.-]` in names are
+/// replaced with `_`.
+#[derive(Debug, Clone)]
+pub struct Gelf {
+    host: HeaderSource,
+    short_message: HeaderSource,
+    full_message_field: Option<String>,
+    timestamp_field: Option<String>,
+    level_field: Option<String>,
+    reserved: GelfReserved,
+}
+
+impl Default for Gelf {
+    fn default() -> Self {
+        Self {
+            host: HeaderSource::fixed("localhost"),
+            short_message: HeaderSource::field("msg"),
+            full_message_field: None,
+            timestamp_field: Some("time".to_string()),
+            level_field: None,
+            reserved: GelfReserved::default(),
+        }
+    }
+}
+
+impl Gelf {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_host(mut self, src: impl Into<HeaderSource>) -> Self {
+        self.host = src.into();
+        self
+    }
+    /// Source of `short_message`; an empty value is written as `-` since
+    /// the field is mandatory.
+    pub fn with_short_message(mut self, src: impl Into<HeaderSource>) -> Self {
+        self.short_message = src.into();
+        self
+    }
+    pub fn with_full_message_field(mut self, name: impl Into<String>) -> Self {
+        self.full_message_field = Some(name.into());
+        self
+    }
+    pub fn with_timestamp_field(mut self, name: impl Into<String>) -> Self {
+        self.timestamp_field = Some(name.into());
+        self
+    }
+    /// Field holding the severity as a code (`3`) or keyword (`err`).
+    pub fn with_level_field(mut self, name: impl Into<String>) -> Self {
+        self.level_field = Some(name.into());
+        self
+    }
+    pub fn with_reserved(mut self, policy: GelfReserved) -> Self {
+        self.reserved = policy;
+        self
+    }
+
+    /// Splits an encoded message into GELF UDP chunks of at most
+    /// `chunk_size` bytes. A message that fits is returned as-is in a
+    /// single datagram. `message_id` must be unique per message. Returns
+    /// `None` when more than 128 chunks would be needed.
+    pub fn chunk(message: &[u8], message_id: [u8; 8], chunk_size: usize) -> Option<Vec<Vec<u8>>> {
+        if message.len() <= chunk_size {
+            return Some(vec![message.to_vec()]);
+        }
+        let payload = chunk_size
+            .checked_sub(GELF_CHUNK_HEADER)
+            .filter(|n| *n > 0)?;
+        let count = message.len().div_ceil(payload);
+        if count > GELF_MAX_CHUNKS {
+            return None;
+        }
+        let chunks = message
+            .chunks(payload)
+            .enumerate()
+            .map(|(seq, part)| {
+                let mut chunk = Vec::with_capacity(GELF_CHUNK_HEADER + part.len());
+                chunk.extend_from_slice(&GELF_CHUNK_MAGIC);
+                chunk.extend_from_slice(&message_id);
+                chunk.push(seq as u8);
+                chunk.push(count as u8);
+                chunk.extend_from_slice(part);
+                chunk
+            })
+            .collect();
+        Some(chunks)
+    }
+
+    fn is_additional(&self, field: &DataField) -> bool {
+        let name = field.get_name();
+        *field.get_meta() != DataType::Ignore
+            && self.host.field_name() != Some(name)
+            && self.short_message.field_name() != Some(name)
+            && [
+                &self.full_message_field,
+                &self.timestamp_field,
+                &self.level_field,
+            ]
+            .iter()
+            .all(|f| f.as_deref() != Some(name))
+    }
+
+    fn lookup<'a>(&self, record: &'a DataRecord, name: &Option<String>) -> Option<&'a Value> {
+        let name = name.as_deref()?;
+        record
+            .items
+            .iter()
+            .find(|f| f.get_name() == name)
+            .map(|f| f.get_value())
+    }
+
+    fn write_additional(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        let mut prefix = field.get_name().to_string();
+        for_each_leaf(&mut prefix, "_", field.get_value(), &mut |key, value| {
+            let mut name: String = key
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            if RESERVED.contains(&name.as_str()) {
+                match self.reserved {
+                    GelfReserved::Rename => name.push('_'),
+                    GelfReserved::Drop => return Ok(()),
+                }
+            }
+            out.write_str(",\"_")?;
+            out.write_str(&name)?;
+            out.write_str("\":")?;
+            self.fmt_value_into(value, out)
+        })
+    }
+}
+
+/// Written as sign and magnitude: `timestamp()` floors, so its fraction
+/// would point the wrong way before 1970.
+fn write_epoch_seconds(t: &NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+    let millis = t.and_utc().timestamp_millis();
+    let sign = if millis < 0 { "-" } else { "" };
+    let (secs, frac) = (millis.unsigned_abs() / 1000, millis.unsigned_abs() % 1000);
+    match frac {
+        0 => write!(out, "{}{}", sign, secs),
+        frac => write!(out, "{}{}.{:03}", sign, secs, frac),
+    }
+}
+
+/// Scalar methods follow GELF's value rules: strings and numbers only, so
+/// booleans are written as strings.
+impl StreamDataFormat for Gelf {
+    fn format_null_into(&self, out: &mut dyn Write) -> fmt::Result {
+        Json::new().format_null_into(out)
+    }
+    fn format_bool_into(&self, v: &bool, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "\"{}\"", v)
+    }
+    fn format_string_into(&self, v: &str, out: &mut dyn Write) -> fmt::Result {
+        write_json_string(out, v)
+    }
+    fn format_i64_into(&self, v: &i64, out: &mut dyn Write) -> fmt::Result {
+        Json::new().format_i64_into(v, out)
+    }
+    fn format_f64_into(&self, v: &f64, out: &mut dyn Write) -> fmt::Result {
+        Json::new().format_f64_into(v, out)
+    }
+    fn format_ip_into(&self, v: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result {
+        Json::new().format_ip_into(v, out)
+    }
+    fn format_datetime_into(&self, v: &NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+        Json::new().format_datetime_into(v, out)
+    }
+    fn format_object_into(&self, value: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        Json::new().format_object_into(value, out)
+    }
+    fn format_array_into(&self, value: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        Json::new().format_array_into(value, out)
+    }
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        // drop the leading comma written for record position
+        let mut buf = String::new();
+        self.write_additional(field, &mut buf)?;
+        out.write_str(buf.strip_prefix(',').unwrap_or(&buf))
+    }
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        out.write_str("{\"version\":\"1.1\",\"host\":")?;
+        write_json_string(out, &self.host.resolve(record))?;
+        out.write_str(",\"short_message\":")?;
+        let short = self.short_message.resolve(record);
+        write_json_string(out, if short.is_empty() { "-" } else { &short })?;
+        if let Some(full) = self.lookup(record, &self.full_message_field) {
+            out.write_str(",\"full_message\":")?;
+            match full {
+                Value::Chars(s) => write_json_string(out, s)?,
+                v => write_json_string(out, &v.to_string())?,
+            }
+        }
+        if let Some(Value::Time(t)) = self.lookup(record, &self.timestamp_field) {
+            out.write_str(",\"timestamp\":")?;
+            write_epoch_seconds(t, out)?;
+        }
+        if let Some(level) = self
+            .lookup(record, &self.level_field)
+            .and_then(parse_severity)
+        {
+            write!(out, ",\"level\":{}", level)?;
+        }
+        for field in record.items.iter().filter(|f| self.is_additional(f)) {
+            self.write_additional(field, out)?;
+        }
+        out.write_char('}')
+    }
+}
+
+impl DataFormat for Gelf {
+    type Output = String;
+    fn format_null(&self) -> String {
+        render(|out| self.format_null_into(out))
+    }
+    fn format_bool(&self, v: &bool) -> String {
+        render(|out| self.format_bool_into(v, out))
+    }
+    fn format_string(&self, v: &str) -> String {
+        render(|out| self.format_string_into(v, out))
+    }
+    fn format_i64(&self, v: &i64) -> String {
+        render(|out| self.format_i64_into(v, out))
+    }
+    fn format_f64(&self, v: &f64) -> String {
+        render(|out| self.format_f64_into(v, out))
+    }
+    fn format_ip(&self, v: &std::net::IpAddr) -> String {
+        render(|out| self.format_ip_into(v, out))
+    }
+    fn format_datetime(&self, v: &NaiveDateTime) -> String {
+        render(|out| self.format_datetime_into(v, out))
+    }
+    fn format_object(&self, value: &ObjectValue) -> String {
+        render(|out| self.format_object_into(value, out))
+    }
+    fn format_array(&self, value: &[DataField]) -> String {
+        render(|out| self.format_array_into(value, out))
+    }
+    fn format_field(&self, field: &DataField) -> String {
+        render(|out| self.format_field_into(field, out))
+    }
+    fn format_record(&self, record: &DataRecord) -> String {
+        render(|out| self.format_record_into(record, out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(items: Vec<DataField>) -> DataRecord {
+        DataRecord { id: 0, items }
+    }
+
+    #[test]
+    fn test_minimal_record() {
+        let r = rec(vec![DataField::from_chars("msg", "hello \"x\"")]);
+        assert_eq!(
+            Gelf::new().format_record(&r),
+            r#"{"version":"1.1","host":"localhost","short_message":"hello \"x\""}"#
+        );
+        let empty = rec(vec![]);
+        assert!(
+            Gelf::new()
+                .format_record(&empty)
+                .contains(r#""short_message":"-""#)
+        );
+    }
+
+    #[test]
+    fn test_timestamp_level_and_additional() {
+        let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19.250", "%Y-%m-%d %H:%M:%S%.3f")
+            .unwrap();
+        let r = rec(vec![
+            DataField::from_time("time", ts),
+            DataField::from_chars("msg", "m"),
+            DataField::from_chars("sev", "warning"),
+            DataField::from_chars("node", "n1"),
+            DataField::from_bool("ok", true),
+            DataField::from_digit("http/status", 200),
+        ]);
+        let f = Gelf::new()
+            .with_host(HeaderSource::field("node"))
+            .with_level_field("sev");
+        assert_eq!(
+            f.format_record(&r),
+            r#"{"version":"1.1","host":"n1","short_message":"m","timestamp":1565093539.250,"level":4,"_ok":"true","_http_status":200}"#
+        );
+    }
+
+    #[test]
+    fn test_epoch_seconds_before_1970() {
+        let epoch = |millis| {
+            let mut out = String::new();
+            let t = chrono::DateTime::from_timestamp_millis(millis).unwrap();
+            write_epoch_seconds(&t.naive_utc(), &mut out).unwrap();
+            out
+        };
+        assert_eq!(epoch(-1500), "-1.500");
+        assert_eq!(epoch(-500), "-0.500");
+        assert_eq!(epoch(-2000), "-2");
+        assert_eq!(epoch(1250), "1.250");
+    }
+
+    #[test]
+    fn test_reserved_id() {
+        let r = rec(vec![
+            DataField::from_chars("msg", "m"),
+            DataField::from_digit("id", 7),
+        ]);
+        assert!(Gelf::new().format_record(&r).ends_with(r#","_id_":7}"#));
+        let dropped = Gelf::new()
+            .with_reserved(GelfReserved::Drop)
+            .format_record(&r);
+        assert!(!dropped.contains("_id"));
+    }
+
+    #[test]
+    fn test_nested_flattening() {
+        let mut obj = ObjectValue::new();
+        obj.insert("addr", DataField::from_chars("addr", "10.0.0.9"));
+        let field = DataField::from_obj("up", obj);
+        assert_eq!(Gelf::new().format_field(&field), r#""_up_addr":"10.0.0.9""#);
+    }
+
+    #[test]
+    fn test_chunking() {
+        let msg: Vec<u8> = (0..30u8).collect();
+        let id = [1, 2, 3, 4, 5, 6, 7, 8];
+        assert_eq!(Gelf::chunk(&msg, id, 64).unwrap(), vec![msg.clone()]);
+
+        let chunks = Gelf::chunk(&msg, id, 22).unwrap();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(&chunks[0][..2], &GELF_CHUNK_MAGIC);
+        assert_eq!(&chunks[0][2..10], &id);
+        assert_eq!((chunks[2][10], chunks[2][11]), (2, 3));
+        let joined: Vec<u8> = chunks.iter().flat_map(|c| c[12..].to_vec()).collect();
+        assert_eq!(joined, msg);
+
+        assert!(Gelf::chunk(&[0u8; 200], id, 13).is_none());
+        assert!(Gelf::chunk(&msg, id, 12).is_none());
+    }
+}
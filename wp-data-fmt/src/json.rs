@@ -1,80 +1,319 @@
-use crate::formatter::StaticDataFormatter;
-use serde_json::{Value as JsonValue, json};
+use crate::formatter::{StaticDataFormatter, StreamDataFormat, render};
+use crate::nested::{self, Entry, Nesting};
+use crate::parse::{Cursor, DataParse, Node, ParseError, ParseHints, build_record};
+use crate::time_fmt::{TimeFormat, TimeStyle};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write};
 use wp_model_core::model::{DataField, DataRecord, DataType, Value, types::value::ObjectValue};
 
-#[derive(Debug, Default)]
-pub struct Json;
+/// Compact single-line JSON by default; [`Json::with_indent`] switches to
+/// one member per line and [`Json::with_nesting`] expands path-style field
+/// names into nested objects.
+#[derive(Debug, Clone, Default)]
+pub struct Json {
+    time_format: TimeFormat,
+    indent: Option<usize>,
+    key_value_space: bool,
+    line_ending: LineEnding,
+    nesting: Option<Nesting>,
+}
+
+/// Line break between members of indented output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+impl Json {
+    pub const fn new() -> Self {
+        Self {
+            time_format: TimeFormat::new(TimeStyle::Naive),
+            indent: None,
+            key_value_space: false,
+            line_ending: LineEnding::Lf,
+            nesting: None,
+        }
+    }
+    /// Two-space indentation with `": "`, as `serde_json::to_string_pretty`.
+    pub const fn pretty() -> Self {
+        Self {
+            time_format: TimeFormat::new(TimeStyle::Naive),
+            indent: Some(2),
+            key_value_space: true,
+            line_ending: LineEnding::Lf,
+            nesting: None,
+        }
+    }
+    /// Epoch styles are written as JSON numbers, the others as strings.
+    pub fn with_time_format(mut self, time_format: impl Into<TimeFormat>) -> Self {
+        self.time_format = time_format.into();
+        self
+    }
+    /// Writes each object member and array item on its own line, indented
+    /// `indent` spaces per level. Empty containers stay `{}` and `[]`.
+    pub fn with_indent(mut self, indent: usize) -> Self {
+        self.indent = Some(indent);
+        self
+    }
+    /// A space after `:`, and after `,` on single-line output.
+    pub fn with_key_value_space(mut self, space: bool) -> Self {
+        self.key_value_space = space;
+        self
+    }
+    pub fn with_line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+    /// Nests record fields by their path-style names. A record whose
+    /// conflicts the [`Nesting`] policy cannot resolve is an error from
+    /// [`Json::try_format_record`]; the other record methods write nothing
+    /// for it.
+    pub fn with_nesting(mut self, nesting: Nesting) -> Self {
+        self.nesting = Some(nesting);
+        self
+    }
+
+    pub fn try_format_record(&self, record: &DataRecord) -> Result<String, String> {
+        let fields = || record.items.iter().map(|f| (f.get_name(), f));
+        let tree = match &self.nesting {
+            Some(nesting) => Some(nesting.build_tree(fields().filter(|(_, f)| kept(f)))?),
+            None => None,
+        };
+        Ok(render(|out| match &tree {
+            Some(tree) => self.write_tree(tree, out),
+            None => self.write_record(fields(), out),
+        }))
+    }
+
+    /// Before the `i`-th member of a container opened at `depth`.
+    fn write_item_sep(&self, i: usize, depth: usize, out: &mut dyn Write) -> fmt::Result {
+        if i > 0 {
+            out.write_char(',')?;
+        }
+        match self.indent {
+            Some(indent) => {
+                out.write_str(self.line_ending.as_str())?;
+                write!(out, "{:width$}", "", width = (depth + 1) * indent)
+            }
+            None if i > 0 && self.key_value_space => out.write_char(' '),
+            None => Ok(()),
+        }
+    }
+
+    fn write_close(
+        &self,
+        close: char,
+        len: usize,
+        depth: usize,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        if len > 0
+            && let Some(indent) = self.indent
+        {
+            out.write_str(self.line_ending.as_str())?;
+            write!(out, "{:width$}", "", width = depth * indent)?;
+        }
+        out.write_char(close)
+    }
+
+    fn write_key_sep(&self, out: &mut dyn Write) -> fmt::Result {
+        out.write_str(if self.key_value_space { ": " } else { ":" })
+    }
+
+    fn write_nested(&self, value: &Value, depth: usize, out: &mut dyn Write) -> fmt::Result {
+        match value {
+            Value::Obj(v) => self.write_object(v, depth, out),
+            Value::Array(v) => self.write_array(v, depth, out),
+            _ => self.fmt_value_into(value, out),
+        }
+    }
+
+    fn write_object(&self, v: &ObjectValue, depth: usize, out: &mut dyn Write) -> fmt::Result {
+        out.write_char('{')?;
+        for (i, (k, field)) in v.iter().enumerate() {
+            self.write_item_sep(i, depth, out)?;
+            write_json_string(out, k)?;
+            self.write_key_sep(out)?;
+            self.write_nested(field.get_value(), depth + 1, out)?;
+        }
+        self.write_close('}', v.len(), depth, out)
+    }
+
+    fn write_array(&self, v: &[DataField], depth: usize, out: &mut dyn Write) -> fmt::Result {
+        out.write_char('[')?;
+        for (i, field) in v.iter().enumerate() {
+            self.write_item_sep(i, depth, out)?;
+            self.write_nested(field.get_value(), depth + 1, out)?;
+        }
+        self.write_close(']', v.len(), depth, out)
+    }
+
+    fn write_record<'a>(
+        &self,
+        fields: impl Iterator<Item = (&'a str, &'a DataField)>,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        let fields = fields.filter(|(_, f)| kept(f));
+        if let Some(nesting) = &self.nesting {
+            let tree = nesting.build_tree(fields).map_err(|_| fmt::Error)?;
+            return self.write_tree(&tree, out);
+        }
+        out.write_char('{')?;
+        let mut len = 0;
+        for (name, field) in fields {
+            self.write_item_sep(len, 0, out)?;
+            self.write_field(name, field, 1, out)?;
+            len += 1;
+        }
+        self.write_close('}', len, 0, out)
+    }
+
+    fn write_tree(&self, tree: &[Entry<'_>], out: &mut dyn Write) -> fmt::Result {
+        out.write_char('{')?;
+        self.write_entries(tree, 0, out)?;
+        self.write_close('}', tree.len(), 0, out)
+    }
+
+    /// Members of a nested object opened at `depth`, without its braces.
+    fn write_entries(
+        &self,
+        entries: &[Entry<'_>],
+        depth: usize,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        for (i, (key, node)) in entries.iter().enumerate() {
+            self.write_item_sep(i, depth, out)?;
+            write_json_string(out, key)?;
+            self.write_key_sep(out)?;
+            match node {
+                nested::Node::Leaf(v) => self.write_nested(v, depth + 1, out)?,
+                nested::Node::Branch(children) => {
+                    out.write_char('{')?;
+                    self.write_entries(children, depth + 1, out)?;
+                    self.write_close('}', children.len(), depth + 1, out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_field(
+        &self,
+        name: &str,
+        field: &DataField,
+        depth: usize,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        if !name.is_empty() {
+            write_json_string(out, name)?;
+            self.write_key_sep(out)?;
+        }
+        self.write_nested(field.get_value(), depth, out)
+    }
+}
+
+/// Static rendering uses the default [`Json`] settings.
 impl StaticDataFormatter for Json {
     type Output = String;
     fn stdfmt_null() -> String {
         "null".to_string()
     }
     fn stdfmt_bool(value: &bool) -> String {
-        json!(value).to_string()
+        value.to_string()
     }
     fn stdfmt_string(value: &str) -> String {
-        serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+        render(|out| write_json_string(out, value))
     }
     fn stdfmt_i64(value: &i64) -> String {
-        json!(value).to_string()
+        value.to_string()
     }
     fn stdfmt_f64(value: &f64) -> String {
-        if value.is_nan() {
-            "null".to_string()
-        } else if value.is_infinite() {
-            if value.is_sign_positive() {
-                "\"Infinity\"".to_string()
-            } else {
-                "\"-Infinity\"".to_string()
-            }
-        } else {
-            json!(value).to_string()
-        }
+        render(|out| write_json_f64(out, *value))
     }
     fn stdfmt_ip_addr(value: &std::net::IpAddr) -> String {
-        json!(value.to_string()).to_string()
+        render(|out| Json::new().format_ip_into(value, out))
     }
     fn stdfmt_datetime(value: &chrono::NaiveDateTime) -> String {
-        json!(value.to_string()).to_string()
+        render(|out| Json::new().format_datetime_into(value, out))
     }
     fn stdfmt_object(value: &ObjectValue) -> String {
-        let mut json_obj = serde_json::Map::new();
-        for (k, v) in value.iter() {
-            json_obj.insert(k.to_string(), to_json_value(v.get_value()));
-        }
-        json!(json_obj).to_string()
+        render(|out| Json::new().format_object_into(value, out))
     }
     fn stdfmt_array(value: &[DataField]) -> String {
-        let items: Vec<String> = value
-            .iter()
-            .map(|field| match field.get_value() {
-                Value::Chars(s) => Self::stdfmt_string(s),
-                _ => Self::stdfmt_value(field.get_value()),
-            })
-            .collect();
-        format!("[{}]", items.join(","))
+        render(|out| Json::new().format_array_into(value, out))
     }
     fn stdfmt_field(field: &DataField) -> String {
-        if field.get_name().is_empty() {
-            Self::stdfmt_value(field.get_value())
+        render(|out| Json::new().format_field_into(field, out))
+    }
+    fn stdfmt_record(record: &DataRecord) -> String {
+        render(|out| Json::new().format_record_into(record, out))
+    }
+}
+
+impl StreamDataFormat for Json {
+    fn format_null_into(&self, out: &mut dyn Write) -> fmt::Result {
+        out.write_str("null")
+    }
+    fn format_bool_into(&self, v: &bool, out: &mut dyn Write) -> fmt::Result {
+        out.write_str(if *v { "true" } else { "false" })
+    }
+    fn format_string_into(&self, v: &str, out: &mut dyn Write) -> fmt::Result {
+        write_json_string(out, v)
+    }
+    fn format_i64_into(&self, v: &i64, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_f64_into(&self, v: &f64, out: &mut dyn Write) -> fmt::Result {
+        write_json_f64(out, *v)
+    }
+    fn format_ip_into(&self, v: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "\"{}\"", v)
+    }
+    fn format_datetime_into(&self, v: &chrono::NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+        if self.time_format.is_numeric() {
+            self.time_format.write_into(v, out)
         } else {
-            format!(
-                "\"{}\":{}",
-                field.get_name(),
-                Self::stdfmt_value(field.get_value())
-            )
+            write_json_string(out, &self.time_format.render(v))
         }
     }
-    fn stdfmt_record(record: &DataRecord) -> String {
-        let mut items = Vec::new();
-        for field in record
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
+    fn format_object_into(&self, v: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        self.write_object(v, 0, out)
+    }
+    fn format_array_into(&self, v: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        self.write_array(v, 0, out)
+    }
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        if let Some(nesting) = &self.nesting
+            && !field.get_name().is_empty()
         {
-            items.push(Self::stdfmt_field(field));
+            let tree = nesting
+                .build_tree(std::iter::once((field.get_name(), field)))
+                .map_err(|_| fmt::Error)?;
+            return self.write_entries(&tree, 0, out);
         }
-        format!("{{{}}}", items.join(","))
+        self.write_field(field.get_name(), field, 0, out)
+    }
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        self.write_record(record.items.iter().map(|f| (f.get_name(), f)), out)
+    }
+    fn format_fields_into(
+        &self,
+        fields: &[(&str, &DataField)],
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        self.write_record(fields.iter().copied(), out)
     }
 }
 
@@ -99,56 +338,193 @@ impl crate::formatter::DataFormat for Json {
         Self::stdfmt_ip_addr(v)
     }
     fn format_datetime(&self, v: &chrono::NaiveDateTime) -> String {
-        Self::stdfmt_datetime(v)
+        render(|out| self.format_datetime_into(v, out))
     }
     fn format_object(&self, v: &ObjectValue) -> String {
-        Self::stdfmt_object(v)
+        render(|out| self.format_object_into(v, out))
     }
     fn format_array(&self, v: &[DataField]) -> String {
-        Self::stdfmt_array(v)
+        render(|out| self.format_array_into(v, out))
     }
     fn format_field(&self, f: &DataField) -> String {
-        Self::stdfmt_field(f)
+        render(|out| self.format_field_into(f, out))
     }
     fn format_record(&self, r: &DataRecord) -> String {
-        Self::stdfmt_record(r)
+        render(|out| self.format_record_into(r, out))
+    }
+    fn format_fields(&self, fields: &[(&str, &DataField)]) -> String {
+        render(|out| self.format_fields_into(fields, out))
     }
 }
 
-fn to_json_value(value: &Value) -> JsonValue {
-    match value {
-        Value::Bool(v) => JsonValue::Bool(*v),
-        Value::Chars(v) => JsonValue::String(v.to_string()),
-        Value::Digit(v) => JsonValue::Number((*v).into()),
-        Value::Float(v) => {
-            if v.is_nan() {
-                JsonValue::Null
-            } else if v.is_infinite() {
-                if v.is_sign_positive() {
-                    JsonValue::String("Infinity".to_string())
-                } else {
-                    JsonValue::String("-Infinity".to_string())
+fn kept(field: &DataField) -> bool {
+    *field.get_meta() != DataType::Ignore
+}
+
+/// Writes `value` as a quoted JSON string using the same escaping as
+/// `serde_json`: `"`, `\` and control characters are escaped, everything
+/// else (including non-ASCII) is written verbatim.
+pub(crate) fn write_json_string(out: &mut dyn Write, value: &str) -> fmt::Result {
+    out.write_char('"')?;
+    let bytes = value.as_bytes();
+    let mut start = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        let escape = match b {
+            b'"' => "\\\"",
+            b'\\' => "\\\\",
+            b'\n' => "\\n",
+            b'\r' => "\\r",
+            b'\t' => "\\t",
+            0x08 => "\\b",
+            0x0c => "\\f",
+            0x00..=0x1f => "",
+            _ => continue,
+        };
+        out.write_str(&value[start..i])?;
+        if escape.is_empty() {
+            write!(out, "\\u{:04x}", b)?;
+        } else {
+            out.write_str(escape)?;
+        }
+        start = i + 1;
+    }
+    out.write_str(&value[start..])?;
+    out.write_char('"')
+}
+
+/// NaN becomes `null` and infinities become quoted strings, since JSON has no
+/// literal for them.
+pub(crate) fn write_json_f64(out: &mut dyn Write, value: f64) -> fmt::Result {
+    if value.is_nan() {
+        out.write_str("null")
+    } else if value.is_infinite() {
+        if value.is_sign_positive() {
+            out.write_str("\"Infinity\"")
+        } else {
+            out.write_str("\"-Infinity\"")
+        }
+    } else {
+        match serde_json::Number::from_f64(value) {
+            Some(n) => write!(out, "{}", n),
+            None => out.write_str("null"),
+        }
+    }
+}
+
+impl DataParse for Json {
+    /// Reads one JSON object; times are read with this formatter's time
+    /// format. `NaN` is written as `null` and reads back as `Null`.
+    fn parse_record_with(&self, input: &str, hints: &ParseHints) -> Result<DataRecord, ParseError> {
+        let mut cur = Cursor::new(input);
+        cur.skip_ws();
+        if cur.peek() != Some('{') {
+            return Err(cur.error("expected a JSON object"));
+        }
+        let Node::Obj(entries) = read_json_value(&mut cur)? else {
+            unreachable!("checked for an object");
+        };
+        cur.skip_ws();
+        if !cur.is_eof() {
+            return Err(cur.error("trailing characters"));
+        }
+        build_record(entries, hints, &self.time_format)
+    }
+}
+
+fn read_json_value(cur: &mut Cursor) -> Result<Node, ParseError> {
+    cur.skip_ws();
+    match cur.peek() {
+        Some('{') => {
+            cur.bump();
+            let mut entries = Vec::new();
+            cur.skip_ws();
+            if cur.eat("}") {
+                return Ok(Node::Obj(entries));
+            }
+            loop {
+                cur.skip_ws();
+                let key = read_json_string(cur)?;
+                cur.skip_ws();
+                cur.expect(":")?;
+                entries.push((key, read_json_value(cur)?));
+                cur.skip_ws();
+                if cur.eat("}") {
+                    return Ok(Node::Obj(entries));
                 }
-            } else {
-                JsonValue::Number(serde_json::Number::from_f64(*v).unwrap_or(0.into()))
+                cur.expect(",")?;
             }
         }
-        Value::IpAddr(v) => JsonValue::String(v.to_string()),
-        Value::Time(v) => JsonValue::String(v.to_string()),
-        Value::Obj(v) => {
-            let mut map = serde_json::Map::new();
-            for (k, field) in v.iter() {
-                map.insert(k.to_string(), to_json_value(field.get_value()));
+        Some('[') => {
+            cur.bump();
+            let mut items = Vec::new();
+            cur.skip_ws();
+            if cur.eat("]") {
+                return Ok(Node::Arr(items));
             }
-            JsonValue::Object(map)
+            loop {
+                items.push(read_json_value(cur)?);
+                cur.skip_ws();
+                if cur.eat("]") {
+                    return Ok(Node::Arr(items));
+                }
+                cur.expect(",")?;
+            }
+        }
+        Some('"') => read_json_string(cur).map(Node::Str),
+        Some('-' | '0'..='9') => {
+            let n = cur.take_until(|c| !matches!(c, '-' | '+' | '.' | 'e' | 'E' | '0'..='9'));
+            Ok(Node::Num(n.to_string()))
         }
-        Value::Array(v) => {
-            JsonValue::Array(v.iter().map(|f| to_json_value(f.get_value())).collect())
+        _ if cur.eat("true") => Ok(Node::Bool(true)),
+        _ if cur.eat("false") => Ok(Node::Bool(false)),
+        _ if cur.eat("null") => Ok(Node::Null),
+        _ => Err(cur.error("expected a JSON value")),
+    }
+}
+
+fn read_json_string(cur: &mut Cursor) -> Result<String, ParseError> {
+    cur.expect("\"")?;
+    let mut s = String::new();
+    loop {
+        let chunk = cur.take_until(|c| c == '"' || c == '\\');
+        s.push_str(chunk);
+        match cur.bump() {
+            Some('"') => return Ok(s),
+            Some('\\') => {}
+            _ => return Err(cur.error("unterminated string")),
         }
-        _ => JsonValue::Null,
+        let c = match cur.bump() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\x08',
+            Some('f') => '\x0c',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                let hi = read_hex4(cur)?;
+                let code = if (0xd800..0xdc00).contains(&hi) && cur.eat("\\u") {
+                    let lo = read_hex4(cur)?;
+                    0x10000 + ((hi - 0xd800) << 10) + (lo.wrapping_sub(0xdc00) & 0x3ff)
+                } else {
+                    hi
+                };
+                char::from_u32(code).ok_or_else(|| cur.error("invalid unicode escape"))?
+            }
+            _ => return Err(cur.error("invalid escape")),
+        };
+        s.push(c);
     }
 }
 
+fn read_hex4(cur: &mut Cursor) -> Result<u32, ParseError> {
+    let hex = cur.rest().get(..4).unwrap_or("");
+    let code = u32::from_str_radix(hex, 16).map_err(|_| cur.error("invalid unicode escape"))?;
+    cur.eat(hex);
+    Ok(code)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -232,6 +608,7 @@ mod tests {
     #[test]
     fn test_json_stdfmt_record() {
         let record = DataRecord {
+            id: 0,
             items: vec![
                 DataField::from_chars("name", "Alice"),
                 DataField::from_digit("age", 30),
@@ -246,7 +623,7 @@ mod tests {
 
     #[test]
     fn test_json_dataformat_impl() {
-        let json = Json;
+        let json = Json::new();
         assert_eq!(json.format_null(), "null");
         assert_eq!(json.format_bool(&true), "true");
         assert_eq!(json.format_string("test"), "\"test\"");
@@ -255,25 +632,83 @@ mod tests {
     }
 
     #[test]
-    fn test_to_json_value_basic() {
-        assert_eq!(to_json_value(&Value::Bool(true)), JsonValue::Bool(true));
+    fn test_json_stdfmt_object_basic() {
+        let mut obj = ObjectValue::new();
+        obj.insert("b", DataField::from_bool("b", true));
+        obj.insert("c", DataField::from_chars("c", "hi"));
+        obj.insert("d", DataField::from_digit("d", 42));
+        assert_eq!(Json::stdfmt_object(&obj), r#"{"b":true,"c":"hi","d":42}"#);
+    }
+
+    #[test]
+    fn test_json_stdfmt_object_float_special() {
+        let mut obj = ObjectValue::new();
+        obj.insert("inf", DataField::from_float("inf", f64::INFINITY));
+        obj.insert("nan", DataField::from_float("nan", f64::NAN));
         assert_eq!(
-            to_json_value(&Value::Chars("hi".into())),
-            JsonValue::String("hi".into())
+            Json::stdfmt_object(&obj),
+            r#"{"inf":"Infinity","nan":null}"#
         );
+    }
+
+    #[test]
+    fn test_json_string_control_chars() {
         assert_eq!(
-            to_json_value(&Value::Digit(42)),
-            JsonValue::Number(42.into())
+            Json::stdfmt_string("a\\b\n\t\u{1}é"),
+            serde_json::to_string("a\\b\n\t\u{1}é").unwrap()
         );
     }
 
     #[test]
-    fn test_to_json_value_float_special() {
-        assert_eq!(to_json_value(&Value::Float(f64::NAN)), JsonValue::Null);
+    fn test_json_escapes_field_names() {
+        let record = DataRecord {
+            id: 0,
+            items: vec![DataField::from_digit(r#"say "hi"\now"#, 1)],
+        };
+        let expect = r#"{"say \"hi\"\\now":1}"#;
+        assert_eq!(Json::new().format_record(&record), expect);
         assert_eq!(
-            to_json_value(&Value::Float(f64::INFINITY)),
-            JsonValue::String("Infinity".into())
+            serde_json::from_str::<serde_json::Value>(expect).unwrap()[r#"say "hi"\now"#],
+            1
         );
+        assert_eq!(
+            Json::stdfmt_field(&record.items[0]),
+            r#""say \"hi\"\\now":1"#
+        );
+    }
+
+    #[test]
+    fn test_json_format_record_into_reuses_buffer() {
+        let record = DataRecord {
+            id: 0,
+            items: vec![DataField::from_digit("n", 1)],
+        };
+        let mut buf = String::from("prefix ");
+        Json::new().format_record_into(&record, &mut buf).unwrap();
+        assert_eq!(buf, r#"prefix {"n":1}"#);
+    }
+
+    #[test]
+    fn test_json_time_format() {
+        let dt = chrono::NaiveDateTime::parse_from_str("2024-01-15 10:30:45", "%Y-%m-%d %H:%M:%S")
+            .unwrap();
+        let mut obj = ObjectValue::new();
+        obj.insert("at", DataField::from_time("at", dt));
+        let record = DataRecord {
+            id: 0,
+            items: vec![
+                DataField::from_time("t", dt),
+                DataField::from_obj("o", obj),
+                DataField::from_arr("a", vec![DataField::from_time("", dt)]),
+            ],
+        };
+        let json = Json::new().with_time_format(TimeStyle::EpochSecs);
+        assert_eq!(
+            json.format_record(&record),
+            r#"{"t":1705314645,"o":{"at":1705314645},"a":[1705314645]}"#
+        );
+        let json = Json::new().with_time_format(TimeStyle::Rfc3339);
+        assert_eq!(json.format_datetime(&dt), r#""2024-01-15T10:30:45Z""#);
     }
 
     #[test]
@@ -286,4 +721,67 @@ mod tests {
         let result = Json::stdfmt_array(&arr);
         assert_eq!(result, "[1,2,3]");
     }
+
+    #[test]
+    fn test_json_pretty_layout() {
+        let mut inner = ObjectValue::new();
+        inner.insert("x", DataField::from_digit("x", 1));
+        inner.insert("e", DataField::from_obj("e", ObjectValue::new()));
+        let record = DataRecord {
+            id: 0,
+            items: vec![
+                DataField::from_obj("o", inner),
+                DataField::from_arr(
+                    "a",
+                    vec![
+                        DataField::from_float("", f64::NAN),
+                        DataField::from_float("", f64::INFINITY),
+                    ],
+                ),
+                DataField::from_arr("none", vec![]),
+                DataField::from_ignore("skip"),
+            ],
+        };
+        let pretty = Json::pretty().format_record(&record);
+        assert_eq!(
+            pretty,
+            "{\n  \"o\": {\n    \"e\": {},\n    \"x\": 1\n  },\n  \"a\": [\n    null,\n    \"Infinity\"\n  ],\n  \"none\": []\n}"
+        );
+        let compact = Json::new().format_record(&record);
+        let strip = |s: &str| -> serde_json::Value { serde_json::from_str(s).unwrap() };
+        assert_eq!(strip(&pretty), strip(&compact));
+
+        let spaced = Json::new().with_key_value_space(true);
+        assert_eq!(
+            spaced.format_record(&record),
+            r#"{"o": {"e": {}, "x": 1}, "a": [null, "Infinity"], "none": []}"#
+        );
+        let crlf = Json::new()
+            .with_indent(1)
+            .with_line_ending(LineEnding::CrLf);
+        assert_eq!(
+            crlf.format_array(&[DataField::from_digit("", 1)]),
+            "[\r\n 1\r\n]"
+        );
+        assert_eq!(Json::pretty().format_record(&DataRecord::default()), "{}");
+    }
+
+    #[test]
+    fn test_json_parse_syntax() {
+        use crate::parse::DataParse;
+        let r = Json::new()
+            .parse_record(
+                r#" {"a" : "\u00e9\ud83d\ude00\n", "b": [1, -2.5e1, null, true], "c": {}} "#,
+            )
+            .unwrap();
+        assert_eq!(r.items[0], DataField::from_chars("a", "é😀\n"));
+        assert_eq!(
+            Json::new().format_record(&r),
+            r#"{"a":"é😀\n","b":[1,-25.0,null,true],"c":{}}"#
+        );
+        let err = Json::new().parse_record(r#"{"a":1,}"#).unwrap_err();
+        assert_eq!(err.to_string(), "expected '\"' at byte 7");
+        assert!(Json::new().parse_record("[1]").is_err());
+        assert!(Json::new().parse_record(r#"{"a":1} x"#).is_err());
+    }
 }
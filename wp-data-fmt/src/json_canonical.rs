@@ -0,0 +1,317 @@
+use crate::formatter::{DataFormat, StreamDataFormat, render};
+use crate::json::write_json_string;
+use crate::time_fmt::{TimeFormat, TimeStyle};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::fmt::{self, Write};
+use wp_model_core::model::{DataField, DataRecord, DataType, types::value::ObjectValue};
+
+/// Byte-stable JSON in the JSON Canonicalization Scheme (RFC 8785).
+///
+/// Object members and record fields are sorted by the UTF-16 code units of
+/// their names, whitespace is omitted, floats use the ECMAScript number
+/// form (`1e+21`, `0.000001`, `4`) and strings the minimal escaping. Field
+/// metadata is not written, so the same values render the same whichever
+/// parser produced them.
+///
+/// Two deviations keep every record representable: `Digit` is written as
+/// the exact integer even beyond 2^53, and non-finite floats follow
+/// [`crate::Json`] (`NaN` as `null`, infinities as strings).
+#[derive(Debug, Clone, Default)]
+pub struct JsonCanonical {
+    time_format: TimeFormat,
+}
+
+impl JsonCanonical {
+    pub const fn new() -> Self {
+        Self {
+            time_format: TimeFormat::new(TimeStyle::Naive),
+        }
+    }
+    /// Epoch styles are written as numbers, the others as strings.
+    pub fn with_time_format(mut self, time_format: impl Into<TimeFormat>) -> Self {
+        self.time_format = time_format.into();
+        self
+    }
+
+    /// SHA-256 of the canonical rendering. The record id is not part of it.
+    pub fn digest(&self, record: &DataRecord) -> [u8; 32] {
+        let mut hasher = DigestWriter(Sha256::new());
+        // hashing never fails
+        let _ = self.format_record_into(record, &mut hasher);
+        hasher.0.finalize().into()
+    }
+
+    /// [`JsonCanonical::digest`] as lowercase hex.
+    pub fn digest_hex(&self, record: &DataRecord) -> String {
+        self.digest(record)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    fn write_members<'a>(
+        &self,
+        members: impl Iterator<Item = (&'a str, &'a DataField)>,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        let mut members: Vec<_> = members.collect();
+        members.sort_by(|a, b| utf16_cmp(a.0, b.0));
+        out.write_char('{')?;
+        for (i, (name, field)) in members.into_iter().enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            write_json_string(out, name)?;
+            out.write_char(':')?;
+            self.fmt_value_into(field.get_value(), out)?;
+        }
+        out.write_char('}')
+    }
+}
+
+/// Feeds the rendering straight into the hash, without a buffer.
+struct DigestWriter(Sha256);
+
+impl Write for DigestWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.update(s.as_bytes());
+        Ok(())
+    }
+}
+
+fn utf16_cmp(a: &str, b: &str) -> Ordering {
+    a.encode_utf16().cmp(b.encode_utf16())
+}
+
+/// ECMAScript `Number.prototype.toString` for finite values.
+fn write_es_f64(out: &mut dyn Write, value: f64) -> fmt::Result {
+    if value == 0.0 {
+        return out.write_char('0');
+    }
+    if value < 0.0 {
+        out.write_char('-')?;
+    }
+    // `{:e}` gives the shortest round-trip digits: `d[.ddd]e<exp>`
+    let sci = format!("{:e}", value.abs());
+    let (mantissa, exp) = sci.split_once('e').unwrap_or((&sci, "0"));
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    // decimal point position: value = 0.digits * 10^n
+    let n = exp.parse::<i32>().unwrap_or(0) + 1;
+    if k <= n && n <= 21 {
+        out.write_str(&digits)?;
+        for _ in 0..n - k {
+            out.write_char('0')?;
+        }
+        Ok(())
+    } else if 0 < n && n <= 21 {
+        let (int, frac) = digits.split_at(n as usize);
+        write!(out, "{}.{}", int, frac)
+    } else if -6 < n && n <= 0 {
+        out.write_str("0.")?;
+        for _ in 0..-n {
+            out.write_char('0')?;
+        }
+        out.write_str(&digits)
+    } else {
+        let (first, rest) = digits.split_at(1);
+        out.write_str(first)?;
+        if !rest.is_empty() {
+            write!(out, ".{}", rest)?;
+        }
+        let e = n - 1;
+        write!(out, "e{}{}", if e < 0 { '-' } else { '+' }, e.abs())
+    }
+}
+
+impl StreamDataFormat for JsonCanonical {
+    fn format_null_into(&self, out: &mut dyn Write) -> fmt::Result {
+        out.write_str("null")
+    }
+    fn format_bool_into(&self, v: &bool, out: &mut dyn Write) -> fmt::Result {
+        out.write_str(if *v { "true" } else { "false" })
+    }
+    fn format_string_into(&self, v: &str, out: &mut dyn Write) -> fmt::Result {
+        write_json_string(out, v)
+    }
+    fn format_i64_into(&self, v: &i64, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_f64_into(&self, v: &f64, out: &mut dyn Write) -> fmt::Result {
+        if v.is_nan() {
+            out.write_str("null")
+        } else if v.is_infinite() {
+            out.write_str(if *v > 0.0 {
+                "\"Infinity\""
+            } else {
+                "\"-Infinity\""
+            })
+        } else {
+            write_es_f64(out, *v)
+        }
+    }
+    fn format_ip_into(&self, v: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "\"{}\"", v)
+    }
+    fn format_datetime_into(&self, v: &chrono::NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+        if self.time_format.is_numeric() {
+            self.time_format.write_into(v, out)
+        } else {
+            write_json_string(out, &self.time_format.render(v))
+        }
+    }
+    fn format_object_into(&self, v: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        self.write_members(v.iter().map(|(k, f)| (k.as_str(), f)), out)
+    }
+    fn format_array_into(&self, v: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        out.write_char('[')?;
+        for (i, field) in v.iter().enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            self.fmt_value_into(field.get_value(), out)?;
+        }
+        out.write_char(']')
+    }
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        if !field.get_name().is_empty() {
+            write_json_string(out, field.get_name())?;
+            out.write_char(':')?;
+        }
+        self.fmt_value_into(field.get_value(), out)
+    }
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        self.write_members(
+            record
+                .items
+                .iter()
+                .filter(|f| *f.get_meta() != DataType::Ignore)
+                .map(|f| (f.get_name(), f)),
+            out,
+        )
+    }
+}
+
+impl DataFormat for JsonCanonical {
+    type Output = String;
+    fn format_null(&self) -> String {
+        render(|out| self.format_null_into(out))
+    }
+    fn format_bool(&self, v: &bool) -> String {
+        render(|out| self.format_bool_into(v, out))
+    }
+    fn format_string(&self, v: &str) -> String {
+        render(|out| self.format_string_into(v, out))
+    }
+    fn format_i64(&self, v: &i64) -> String {
+        render(|out| self.format_i64_into(v, out))
+    }
+    fn format_f64(&self, v: &f64) -> String {
+        render(|out| self.format_f64_into(v, out))
+    }
+    fn format_ip(&self, v: &std::net::IpAddr) -> String {
+        render(|out| self.format_ip_into(v, out))
+    }
+    fn format_datetime(&self, v: &chrono::NaiveDateTime) -> String {
+        render(|out| self.format_datetime_into(v, out))
+    }
+    fn format_object(&self, v: &ObjectValue) -> String {
+        render(|out| self.format_object_into(v, out))
+    }
+    fn format_array(&self, v: &[DataField]) -> String {
+        render(|out| self.format_array_into(v, out))
+    }
+    fn format_field(&self, f: &DataField) -> String {
+        render(|out| self.format_field_into(f, out))
+    }
+    fn format_record(&self, r: &DataRecord) -> String {
+        render(|out| self.format_record_into(r, out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wp_model_core::model::Value;
+
+    #[test]
+    fn test_es_numbers() {
+        // RFC 8785 appendix B
+        let cases = [
+            (0.0, "0"),
+            (-0.0, "0"),
+            (1e-7, "1e-7"),
+            (1e21, "1e+21"),
+            (1e20, "100000000000000000000"),
+            (333333333.3333333, "333333333.3333333"),
+            (4.35, "4.35"),
+            (0.000001, "0.000001"),
+            (9007199254740992.0, "9007199254740992"),
+            (1.7976931348623157e308, "1.7976931348623157e+308"),
+            (5e-324, "5e-324"),
+            (-5e-324, "-5e-324"),
+            (295147905179352830000.0, "295147905179352830000"),
+            (-1.5, "-1.5"),
+            (4.0, "4"),
+        ];
+        for (value, expect) in cases {
+            assert_eq!(
+                JsonCanonical::new().format_f64(&value),
+                expect,
+                "{:e}",
+                value
+            );
+        }
+        assert_eq!(JsonCanonical::new().format_f64(&f64::NAN), "null");
+    }
+
+    #[test]
+    fn test_utf16_member_order() {
+        // RFC 8785 section 3.2.3
+        let keys = [
+            "\u{20ac}",
+            "\r",
+            "\u{fb33}",
+            "1",
+            "\u{1f600}",
+            "\u{80}",
+            "\u{f6}",
+        ];
+        let mut obj = ObjectValue::new();
+        for (i, k) in keys.iter().enumerate() {
+            obj.insert(*k, DataField::from_digit(*k, i as i64));
+        }
+        assert_eq!(
+            JsonCanonical::new().format_object(&obj),
+            "{\"\\r\":1,\"1\":3,\"\u{80}\":5,\"\u{f6}\":6,\"\u{20ac}\":0,\"\u{1f600}\":4,\"\u{fb33}\":2}"
+        );
+    }
+
+    #[test]
+    fn test_digest_is_order_and_meta_independent() {
+        let a = DataRecord {
+            id: 1,
+            items: vec![
+                DataField::from_chars("b", "x"),
+                DataField::from_float("a", 1.0),
+                DataField::from_ignore("skip"),
+            ],
+        };
+        let b = DataRecord {
+            id: 2,
+            items: vec![
+                DataField::from_digit("a", 1),
+                DataField::new(DataType::Symbol, "b", Value::Chars("x".into())),
+            ],
+        };
+        let canonical = JsonCanonical::new();
+        assert_eq!(canonical.format_record(&a), r#"{"a":1,"b":"x"}"#);
+        assert_eq!(canonical.format_record(&b), r#"{"a":1,"b":"x"}"#);
+        assert_eq!(canonical.digest(&a), canonical.digest(&b));
+        assert_eq!(
+            canonical.digest_hex(&a),
+            "ecf9e98ec0641e23113ff3ce8bdc78d0ddd249886517fd4a7f68cc83d4e65667"
+        );
+    }
+}
@@ -1,11 +1,14 @@
-use crate::formatter::DataFormat;
-use std::fmt::Write;
+use crate::formatter::{DataFormat, StreamDataFormat, render};
+use crate::parse::{Cursor, DataParse, Node, ParseError, ParseHints, build_record};
+use crate::time_fmt::TimeFormat;
+use std::fmt::{self, Write};
 use wp_model_core::model::{DataField, DataRecord, DataType, types::value::ObjectValue};
 
 pub struct KeyValue {
     pair_separator: String,
     key_value_separator: String,
     quote_strings: bool,
+    time_format: TimeFormat,
 }
 
 impl Default for KeyValue {
@@ -14,6 +17,7 @@ impl Default for KeyValue {
             pair_separator: ", ".to_string(),
             key_value_separator: ": ".to_string(),
             quote_strings: true,
+            time_format: TimeFormat::default(),
         }
     }
 }
@@ -34,13 +38,111 @@ impl KeyValue {
         self.quote_strings = quote;
         self
     }
+    pub fn with_time_format(mut self, time_format: impl Into<TimeFormat>) -> Self {
+        self.time_format = time_format.into();
+        self
+    }
+
+    fn write_record<'a>(
+        &self,
+        fields: impl Iterator<Item = (&'a str, &'a DataField)>,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        let mut first = true;
+        for (name, field) in fields.filter(|(_, f)| *f.get_meta() != DataType::Ignore) {
+            if !first {
+                out.write_str(&self.pair_separator)?;
+            }
+            first = false;
+            self.write_pair(name, field, out)?;
+        }
+        Ok(())
+    }
+
+    fn write_pair(&self, name: &str, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        out.write_str(name)?;
+        out.write_str(&self.key_value_separator)?;
+        self.fmt_value_into(field.get_value(), out)
+    }
 
-    fn format_string_value(&self, value: &str) -> String {
+    fn format_string_value(&self, value: &str, out: &mut dyn Write) -> fmt::Result {
         if self.quote_strings {
-            format!("\"{}\"", value.replace('\"', "\\\""))
+            out.write_char('"')?;
+            let mut rest = value;
+            while let Some(pos) = rest.find(['"', '\\']) {
+                out.write_str(&rest[..pos])?;
+                out.write_char('\\')?;
+                out.write_str(&rest[pos..pos + 1])?;
+                rest = &rest[pos + 1..];
+            }
+            out.write_str(rest)?;
+            out.write_char('"')
         } else {
-            value.to_string()
+            out.write_str(value)
+        }
+    }
+}
+
+impl StreamDataFormat for KeyValue {
+    fn format_null_into(&self, _out: &mut dyn Write) -> fmt::Result {
+        Ok(())
+    }
+    fn format_bool_into(&self, v: &bool, out: &mut dyn Write) -> fmt::Result {
+        out.write_str(if *v { "true" } else { "false" })
+    }
+    fn format_string_into(&self, v: &str, out: &mut dyn Write) -> fmt::Result {
+        self.format_string_value(v, out)
+    }
+    fn format_i64_into(&self, v: &i64, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_f64_into(&self, v: &f64, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_ip_into(&self, v: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_datetime_into(&self, v: &chrono::NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+        self.time_format.write_into(v, out)
+    }
+
+    fn format_object_into(&self, value: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        out.write_char('{')?;
+        for (i, (k, v)) in value.iter().enumerate() {
+            if i > 0 {
+                out.write_str(&self.pair_separator)?;
+            }
+            self.format_string_into(k, out)?;
+            out.write_str(&self.key_value_separator)?;
+            self.fmt_value_into(v.get_value(), out)?;
         }
+        out.write_char('}')
+    }
+
+    fn format_array_into(&self, value: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        out.write_char('[')?;
+        for (i, field) in value.iter().enumerate() {
+            if i > 0 {
+                out.write_str(&self.pair_separator)?;
+            }
+            self.fmt_value_into(field.get_value(), out)?;
+        }
+        out.write_char(']')
+    }
+
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        self.write_pair(field.get_name(), field, out)
+    }
+
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        self.write_record(record.items.iter().map(|f| (f.get_name(), f)), out)
+    }
+    fn format_fields_into(
+        &self,
+        fields: &[(&str, &DataField)],
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        self.write_record(fields.iter().copied(), out)
     }
 }
 
@@ -54,7 +156,7 @@ impl DataFormat for KeyValue {
         if *v { "true".into() } else { "false".into() }
     }
     fn format_string(&self, v: &str) -> String {
-        self.format_string_value(v)
+        render(|out| self.format_string_into(v, out))
     }
     fn format_i64(&self, v: &i64) -> String {
         v.to_string()
@@ -66,59 +168,121 @@ impl DataFormat for KeyValue {
         v.to_string()
     }
     fn format_datetime(&self, v: &chrono::NaiveDateTime) -> String {
-        v.to_string()
+        self.time_format.render(v)
     }
 
     fn format_object(&self, value: &ObjectValue) -> String {
-        let mut output = String::new();
-        output.push('{');
-        for (i, (k, v)) in value.iter().enumerate() {
-            if i > 0 {
-                output.push_str(&self.pair_separator);
-            }
-            write!(
-                output,
-                "{}{}{}",
-                self.format_string(k),
-                self.key_value_separator,
-                self.fmt_value(v.get_value())
-            )
-            .unwrap();
-        }
-        output.push('}');
-        output
+        render(|out| self.format_object_into(value, out))
     }
 
     fn format_array(&self, value: &[DataField]) -> String {
-        let mut output = String::new();
-        output.push('[');
-        for (i, field) in value.iter().enumerate() {
-            if i > 0 {
-                output.push_str(&self.pair_separator);
-            }
-            output.push_str(&self.fmt_value(field.get_value()));
-        }
-        output.push(']');
-        output
+        render(|out| self.format_array_into(value, out))
     }
 
     fn format_field(&self, field: &DataField) -> String {
-        format!(
-            "{}{}{}",
-            field.get_name(),
-            self.key_value_separator,
-            self.fmt_value(field.get_value())
-        )
+        render(|out| self.format_field_into(field, out))
     }
 
     fn format_record(&self, record: &DataRecord) -> String {
-        record
-            .items
+        render(|out| self.format_record_into(record, out))
+    }
+    fn format_fields(&self, fields: &[(&str, &DataField)]) -> String {
+        render(|out| self.format_fields_into(fields, out))
+    }
+}
+
+impl DataParse for KeyValue {
+    /// Reads `key<kv-sep>value` pairs split by the pair separator. With
+    /// quoting off, text cannot contain a separator, and empty text reads
+    /// as `Null`.
+    fn parse_record_with(&self, input: &str, hints: &ParseHints) -> Result<DataRecord, ParseError> {
+        let mut cur = Cursor::new(input);
+        let mut entries = Vec::new();
+        while !cur.is_eof() {
+            let key = match cur.rest().find(self.key_value_separator.as_str()) {
+                Some(end) => cur.rest()[..end].to_string(),
+                None => return Err(cur.error("expected a key")),
+            };
+            cur.eat(&key);
+            cur.eat(&self.key_value_separator);
+            entries.push((key, self.read_value(&mut cur, &[&self.pair_separator])?));
+            if !cur.is_eof() {
+                cur.expect(&self.pair_separator)?;
+            }
+        }
+        build_record(entries, hints, &self.time_format)
+    }
+}
+
+impl KeyValue {
+    fn read_value(&self, cur: &mut Cursor, stops: &[&str]) -> Result<Node, ParseError> {
+        if self.quote_strings && cur.peek() == Some('"') {
+            return self.read_quoted(cur).map(Node::Str);
+        }
+        if cur.eat("{") {
+            let mut entries = Vec::new();
+            if cur.eat("}") {
+                return Ok(Node::Obj(entries));
+            }
+            loop {
+                let key = if self.quote_strings {
+                    self.read_quoted(cur)?
+                } else {
+                    let rest = cur.rest();
+                    let end = rest
+                        .find(self.key_value_separator.as_str())
+                        .ok_or_else(|| cur.error("expected a key"))?;
+                    cur.eat(&rest[..end]);
+                    rest[..end].to_string()
+                };
+                cur.expect(&self.key_value_separator)?;
+                entries.push((key, self.read_value(cur, &[&self.pair_separator, "}"])?));
+                if cur.eat("}") {
+                    return Ok(Node::Obj(entries));
+                }
+                cur.expect(&self.pair_separator)?;
+            }
+        }
+        if cur.eat("[") {
+            let mut items = Vec::new();
+            if cur.eat("]") {
+                return Ok(Node::Arr(items));
+            }
+            loop {
+                items.push(self.read_value(cur, &[&self.pair_separator, "]"])?);
+                if cur.eat("]") {
+                    return Ok(Node::Arr(items));
+                }
+                cur.expect(&self.pair_separator)?;
+            }
+        }
+        let rest = cur.rest();
+        let end = stops
             .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-            .map(|field| self.format_field(field))
-            .collect::<Vec<_>>()
-            .join(&self.pair_separator)
+            .filter_map(|stop| rest.find(stop))
+            .min()
+            .unwrap_or(rest.len());
+        cur.eat(&rest[..end]);
+        Ok(Node::Bare(rest[..end].to_string()))
+    }
+
+    /// Inverse of `format_string_value`: `"` and `\` are escaped. A `\`
+    /// before anything else is kept as is.
+    fn read_quoted(&self, cur: &mut Cursor) -> Result<String, ParseError> {
+        cur.expect("\"")?;
+        let mut s = String::new();
+        loop {
+            s.push_str(cur.take_until(|c| c == '"' || c == '\\'));
+            if cur.eat("\\\"") {
+                s.push('"');
+            } else if cur.eat("\\\\") || cur.eat("\\") {
+                s.push('\\');
+            } else if cur.eat("\"") {
+                return Ok(s);
+            } else {
+                return Err(cur.error("unterminated string"));
+            }
+        }
     }
 }
 
@@ -236,6 +400,7 @@ mod tests {
     fn test_format_record() {
         let kv = KeyValue::default();
         let record = DataRecord {
+            id: 0,
             items: vec![
                 DataField::from_chars("name", "Alice"),
                 DataField::from_digit("age", 30),
@@ -254,6 +419,7 @@ mod tests {
             .with_key_value_separator("=")
             .with_quote_strings(false);
         let record = DataRecord {
+            id: 0,
             items: vec![
                 DataField::from_chars("a", "x"),
                 DataField::from_chars("b", "y"),
@@ -273,4 +439,41 @@ mod tests {
         assert!(result.contains("1"));
         assert!(result.contains("2"));
     }
+
+    #[test]
+    fn test_kv_parse_quoting() {
+        use crate::parse::DataParse;
+        let kv = KeyValue::new();
+        let r = kv
+            .parse_record(r#"a: "say \"hi\", ok", b: , c: {"k": [1, 2]}"#)
+            .unwrap();
+        assert_eq!(r.items[0], DataField::from_chars("a", "say \"hi\", ok"));
+        assert_eq!(*r.items[1].get_value(), wp_model_core::model::Value::Null);
+        assert_eq!(
+            kv.format_record(&r),
+            r#"a: "say \"hi\", ok", b: , c: {"k": [1, 2]}"#
+        );
+        assert!(kv.parse_record(r#"a: "open"#).is_err());
+        assert!(kv.parse_record("no separator").is_err());
+    }
+
+    #[test]
+    fn test_kv_backslashes_round_trip() {
+        use crate::parse::DataParse;
+        let kv = KeyValue::new();
+        for v in ["C:\\temp\\", "a\\\"b", "\\", "end\\"] {
+            let r = DataRecord {
+                id: 0,
+                items: vec![DataField::from_chars("p", v)],
+            };
+            let out = kv.format_record(&r);
+            assert_eq!(
+                kv.parse_record(&out).unwrap().items[0],
+                r.items[0],
+                "{}",
+                out
+            );
+        }
+        assert_eq!(kv.format_string("a\\b"), r#""a\\b""#);
+    }
 }
@@ -0,0 +1,331 @@
+use crate::cef::{HeaderSource, for_each_leaf, write_pipe_header};
+use crate::formatter::{DataFormat, StreamDataFormat, render};
+use chrono::NaiveDateTime;
+use std::fmt::{self, Write};
+use wp_model_core::model::{DataField, DataRecord, DataType, types::value::ObjectValue};
+
+const DEV_TIME_FORMAT: &str = "MMM dd yyyy HH:mm:ss.SSS";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LeefVersion {
+    /// Attributes are always tab separated.
+    V1,
+    /// The attribute delimiter is declared in the header.
+    #[default]
+    V2,
+}
+
+/// IBM QRadar Log Event Extended Format:
+/// `LEEF:2.0|Vendor|Product|Version|EventID|Delim|k=v<delim>k=v`
+/// (`LEEF:1.0` has no delimiter slot and always uses a tab).
+///
+/// Fields used by the header are left out of the attributes. The time field
+/// (`time` by default) is written as `devTime` together with the matching
+/// `devTimeFormat`. Nested values are flattened with `.`.
+#[derive(Debug, Clone)]
+pub struct Leef {
+    version: LeefVersion,
+    delimiter: char,
+    vendor: HeaderSource,
+    product: HeaderSource,
+    product_version: HeaderSource,
+    event_id: HeaderSource,
+    time_field: Option<String>,
+}
+
+impl Default for Leef {
+    fn default() -> Self {
+        Self {
+            version: LeefVersion::V2,
+            delimiter: '\t',
+            vendor: HeaderSource::fixed("WarpParse"),
+            product: HeaderSource::fixed("warp-parse"),
+            product_version: HeaderSource::fixed(env!("CARGO_PKG_VERSION")),
+            event_id: HeaderSource::fixed("0"),
+            time_field: Some("time".to_string()),
+        }
+    }
+}
+
+impl Leef {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_version(mut self, version: LeefVersion) -> Self {
+        self.version = version;
+        self
+    }
+    /// Attribute delimiter for LEEF 2.0; ignored by 1.0.
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+    pub fn with_vendor(mut self, src: impl Into<HeaderSource>) -> Self {
+        self.vendor = src.into();
+        self
+    }
+    pub fn with_product(mut self, src: impl Into<HeaderSource>) -> Self {
+        self.product = src.into();
+        self
+    }
+    pub fn with_product_version(mut self, src: impl Into<HeaderSource>) -> Self {
+        self.product_version = src.into();
+        self
+    }
+    pub fn with_event_id(mut self, src: impl Into<HeaderSource>) -> Self {
+        self.event_id = src.into();
+        self
+    }
+    /// Field written as `devTime`; `None` keeps every field under its own name.
+    pub fn with_time_field(mut self, name: Option<String>) -> Self {
+        self.time_field = name;
+        self
+    }
+
+    fn delimiter(&self) -> char {
+        match self.version {
+            LeefVersion::V1 => '\t',
+            LeefVersion::V2 => self.delimiter,
+        }
+    }
+
+    fn headers(&self) -> [&HeaderSource; 4] {
+        [
+            &self.vendor,
+            &self.product,
+            &self.product_version,
+            &self.event_id,
+        ]
+    }
+
+    fn is_attribute(&self, field: &DataField) -> bool {
+        *field.get_meta() != DataType::Ignore
+            && !self
+                .headers()
+                .iter()
+                .any(|h| h.field_name() == Some(field.get_name()))
+    }
+
+    fn write_attribute(
+        &self,
+        field: &DataField,
+        first: &mut bool,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        let delimiter = self.delimiter();
+        let is_time = self.time_field.as_deref() == Some(field.get_name());
+        let mut prefix = if is_time {
+            "devTime".to_string()
+        } else {
+            field.get_name().to_string()
+        };
+        for_each_leaf(&mut prefix, ".", field.get_value(), &mut |key, value| {
+            if !*first {
+                out.write_char(delimiter)?;
+            }
+            *first = false;
+            for c in key.chars() {
+                let bad = c == '=' || c == delimiter || c.is_whitespace() || c.is_control();
+                out.write_char(if bad { '_' } else { c })?;
+            }
+            out.write_char('=')?;
+            self.fmt_value_into(value, out)
+        })?;
+        if is_time && !*first {
+            write!(out, "{}devTimeFormat={}", delimiter, DEV_TIME_FORMAT)?;
+        }
+        Ok(())
+    }
+}
+
+/// Scalar methods render attribute values: `\`, line breaks and the
+/// delimiter are escaped.
+impl StreamDataFormat for Leef {
+    fn format_null_into(&self, _out: &mut dyn Write) -> fmt::Result {
+        Ok(())
+    }
+    fn format_bool_into(&self, v: &bool, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_string_into(&self, v: &str, out: &mut dyn Write) -> fmt::Result {
+        let delimiter = self.delimiter();
+        for c in v.chars() {
+            match c {
+                '\\' => out.write_str("\\\\")?,
+                '\n' => out.write_str("\\n")?,
+                '\r' => out.write_str("\\r")?,
+                '\t' => out.write_str("\\t")?,
+                c if c == delimiter => {
+                    out.write_char('\\')?;
+                    out.write_char(c)?;
+                }
+                c => out.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+    fn format_i64_into(&self, v: &i64, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_f64_into(&self, v: &f64, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_ip_into(&self, v: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    /// Matches `devTimeFormat` (`MMM dd yyyy HH:mm:ss.SSS`).
+    fn format_datetime_into(&self, v: &NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v.format("%b %d %Y %H:%M:%S%.3f"))
+    }
+    fn format_object_into(&self, value: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        let mut first = true;
+        for field in value.values() {
+            self.write_attribute(field, &mut first, out)?;
+        }
+        Ok(())
+    }
+    fn format_array_into(&self, value: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        for (i, field) in value.iter().enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            self.fmt_value_into(field.get_value(), out)?;
+        }
+        Ok(())
+    }
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        let mut first = true;
+        self.write_attribute(field, &mut first, out)
+    }
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        out.write_str(match self.version {
+            LeefVersion::V1 => "LEEF:1.0",
+            LeefVersion::V2 => "LEEF:2.0",
+        })?;
+        for header in self.headers() {
+            out.write_char('|')?;
+            write_pipe_header(out, &header.resolve(record))?;
+        }
+        out.write_char('|')?;
+        if self.version == LeefVersion::V2 {
+            // control and separator characters are declared in hex form
+            let d = self.delimiter;
+            if d.is_ascii_graphic() && d != '|' {
+                out.write_char(d)?;
+            } else {
+                write!(out, "x{:02X}", d as u32)?;
+            }
+            out.write_char('|')?;
+        }
+        let mut first = true;
+        for field in record.items.iter().filter(|f| self.is_attribute(f)) {
+            self.write_attribute(field, &mut first, out)?;
+        }
+        Ok(())
+    }
+}
+
+impl DataFormat for Leef {
+    type Output = String;
+    fn format_null(&self) -> String {
+        String::new()
+    }
+    fn format_bool(&self, v: &bool) -> String {
+        v.to_string()
+    }
+    fn format_string(&self, v: &str) -> String {
+        render(|out| self.format_string_into(v, out))
+    }
+    fn format_i64(&self, v: &i64) -> String {
+        v.to_string()
+    }
+    fn format_f64(&self, v: &f64) -> String {
+        v.to_string()
+    }
+    fn format_ip(&self, v: &std::net::IpAddr) -> String {
+        v.to_string()
+    }
+    fn format_datetime(&self, v: &NaiveDateTime) -> String {
+        render(|out| self.format_datetime_into(v, out))
+    }
+    fn format_object(&self, value: &ObjectValue) -> String {
+        render(|out| self.format_object_into(value, out))
+    }
+    fn format_array(&self, value: &[DataField]) -> String {
+        render(|out| self.format_array_into(value, out))
+    }
+    fn format_field(&self, field: &DataField) -> String {
+        render(|out| self.format_field_into(field, out))
+    }
+    fn format_record(&self, record: &DataRecord) -> String {
+        render(|out| self.format_record_into(record, out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(items: Vec<DataField>) -> DataRecord {
+        DataRecord { id: 0, items }
+    }
+
+    fn leef() -> Leef {
+        Leef::new()
+            .with_vendor("Acme")
+            .with_product("fw")
+            .with_product_version("1.0")
+            .with_event_id(HeaderSource::field("event"))
+    }
+
+    #[test]
+    fn test_leef1_tab_separated() {
+        let r = rec(vec![
+            DataField::from_chars("event", "deny"),
+            DataField::from_chars("usrName", "bob"),
+            DataField::from_digit("dstPort", 22),
+        ]);
+        let f = leef().with_version(LeefVersion::V1);
+        assert_eq!(
+            f.format_record(&r),
+            "LEEF:1.0|Acme|fw|1.0|deny|usrName=bob\tdstPort=22"
+        );
+    }
+
+    #[test]
+    fn test_leef2_delimiter() {
+        let r = rec(vec![
+            DataField::from_chars("event", "deny"),
+            DataField::from_chars("msg", "a^b"),
+            DataField::from_digit("n", 1),
+        ]);
+        let f = leef().with_delimiter('^');
+        assert_eq!(
+            f.format_record(&r),
+            "LEEF:2.0|Acme|fw|1.0|deny|^|msg=a\\^b^n=1"
+        );
+        let f = leef();
+        assert_eq!(
+            f.format_record(&r),
+            "LEEF:2.0|Acme|fw|1.0|deny|x09|msg=a^b\tn=1"
+        );
+    }
+
+    #[test]
+    fn test_value_escaping() {
+        assert_eq!(leef().format_string("a\tb\\c\nd"), "a\\tb\\\\c\\nd");
+    }
+
+    #[test]
+    fn test_dev_time() {
+        let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+        let r = rec(vec![
+            DataField::from_chars("event", "allow"),
+            DataField::from_time("time", ts),
+        ]);
+        assert_eq!(
+            leef().with_delimiter('|').format_record(&r),
+            "LEEF:2.0|Acme|fw|1.0|allow|x7C|devTime=Aug 06 2019 12:12:19.000|devTimeFormat=MMM dd yyyy HH:mm:ss.SSS"
+        );
+    }
+}
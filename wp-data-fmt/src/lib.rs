@@ -1,42 +1,103 @@
+mod cef;
 mod csv;
+mod es_bulk;
 pub mod fmt_meta;
+mod format_spec;
 mod formatter;
+mod gelf;
 mod json;
+mod json_canonical;
 mod kv;
+mod leef;
+mod logfmt;
+mod mapper;
+mod nested;
+mod parse;
 mod proto;
+mod protobuf;
 mod raw;
+mod redact;
+mod severity;
 mod sql;
+mod sql_copy;
+mod sql_dialect;
+mod sql_schema;
+mod syslog;
+mod table;
+mod template;
+mod time_fmt;
+mod timestamp;
 
-pub use csv::Csv;
-pub use formatter::{DataFormat, StaticDataFormatter};
-pub use json::Json;
+pub use cef::{Cef, HeaderSource};
+pub use csv::{Csv, CsvWriter};
+pub use es_bulk::{BulkAction, EsBulk};
+pub use format_spec::FormatSpec;
+pub use formatter::{DataFormat, StaticDataFormatter, StreamDataFormat};
+pub use gelf::{GELF_WAN_CHUNK_SIZE, Gelf, GelfReserved};
+pub use json::{Json, LineEnding};
+pub use json_canonical::JsonCanonical;
 pub use kv::KeyValue;
+pub use leef::{Leef, LeefVersion};
+pub use logfmt::Logfmt;
+pub use mapper::{ConstValue, FieldMapper, Mapped};
+pub use nested::{NestConflict, Nesting};
+pub use parse::{DataParse, ParseError, ParseHints};
 pub use proto::ProtoTxt;
+pub use protobuf::{ProtoBin, ProtoFieldDef, ProtoKind, ProtoSchema};
 pub use raw::Raw;
-pub use sql::SqlInsert;
+pub use redact::{RedactAction, RedactPolicy, RedactRule, Redacted};
+pub use sql::{SqlBatch, SqlInsert, SqlMigration, SqlParam, SqlStatement};
+pub use sql_copy::{CopyFormat, SqlCopy};
+pub use sql_dialect::SqlDialect;
+pub use sql_schema::{ColumnKind, SchemaChange, SqlColumn, SqlSchema};
+pub use syslog::{Syslog5424, SyslogFraming};
+pub use table::{Table, TableStyle};
+pub use template::Template;
+pub use time_fmt::{TimeFormat, TimeStyle};
+pub use timestamp::{ParsedTimestamp, TimestampFormat, TimestampParser};
 
+use wp_model_core::model::DataRecord;
 use wp_model_core::model::fmt_def::TextFmt;
 
 pub enum FormatType {
     Json(Json),
     Csv(Csv),
     Kv(KeyValue),
+    Logfmt(Logfmt),
     Sql(SqlInsert),
     Raw(Raw),
     ProtoText(ProtoTxt),
+    Template(Template),
+    /// Human-readable preview; [`Table::format_batch`] draws a batch.
+    Table(Table),
+    /// Binary protobuf, written by [`FormatType::format_record_bytes`] and
+    /// `format_record_to_io`. The text methods render the same bytes as
+    /// lowercase hex; check [`FormatType::is_binary`] to tell them apart.
+    Proto(ProtoBin),
+}
+
+impl FormatType {
+    /// Whether the format produces bytes that are not valid text.
+    pub fn is_binary(&self) -> bool {
+        matches!(self, FormatType::Proto(_))
+    }
+
+    /// Renders a record as bytes: wire format for [`FormatType::Proto`],
+    /// UTF-8 text for every other format. Only [`FormatType::Proto`] can
+    /// fail, see [`ProtoBin::try_format_record`].
+    pub fn format_record_bytes(&self, record: &DataRecord) -> Result<Vec<u8>, String> {
+        match self {
+            FormatType::Proto(f) => f.try_format_record(record),
+            _ => Ok(self.format_record(record).into_bytes()),
+        }
+    }
 }
 
 impl From<&TextFmt> for FormatType {
     fn from(fmt: &TextFmt) -> Self {
-        match fmt {
-            TextFmt::Json => FormatType::Json(Json),
-            TextFmt::Csv => FormatType::Csv(Csv::default()),
-            TextFmt::Kv => FormatType::Kv(KeyValue::default()),
-            TextFmt::Raw => FormatType::Raw(Raw),
-            TextFmt::ProtoText => FormatType::ProtoText(ProtoTxt),
-            TextFmt::Show => FormatType::Raw(Raw),
-            TextFmt::Proto => FormatType::ProtoText(ProtoTxt),
-        }
+        FormatSpec::from(fmt)
+            .make()
+            .expect("TextFmt specs use the default options")
     }
 }
 
@@ -50,11 +111,11 @@ pub enum SqlFormat {
 impl From<&TextFmt> for SqlFormat {
     fn from(fmt: &TextFmt) -> Self {
         match fmt {
-            TextFmt::Json => SqlFormat::Json(Json),
+            TextFmt::Json => SqlFormat::Json(Json::new()),
             TextFmt::Kv => SqlFormat::Kv(KeyValue::default()),
-            TextFmt::Raw => SqlFormat::Raw(Raw),
-            TextFmt::ProtoText => SqlFormat::ProtoText(ProtoTxt),
-            _ => SqlFormat::Raw(Raw),
+            TextFmt::Raw => SqlFormat::Raw(Raw::new()),
+            TextFmt::ProtoText => SqlFormat::ProtoText(ProtoTxt::new()),
+            _ => SqlFormat::Raw(Raw::new()),
         }
     }
 }
@@ -62,8 +123,8 @@ impl From<&TextFmt> for SqlFormat {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use formatter::DataFormat;
-    use wp_model_core::model::{DataField, DataRecord};
+    use formatter::{DataFormat, StreamDataFormat};
+    use wp_model_core::model::DataField;
 
     #[test]
     fn test_format_type_from_text_fmt_json() {
@@ -98,13 +159,53 @@ mod tests {
     #[test]
     fn test_format_type_from_text_fmt_show() {
         let fmt = FormatType::from(&TextFmt::Show);
-        matches!(fmt, FormatType::Raw(_));
+        assert!(matches!(fmt, FormatType::Table(_)));
     }
 
     #[test]
     fn test_format_type_from_text_fmt_proto() {
         let fmt = FormatType::from(&TextFmt::Proto);
-        matches!(fmt, FormatType::ProtoText(_));
+        matches!(fmt, FormatType::Proto(_));
+    }
+
+    #[test]
+    fn test_format_type_proto_bytes() {
+        let fmt = FormatType::from(&TextFmt::Proto);
+        assert!(fmt.is_binary());
+        let record = DataRecord {
+            id: 0,
+            items: vec![DataField::from_digit("a", 150)],
+        };
+        assert_eq!(
+            fmt.format_record_bytes(&record).unwrap(),
+            vec![0x08, 0x96, 0x01]
+        );
+        let mut text = String::new();
+        fmt.format_record_into(&record, &mut text).unwrap();
+        assert_eq!(text, "089601");
+        assert_eq!(fmt.format_record(&record), "089601");
+
+        let mut io_out: Vec<u8> = Vec::new();
+        fmt.format_record_to_io(&record, &mut io_out).unwrap();
+        assert_eq!(io_out, vec![0x08, 0x96, 0x01]);
+
+        let json = FormatType::from(&TextFmt::Json);
+        assert!(!json.is_binary());
+        assert_eq!(
+            json.format_record_bytes(&record).unwrap(),
+            b"{\"a\":150}".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_format_type_logfmt() {
+        let fmt = FormatType::Logfmt(Logfmt::new());
+        let record = DataRecord {
+            id: 0,
+            items: vec![DataField::from_chars("msg", "hello world")],
+        };
+        assert_eq!(fmt.format_record(&record), "msg=\"hello world\"");
+        assert!(!fmt.is_binary());
     }
 
     #[test]
@@ -183,6 +284,7 @@ mod tests {
     fn test_format_type_format_record() {
         let json_fmt = FormatType::from(&TextFmt::Json);
         let record = DataRecord {
+            id: 0,
             items: vec![
                 DataField::from_chars("name", "Alice"),
                 DataField::from_digit("age", 30),
@@ -207,6 +309,7 @@ mod tests {
     fn test_csv_format_type() {
         let csv_fmt = FormatType::from(&TextFmt::Csv);
         let record = DataRecord {
+            id: 0,
             items: vec![
                 DataField::from_chars("a", "x"),
                 DataField::from_chars("b", "y"),
@@ -220,6 +323,7 @@ mod tests {
     fn test_kv_format_type() {
         let kv_fmt = FormatType::from(&TextFmt::Kv);
         let record = DataRecord {
+            id: 0,
             items: vec![DataField::from_chars("name", "Alice")],
         };
         let result = kv_fmt.format_record(&record);
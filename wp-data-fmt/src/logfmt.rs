@@ -0,0 +1,331 @@
+use crate::formatter::{DataFormat, StreamDataFormat, render};
+use crate::time_fmt::TimeFormat;
+use std::fmt::{self, Write};
+use wp_model_core::model::{DataField, DataRecord, DataType, Value, types::value::ObjectValue};
+
+/// logfmt output: `key=value` pairs separated by single spaces.
+///
+/// Values are quoted only when they are empty or contain spaces, `=`, `"`,
+/// `\` or control characters. Nested objects and arrays are flattened into
+/// dotted keys (`upstream.addr=..`, `tags.0=..`). Characters that would
+/// break a key (whitespace, `=`, `"`, controls) are replaced with `_`.
+#[derive(Debug, Clone)]
+pub struct Logfmt {
+    key_separator: String,
+    time_format: TimeFormat,
+}
+
+impl Default for Logfmt {
+    fn default() -> Self {
+        Self {
+            key_separator: ".".to_string(),
+            time_format: TimeFormat::default(),
+        }
+    }
+}
+
+impl Logfmt {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Separator used when flattening nested keys; `.` by default.
+    pub fn with_key_separator(mut self, s: impl Into<String>) -> Self {
+        self.key_separator = s.into();
+        self
+    }
+    pub fn with_time_format(mut self, time_format: impl Into<TimeFormat>) -> Self {
+        self.time_format = time_format.into();
+        self
+    }
+
+    fn write_key(&self, key: &str, out: &mut dyn Write) -> fmt::Result {
+        if key.is_empty() {
+            return out.write_char('_');
+        }
+        for c in key.chars() {
+            if c == '=' || c == '"' || c.is_whitespace() || c.is_control() {
+                out.write_char('_')?;
+            } else {
+                out.write_char(c)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_record<'a>(
+        &self,
+        fields: impl Iterator<Item = (&'a str, &'a DataField)>,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        let mut first = true;
+        let mut prefix = String::new();
+        for (name, field) in fields.filter(|(_, f)| *f.get_meta() != DataType::Ignore) {
+            prefix.clear();
+            prefix.push_str(name);
+            self.write_pairs(&mut prefix, field.get_value(), &mut first, out)?;
+        }
+        Ok(())
+    }
+
+    /// Writes every leaf under `value` as a `prefix.key=value` pair.
+    fn write_pairs(
+        &self,
+        prefix: &mut String,
+        value: &Value,
+        first: &mut bool,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        match value {
+            Value::Obj(obj) => {
+                for (k, f) in obj.iter() {
+                    self.write_nested(prefix, k, f.get_value(), first, out)?;
+                }
+                Ok(())
+            }
+            Value::Array(items) => {
+                for (i, f) in items.iter().enumerate() {
+                    self.write_nested(prefix, &i.to_string(), f.get_value(), first, out)?;
+                }
+                Ok(())
+            }
+            _ => {
+                if !*first {
+                    out.write_char(' ')?;
+                }
+                *first = false;
+                self.write_key(prefix, out)?;
+                out.write_char('=')?;
+                self.fmt_value_into(value, out)
+            }
+        }
+    }
+
+    fn write_nested(
+        &self,
+        prefix: &mut String,
+        key: &str,
+        value: &Value,
+        first: &mut bool,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        let len = prefix.len();
+        if !prefix.is_empty() {
+            prefix.push_str(&self.key_separator);
+        }
+        prefix.push_str(key);
+        let res = self.write_pairs(prefix, value, first, out);
+        prefix.truncate(len);
+        res
+    }
+}
+
+fn needs_quotes(value: &str) -> bool {
+    value.is_empty()
+        || value
+            .chars()
+            .any(|c| c == ' ' || c == '=' || c == '"' || c == '\\' || c.is_control())
+}
+
+impl StreamDataFormat for Logfmt {
+    fn format_null_into(&self, _out: &mut dyn Write) -> fmt::Result {
+        Ok(())
+    }
+    fn format_bool_into(&self, v: &bool, out: &mut dyn Write) -> fmt::Result {
+        out.write_str(if *v { "true" } else { "false" })
+    }
+    fn format_string_into(&self, v: &str, out: &mut dyn Write) -> fmt::Result {
+        if !needs_quotes(v) {
+            return out.write_str(v);
+        }
+        out.write_char('"')?;
+        for c in v.chars() {
+            match c {
+                '"' => out.write_str("\\\"")?,
+                '\\' => out.write_str("\\\\")?,
+                '\n' => out.write_str("\\n")?,
+                '\r' => out.write_str("\\r")?,
+                '\t' => out.write_str("\\t")?,
+                c if c.is_control() => write!(out, "\\u{:04x}", c as u32)?,
+                c => out.write_char(c)?,
+            }
+        }
+        out.write_char('"')
+    }
+    fn format_i64_into(&self, v: &i64, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_f64_into(&self, v: &f64, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_ip_into(&self, v: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_datetime_into(&self, v: &chrono::NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+        self.format_string_into(&self.time_format.render(v), out)
+    }
+    fn format_object_into(&self, value: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        let mut first = true;
+        for (k, f) in value.iter() {
+            self.write_pairs(&mut k.to_string(), f.get_value(), &mut first, out)?;
+        }
+        Ok(())
+    }
+    fn format_array_into(&self, value: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        let mut first = true;
+        for (i, f) in value.iter().enumerate() {
+            self.write_pairs(&mut i.to_string(), f.get_value(), &mut first, out)?;
+        }
+        Ok(())
+    }
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        let mut first = true;
+        self.write_pairs(
+            &mut field.get_name().to_string(),
+            field.get_value(),
+            &mut first,
+            out,
+        )
+    }
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        self.write_record(record.items.iter().map(|f| (f.get_name(), f)), out)
+    }
+    fn format_fields_into(
+        &self,
+        fields: &[(&str, &DataField)],
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        self.write_record(fields.iter().copied(), out)
+    }
+}
+
+impl DataFormat for Logfmt {
+    type Output = String;
+    fn format_null(&self) -> String {
+        String::new()
+    }
+    fn format_bool(&self, v: &bool) -> String {
+        render(|out| self.format_bool_into(v, out))
+    }
+    fn format_string(&self, v: &str) -> String {
+        render(|out| self.format_string_into(v, out))
+    }
+    fn format_i64(&self, v: &i64) -> String {
+        v.to_string()
+    }
+    fn format_f64(&self, v: &f64) -> String {
+        v.to_string()
+    }
+    fn format_ip(&self, v: &std::net::IpAddr) -> String {
+        v.to_string()
+    }
+    fn format_datetime(&self, v: &chrono::NaiveDateTime) -> String {
+        render(|out| self.format_datetime_into(v, out))
+    }
+    fn format_object(&self, value: &ObjectValue) -> String {
+        render(|out| self.format_object_into(value, out))
+    }
+    fn format_array(&self, value: &[DataField]) -> String {
+        render(|out| self.format_array_into(value, out))
+    }
+    fn format_field(&self, field: &DataField) -> String {
+        render(|out| self.format_field_into(field, out))
+    }
+    fn format_record(&self, record: &DataRecord) -> String {
+        render(|out| self.format_record_into(record, out))
+    }
+    fn format_fields(&self, fields: &[(&str, &DataField)]) -> String {
+        render(|out| self.format_fields_into(fields, out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(items: Vec<DataField>) -> DataRecord {
+        DataRecord { id: 0, items }
+    }
+
+    #[test]
+    fn test_bare_and_quoted_values() {
+        let f = Logfmt::new();
+        assert_eq!(f.format_string("plain"), "plain");
+        assert_eq!(f.format_string(""), "\"\"");
+        assert_eq!(f.format_string("a b"), "\"a b\"");
+        assert_eq!(f.format_string("k=v"), "\"k=v\"");
+        assert_eq!(
+            f.format_string("say \"hi\"\\\n"),
+            "\"say \\\"hi\\\"\\\\\\n\""
+        );
+    }
+
+    #[test]
+    fn test_key_sanitizing() {
+        let f = Logfmt::new();
+        let r = rec(vec![
+            DataField::from_digit("a b", 1),
+            DataField::from_digit("c=d", 2),
+            DataField::from_digit("", 3),
+        ]);
+        assert_eq!(f.format_record(&r), "a_b=1 c_d=2 _=3");
+    }
+
+    #[test]
+    fn test_null_is_empty_value() {
+        let f = Logfmt::new();
+        let r = rec(vec![
+            DataField::new(DataType::Auto, "x", Value::Null),
+            DataField::from_bool("ok", true),
+        ]);
+        assert_eq!(f.format_record(&r), "x= ok=true");
+    }
+
+    #[test]
+    fn test_nested_flattening() {
+        let mut inner = ObjectValue::new();
+        inner.insert("port", DataField::from_digit("port", 80));
+        let mut obj = ObjectValue::new();
+        obj.insert("addr", DataField::from_chars("addr", "10.0.0.9"));
+        obj.insert("peer", DataField::from_obj("peer", inner));
+        let r = rec(vec![
+            DataField::from_obj("up", obj),
+            DataField::from_arr(
+                "tags",
+                vec![
+                    DataField::from_chars("", "a"),
+                    DataField::from_chars("", "b c"),
+                ],
+            ),
+        ]);
+        assert_eq!(
+            Logfmt::new().format_record(&r),
+            "up.addr=10.0.0.9 up.peer.port=80 tags.0=a tags.1=\"b c\""
+        );
+        assert_eq!(
+            Logfmt::new().with_key_separator("_").format_record(&r),
+            "up_addr=10.0.0.9 up_peer_port=80 tags_0=a tags_1=\"b c\""
+        );
+    }
+
+    #[test]
+    fn test_skips_ignore_fields() {
+        let r = rec(vec![
+            DataField::from_ignore("skip"),
+            DataField::from_digit("n", 1),
+        ]);
+        assert_eq!(Logfmt::new().format_record(&r), "n=1");
+    }
+
+    #[test]
+    fn test_time_format() {
+        let ts = chrono::NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S")
+            .unwrap();
+        let r = rec(vec![DataField::from_time("time", ts)]);
+        assert_eq!(
+            Logfmt::new().format_record(&r),
+            "time=\"2019-08-06 12:12:19\""
+        );
+        let f = Logfmt::new().with_time_format(crate::TimeStyle::Rfc3339);
+        assert_eq!(f.format_record(&r), "time=2019-08-06T12:12:19Z");
+    }
+}
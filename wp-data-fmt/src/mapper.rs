@@ -0,0 +1,414 @@
+use crate::formatter::{DataFormat, StreamDataFormat};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use wildmatch::WildMatch;
+use wp_model_core::model::{DataField, DataRecord, DataType, types::value::ObjectValue};
+
+/// Value of a constant output field.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ConstValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl ConstValue {
+    fn to_field(&self, name: &str) -> DataField {
+        match self {
+            ConstValue::Bool(v) => DataField::from_bool(name, *v),
+            ConstValue::Int(v) => DataField::from_digit(name, *v),
+            ConstValue::Float(v) => DataField::from_float(name, *v),
+            ConstValue::Text(v) => DataField::from_chars(name, v.as_str()),
+        }
+    }
+}
+
+/// Selects, renames and orders the fields a sink receives.
+///
+/// Steps, all keyed by source field names:
+/// - `include`: output only these fields, in this order (empty keeps every
+///   field in record order); a constant's name may be listed to place it
+/// - `exclude`: drop fields matching these wildcard patterns (`*`, `?`)
+/// - `order`: move these fields to the front, in this order
+/// - `rename`: source name to output name
+/// - `constants`: fixed fields, appended unless placed by `include`
+///
+/// Exclude patterns and constant fields are built once, when the mapper is
+/// built or deserialized.
+///
+/// ```toml
+/// include = ["time", "ip", "http/status", "env"]
+/// exclude = ["http/*agent*"]
+/// rename = { "http/status" = "status" }
+/// constants = { env = "prod" }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "MapperConf", into = "MapperConf")]
+pub struct FieldMapper {
+    include: Vec<String>,
+    exclude: Vec<String>,
+    exclude_patterns: Vec<WildMatch>,
+    order: Vec<String>,
+    rename: BTreeMap<String, String>,
+    constants: BTreeMap<String, ConstValue>,
+    const_fields: BTreeMap<String, DataField>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MapperConf {
+    include: Vec<String>,
+    exclude: Vec<String>,
+    order: Vec<String>,
+    rename: BTreeMap<String, String>,
+    constants: BTreeMap<String, ConstValue>,
+}
+
+impl From<MapperConf> for FieldMapper {
+    fn from(conf: MapperConf) -> Self {
+        let mut mapper = FieldMapper::new()
+            .with_include(conf.include)
+            .with_exclude(conf.exclude)
+            .with_order(conf.order);
+        mapper.rename = conf.rename;
+        for (name, value) in conf.constants {
+            mapper = mapper.with_constant(name, value);
+        }
+        mapper
+    }
+}
+
+impl From<FieldMapper> for MapperConf {
+    fn from(mapper: FieldMapper) -> Self {
+        MapperConf {
+            include: mapper.include,
+            exclude: mapper.exclude,
+            order: mapper.order,
+            rename: mapper.rename,
+            constants: mapper.constants,
+        }
+    }
+}
+
+/// A selected field paired with its source name.
+type Selected<'a> = (&'a str, &'a DataField);
+
+impl FieldMapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_include<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        self.include = names.into_iter().map(Into::into).collect();
+        self
+    }
+    pub fn with_exclude<S: Into<String>>(mut self, patterns: impl IntoIterator<Item = S>) -> Self {
+        self.exclude = patterns.into_iter().map(Into::into).collect();
+        self.exclude_patterns = self.exclude.iter().map(|p| WildMatch::new(p)).collect();
+        self
+    }
+    pub fn with_order<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        self.order = names.into_iter().map(Into::into).collect();
+        self
+    }
+    pub fn with_rename(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.rename.insert(from.into(), to.into());
+        self
+    }
+    pub fn with_constant(mut self, name: impl Into<String>, value: ConstValue) -> Self {
+        let name = name.into();
+        self.const_fields
+            .insert(name.clone(), value.to_field(&name));
+        self.constants.insert(name, value);
+        self
+    }
+
+    /// Wraps a formatter so every record it formats is mapped first.
+    pub fn wrap<F>(self, inner: F) -> Mapped<F> {
+        Mapped {
+            mapper: self,
+            inner,
+        }
+    }
+
+    fn excluded(&self, name: &str) -> bool {
+        self.exclude_patterns.iter().any(|p| p.matches(name))
+    }
+
+    fn output_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.rename.get(name).map_or(name, String::as_str)
+    }
+
+    fn select<'a>(&'a self, fields: impl Iterator<Item = Selected<'a>>) -> Vec<Selected<'a>> {
+        let fields = fields.filter(|(_, f)| *f.get_meta() != DataType::Ignore);
+        let constants = self.const_fields.iter().map(|(name, f)| (name.as_str(), f));
+        let mut selected: Vec<Selected<'a>> = if self.include.is_empty() {
+            fields.chain(constants).collect()
+        } else {
+            let fields: Vec<Selected<'a>> = fields.collect();
+            let mut selected: Vec<Selected<'a>> = self
+                .include
+                .iter()
+                .filter_map(|name| {
+                    fields
+                        .iter()
+                        .find(|(n, _)| *n == name.as_str())
+                        .copied()
+                        .or_else(|| {
+                            let (name, f) = self.const_fields.get_key_value(name)?;
+                            Some((name.as_str(), f))
+                        })
+                })
+                .collect();
+            selected.extend(constants.filter(|(name, _)| !self.include.iter().any(|i| i == name)));
+            selected
+        };
+        selected.retain(|(name, _)| !self.excluded(name));
+        // stable sort: listed fields first in `order`, the rest keep their place
+        selected.sort_by_key(|(name, _)| {
+            self.order
+                .iter()
+                .position(|o| o == name)
+                .unwrap_or(self.order.len())
+        });
+        selected
+    }
+
+    /// The selected fields, each paired with its output name.
+    fn named<'a>(&'a self, fields: impl Iterator<Item = Selected<'a>>) -> Vec<Selected<'a>> {
+        let mut selected = self.select(fields);
+        for (name, _) in selected.iter_mut() {
+            *name = self.output_name(name);
+        }
+        selected
+    }
+
+    /// The mapped record as a copy of the selected fields. [`Mapped`] does
+    /// not call this; it hands the borrowed fields to its formatter.
+    pub fn map(&self, record: &DataRecord) -> DataRecord {
+        let items = self
+            .named(record_fields(record))
+            .into_iter()
+            .map(|(name, f)| DataField::new(f.get_meta().clone(), name, f.get_value().clone()))
+            .collect();
+        DataRecord {
+            id: record.id,
+            items,
+        }
+    }
+
+    /// Output field names for records with the given source fields, e.g. for
+    /// a CSV header or table columns.
+    pub fn output_names(&self, record: &DataRecord) -> Vec<String> {
+        self.named(record_fields(record))
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect()
+    }
+}
+
+fn record_fields(record: &DataRecord) -> impl Iterator<Item = Selected<'_>> {
+    record.items.iter().map(|f| (f.get_name(), f))
+}
+
+/// A formatter behind a [`FieldMapper`]. Values and fields pass straight
+/// through; records are mapped and reach the inner formatter as borrowed
+/// fields through [`StreamDataFormat::format_fields_into`].
+#[derive(Debug, Clone)]
+pub struct Mapped<F> {
+    mapper: FieldMapper,
+    inner: F,
+}
+
+impl<F> Mapped<F> {
+    pub fn mapper(&self) -> &FieldMapper {
+        &self.mapper
+    }
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+}
+
+impl<F: StreamDataFormat> StreamDataFormat for Mapped<F> {
+    fn format_null_into(&self, out: &mut dyn Write) -> fmt::Result {
+        self.inner.format_null_into(out)
+    }
+    fn format_bool_into(&self, v: &bool, out: &mut dyn Write) -> fmt::Result {
+        self.inner.format_bool_into(v, out)
+    }
+    fn format_string_into(&self, v: &str, out: &mut dyn Write) -> fmt::Result {
+        self.inner.format_string_into(v, out)
+    }
+    fn format_i64_into(&self, v: &i64, out: &mut dyn Write) -> fmt::Result {
+        self.inner.format_i64_into(v, out)
+    }
+    fn format_f64_into(&self, v: &f64, out: &mut dyn Write) -> fmt::Result {
+        self.inner.format_f64_into(v, out)
+    }
+    fn format_ip_into(&self, v: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result {
+        self.inner.format_ip_into(v, out)
+    }
+    fn format_datetime_into(&self, v: &chrono::NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+        self.inner.format_datetime_into(v, out)
+    }
+    fn format_object_into(&self, value: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        self.inner.format_object_into(value, out)
+    }
+    fn format_array_into(&self, value: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        self.inner.format_array_into(value, out)
+    }
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        self.inner.format_field_into(field, out)
+    }
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        self.inner
+            .format_fields_into(&self.mapper.named(record_fields(record)), out)
+    }
+    fn format_fields_into(
+        &self,
+        fields: &[(&str, &DataField)],
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        self.inner
+            .format_fields_into(&self.mapper.named(fields.iter().copied()), out)
+    }
+}
+
+impl<F: DataFormat> DataFormat for Mapped<F> {
+    type Output = F::Output;
+    fn format_null(&self) -> F::Output {
+        self.inner.format_null()
+    }
+    fn format_bool(&self, v: &bool) -> F::Output {
+        self.inner.format_bool(v)
+    }
+    fn format_string(&self, v: &str) -> F::Output {
+        self.inner.format_string(v)
+    }
+    fn format_i64(&self, v: &i64) -> F::Output {
+        self.inner.format_i64(v)
+    }
+    fn format_f64(&self, v: &f64) -> F::Output {
+        self.inner.format_f64(v)
+    }
+    fn format_ip(&self, v: &std::net::IpAddr) -> F::Output {
+        self.inner.format_ip(v)
+    }
+    fn format_datetime(&self, v: &chrono::NaiveDateTime) -> F::Output {
+        self.inner.format_datetime(v)
+    }
+    fn format_object(&self, value: &ObjectValue) -> F::Output {
+        self.inner.format_object(value)
+    }
+    fn format_array(&self, value: &[DataField]) -> F::Output {
+        self.inner.format_array(value)
+    }
+    fn format_field(&self, field: &DataField) -> F::Output {
+        self.inner.format_field(field)
+    }
+    fn format_record(&self, record: &DataRecord) -> F::Output {
+        self.inner
+            .format_fields(&self.mapper.named(record_fields(record)))
+    }
+    fn format_fields(&self, fields: &[(&str, &DataField)]) -> F::Output {
+        self.inner
+            .format_fields(&self.mapper.named(fields.iter().copied()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Json;
+
+    fn record() -> DataRecord {
+        DataRecord {
+            id: 7,
+            items: vec![
+                DataField::from_chars("ip", "10.0.0.1"),
+                DataField::from_digit("http/status", 200),
+                DataField::from_chars("http/agent", "curl"),
+                DataField::from_ignore("skip"),
+                DataField::from_digit("bytes", 512),
+            ],
+        }
+    }
+
+    fn names(r: &DataRecord) -> Vec<&str> {
+        r.items.iter().map(|f| f.get_name()).collect()
+    }
+
+    #[test]
+    fn test_default_is_identity() {
+        let mapped = FieldMapper::new().map(&record());
+        assert_eq!(names(&mapped), ["ip", "http/status", "http/agent", "bytes"]);
+        assert_eq!(mapped.id, 7);
+    }
+
+    #[test]
+    fn test_include_rename_and_constants() {
+        let mapper = FieldMapper::new()
+            .with_include(["bytes", "env", "http/status", "none"])
+            .with_rename("http/status", "status")
+            .with_constant("env", ConstValue::Text("prod".into()))
+            .with_constant("v", ConstValue::Int(2));
+        let mapped = mapper.map(&record());
+        assert_eq!(names(&mapped), ["bytes", "env", "status", "v"]);
+        assert_eq!(
+            Json::new().format_record(&mapped),
+            r#"{"bytes":512,"env":"prod","status":200,"v":2}"#
+        );
+    }
+
+    #[test]
+    fn test_exclude_and_order() {
+        let mapper = FieldMapper::new()
+            .with_exclude(["http/*"])
+            .with_order(["bytes"]);
+        assert_eq!(names(&mapper.map(&record())), ["bytes", "ip"]);
+        assert_eq!(mapper.output_names(&record()), ["bytes", "ip"]);
+    }
+
+    #[test]
+    fn test_wrap_formatter() {
+        let f = FieldMapper::new().with_include(["ip"]).wrap(Json::new());
+        assert_eq!(f.format_record(&record()), r#"{"ip":"10.0.0.1"}"#);
+        let mut buf = String::new();
+        f.format_record_into(&record(), &mut buf).unwrap();
+        assert_eq!(buf, r#"{"ip":"10.0.0.1"}"#);
+    }
+
+    #[test]
+    fn test_wrap_renames_without_building_a_record() {
+        let f = FieldMapper::new()
+            .with_rename("http/status", "status")
+            .with_constant("env", ConstValue::Text("prod".into()))
+            .wrap(crate::KeyValue::new());
+        let expect = r#"ip: "10.0.0.1", status: 200, http/agent: "curl", bytes: 512, env: "prod""#;
+        assert_eq!(f.format_record(&record()), expect);
+        let mut buf = String::new();
+        f.format_record_into(&record(), &mut buf).unwrap();
+        assert_eq!(buf, expect);
+
+        // the outer mapper selects first, then the inner one renames and
+        // adds its constants
+        let twice = FieldMapper::new()
+            .with_include(["http/status", "ip"])
+            .wrap(f);
+        assert_eq!(
+            twice.format_record(&record()),
+            r#"status: 200, ip: "10.0.0.1", env: "prod""#
+        );
+    }
+
+    #[test]
+    fn test_toml_round_trip() {
+        let mapper = FieldMapper::new()
+            .with_exclude(["http/*"])
+            .with_constant("env", ConstValue::Text("prod".into()));
+        let text = toml::to_string(&mapper).unwrap();
+        assert_eq!(toml::from_str::<FieldMapper>(&text).unwrap(), mapper);
+    }
+}
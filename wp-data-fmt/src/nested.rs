@@ -0,0 +1,347 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use wp_model_core::model::{DataField, Value, types::value::ObjectValue};
+
+/// What to do when two fields claim the same path, e.g. a scalar `a` and
+/// `a/b`, or `a/b` appearing twice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NestConflict {
+    /// Keep the earlier value in the tree and write the conflicting field
+    /// flat at the top level under its original name. If that name is
+    /// taken too, the record is an error.
+    #[default]
+    KeepFlat,
+    /// Keep the earlier value and drop the conflicting field.
+    FirstWins,
+    /// Replace the earlier value with the conflicting field.
+    LastWins,
+}
+
+fn default_separator() -> String {
+    "/".to_string()
+}
+
+/// Expands path-style field names into nested objects, see
+/// [`Json::with_nesting`](crate::Json::with_nesting): `http/request` and
+/// `http/status` become `{"http":{"request":..,"status":..}}`.
+///
+/// Object-valued fields are merged with expanded paths that share their
+/// prefix. Names with empty segments (`a//b`, `/a`) are written flat.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Nesting {
+    #[serde(default = "default_separator")]
+    separator: String,
+    #[serde(default)]
+    conflict: NestConflict,
+}
+
+impl Default for Nesting {
+    fn default() -> Self {
+        Self {
+            separator: default_separator(),
+            conflict: NestConflict::default(),
+        }
+    }
+}
+
+impl Nesting {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+    pub fn with_conflict(mut self, conflict: NestConflict) -> Self {
+        self.conflict = conflict;
+        self
+    }
+
+    pub(crate) fn build_tree<'a>(
+        &self,
+        fields: impl Iterator<Item = (&'a str, &'a DataField)>,
+    ) -> Result<Vec<Entry<'a>>, String> {
+        let mut root = Vec::new();
+        for (name, field) in fields {
+            let path: Vec<&str> = if self.separator.is_empty() {
+                vec![name]
+            } else {
+                name.split(self.separator.as_str()).collect()
+            };
+            if path.iter().any(|s| s.is_empty()) {
+                self.place_flat(&mut root, Cow::Borrowed(name), field.get_value())?;
+            } else {
+                self.place(&mut root, &path, field.get_value(), Cow::Borrowed(name))?;
+            }
+        }
+        Ok(root)
+    }
+
+    /// Places `value` at `path`, applying the conflict policy. `flat_name`
+    /// is the key used when the value has to be written flat instead.
+    fn place<'a>(
+        &self,
+        root: &mut Vec<Entry<'a>>,
+        path: &[&'a str],
+        value: &'a Value,
+        flat_name: Cow<'a, str>,
+    ) -> Result<(), String> {
+        match self.insert(root, path, value) {
+            Placement::Placed => Ok(()),
+            Placement::Conflict if self.conflict == NestConflict::KeepFlat => {
+                self.place_flat(root, flat_name, value)
+            }
+            Placement::Conflict => Ok(()),
+            Placement::Merge(obj) => {
+                for (k, f) in obj.iter() {
+                    let mut child = path.to_vec();
+                    child.push(k.as_str());
+                    let name = format!("{}{}{}", flat_name, self.separator, k);
+                    self.place(root, &child, f.get_value(), Cow::Owned(name))?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes `value` at the top level under `name`, which may already be
+    /// there; the conflict policy decides which of the two is kept.
+    fn place_flat<'a>(
+        &self,
+        root: &mut Vec<Entry<'a>>,
+        name: Cow<'a, str>,
+        value: &'a Value,
+    ) -> Result<(), String> {
+        match root.iter().position(|(k, _)| *k == name) {
+            None => root.push((name, Node::from_value(value))),
+            Some(pos) => match self.conflict {
+                NestConflict::LastWins => root[pos].1 = Node::from_value(value),
+                NestConflict::FirstWins => {}
+                NestConflict::KeepFlat => {
+                    return Err(format!(
+                        "field `{}` conflicts with a nested path and its flat name is taken",
+                        name
+                    ));
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn insert<'a>(
+        &self,
+        level: &mut Vec<Entry<'a>>,
+        path: &[&'a str],
+        value: &'a Value,
+    ) -> Placement<'a> {
+        let (key, rest) = (path[0], &path[1..]);
+        let Some(pos) = level.iter().position(|(k, _)| k == key) else {
+            level.push((Cow::Borrowed(key), Node::from_path(rest, value)));
+            return Placement::Placed;
+        };
+        match (&mut level[pos].1, rest.is_empty(), value) {
+            (Node::Branch(children), false, _) => self.insert(children, rest, value),
+            (Node::Branch(_), true, Value::Obj(obj)) => Placement::Merge(obj),
+            _ if self.conflict == NestConflict::LastWins => {
+                level[pos].1 = Node::from_path(rest, value);
+                Placement::Placed
+            }
+            _ => Placement::Conflict,
+        }
+    }
+}
+
+pub(crate) type Entry<'a> = (Cow<'a, str>, Node<'a>);
+
+enum Placement<'a> {
+    Placed,
+    Conflict,
+    /// An object landed on an existing branch; its children are placed one
+    /// by one so each can conflict on its own.
+    Merge(&'a ObjectValue),
+}
+
+pub(crate) enum Node<'a> {
+    Leaf(&'a Value),
+    Branch(Vec<Entry<'a>>),
+}
+
+impl<'a> Node<'a> {
+    fn from_value(value: &'a Value) -> Self {
+        match value {
+            Value::Obj(obj) => Node::Branch(
+                obj.iter()
+                    .map(|(k, f)| (Cow::Borrowed(k.as_str()), Node::from_value(f.get_value())))
+                    .collect(),
+            ),
+            _ => Node::Leaf(value),
+        }
+    }
+    fn from_path(path: &[&'a str], value: &'a Value) -> Self {
+        match path.split_first() {
+            None => Node::from_value(value),
+            Some((key, rest)) => {
+                Node::Branch(vec![(Cow::Borrowed(*key), Node::from_path(rest, value))])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Json;
+    use crate::formatter::DataFormat;
+    use wp_model_core::model::DataRecord;
+
+    fn rec(items: Vec<DataField>) -> DataRecord {
+        DataRecord { id: 0, items }
+    }
+
+    #[test]
+    fn test_nested_expands_paths() {
+        let r = rec(vec![
+            DataField::from_chars("http/request", "GET /"),
+            DataField::from_digit("length", 3),
+            DataField::from_digit("http/status", 200),
+        ]);
+        assert_eq!(
+            Json::new().with_nesting(Nesting::new()).format_record(&r),
+            r#"{"http":{"request":"GET /","status":200},"length":3}"#
+        );
+    }
+
+    #[test]
+    fn test_nested_custom_separator() {
+        let r = rec(vec![
+            DataField::from_digit("a.b.c", 1),
+            DataField::from_digit("a/b", 2),
+        ]);
+        assert_eq!(
+            Json::new()
+                .with_nesting(Nesting::new().with_separator("."))
+                .format_record(&r),
+            r#"{"a":{"b":{"c":1}},"a/b":2}"#
+        );
+    }
+
+    #[test]
+    fn test_nested_conflict_keep_flat() {
+        let r = rec(vec![
+            DataField::from_digit("a", 1),
+            DataField::from_digit("a/b", 2),
+        ]);
+        assert_eq!(
+            Json::new().with_nesting(Nesting::new()).format_record(&r),
+            r#"{"a":1,"a/b":2}"#
+        );
+    }
+
+    #[test]
+    fn test_nested_keep_flat_reports_a_taken_flat_name() {
+        let json = Json::new().with_nesting(Nesting::new());
+        let r = rec(vec![
+            DataField::from_digit("a", 1),
+            DataField::from_digit("a/b", 2),
+            DataField::from_digit("a", 3),
+        ]);
+        let err = json.try_format_record(&r).unwrap_err();
+        assert!(err.contains("`a`"), "{}", err);
+        assert_eq!(json.format_record(&r), "");
+
+        let r = rec(vec![
+            DataField::from_digit("/a", 1),
+            DataField::from_digit("/a", 2),
+        ]);
+        assert!(json.try_format_record(&r).is_err());
+        let first = Json::new().with_nesting(Nesting::new().with_conflict(NestConflict::FirstWins));
+        assert_eq!(first.format_record(&r), r#"{"/a":1}"#);
+        let last = Json::new().with_nesting(Nesting::new().with_conflict(NestConflict::LastWins));
+        assert_eq!(last.format_record(&r), r#"{"/a":2}"#);
+    }
+
+    #[test]
+    fn test_nested_uses_json_settings() {
+        let ts = chrono::NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S")
+            .unwrap();
+        let r = rec(vec![
+            DataField::from_time("http/time", ts),
+            DataField::from_digit("http/status", 200),
+            DataField::from_digit("n", 1),
+        ]);
+        let json = Json::new()
+            .with_indent(2)
+            .with_key_value_space(true)
+            .with_time_format(crate::TimeStyle::EpochSecs)
+            .with_nesting(Nesting::new());
+        assert_eq!(
+            json.format_record(&r),
+            "{\n  \"http\": {\n    \"time\": 1565093539,\n    \"status\": 200\n  },\n  \"n\": 1\n}"
+        );
+    }
+
+    #[test]
+    fn test_nested_conflict_first_and_last_wins() {
+        let r = rec(vec![
+            DataField::from_digit("a/b", 1),
+            DataField::from_digit("a", 2),
+            DataField::from_digit("a/b", 3),
+        ]);
+        let first = Json::new().with_nesting(Nesting::new().with_conflict(NestConflict::FirstWins));
+        assert_eq!(first.format_record(&r), r#"{"a":{"b":1}}"#);
+        let last = Json::new().with_nesting(Nesting::new().with_conflict(NestConflict::LastWins));
+        assert_eq!(last.format_record(&r), r#"{"a":{"b":3}}"#);
+    }
+
+    #[test]
+    fn test_nested_merges_object_values() {
+        let mut obj = ObjectValue::new();
+        obj.insert("x", DataField::from_digit("x", 1));
+        let r = rec(vec![
+            DataField::from_obj("a", obj),
+            DataField::from_digit("a/y", 2),
+        ]);
+        assert_eq!(
+            Json::new().with_nesting(Nesting::new()).format_record(&r),
+            r#"{"a":{"x":1,"y":2}}"#
+        );
+    }
+
+    #[test]
+    fn test_nested_object_merge_conflict_keeps_child_flat() {
+        let mut obj = ObjectValue::new();
+        obj.insert("b", DataField::from_digit("b", 9));
+        obj.insert("c", DataField::from_digit("c", 3));
+        let r = rec(vec![
+            DataField::from_digit("a/b", 1),
+            DataField::from_obj("a", obj),
+        ]);
+        assert_eq!(
+            Json::new().with_nesting(Nesting::new()).format_record(&r),
+            r#"{"a":{"b":1,"c":3},"a/b":9}"#
+        );
+    }
+
+    #[test]
+    fn test_nested_empty_segments_stay_flat() {
+        let r = rec(vec![
+            DataField::from_digit("/a", 1),
+            DataField::from_digit("b//c", 2),
+        ]);
+        assert_eq!(
+            Json::new().with_nesting(Nesting::new()).format_record(&r),
+            r#"{"/a":1,"b//c":2}"#
+        );
+    }
+
+    #[test]
+    fn test_nested_format_field() {
+        let f = DataField::from_chars("http/agent", "curl");
+        assert_eq!(
+            Json::new().with_nesting(Nesting::new()).format_field(&f),
+            r#""http":{"agent":"curl"}"#
+        );
+    }
+}
@@ -0,0 +1,367 @@
+use crate::time_fmt::TimeFormat;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::IpAddr;
+use wp_model_core::model::{DataField, DataRecord, DataType, Value, types::value::ObjectValue};
+
+/// Reads formatter output back into records.
+///
+/// Implemented by the formatters themselves, so a parser always agrees with
+/// the options (separators, quoting, time format) its output was written
+/// with. Without hints, values come back as the text format shows them:
+/// quoted text as `Chars`, numbers as `Digit`/`Float`, `true`/`false` as
+/// `Bool`; [`ParseHints`] restore the other types.
+pub trait DataParse {
+    fn parse_record_with(&self, input: &str, hints: &ParseHints) -> Result<DataRecord, ParseError>;
+
+    fn parse_record(&self, input: &str) -> Result<DataRecord, ParseError> {
+        self.parse_record_with(input, &ParseHints::default())
+    }
+}
+
+/// Field types to restore, and column names for formats without them.
+///
+/// Types are keyed by field name, or by dotted path for values nested in
+/// objects (`upstream.addr`); a hint on an array applies to its items.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParseHints {
+    types: BTreeMap<String, DataType>,
+    columns: Vec<String>,
+}
+
+impl ParseHints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_type(mut self, name: impl Into<String>, data_type: DataType) -> Self {
+        self.types.insert(name.into(), data_type);
+        self
+    }
+    /// Field names of CSV values, in column order.
+    pub fn with_columns<I, S>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.columns = columns.into_iter().map(Into::into).collect();
+        self
+    }
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+    pub fn type_of(&self, path: &str) -> Option<&DataType> {
+        self.types.get(path)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Byte offset in the input, when the error is a syntax error.
+    pub offset: Option<usize>,
+    pub message: String,
+}
+
+impl ParseError {
+    pub(crate) fn at(offset: usize, message: impl Into<String>) -> Self {
+        Self {
+            offset: Some(offset),
+            message: message.into(),
+        }
+    }
+    pub(crate) fn value(message: impl Into<String>) -> Self {
+        Self {
+            offset: None,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.offset {
+            Some(offset) => write!(f, "{} at byte {}", self.message, offset),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A value as read from text, before types are assigned.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Node {
+    Null,
+    Bool(bool),
+    /// A numeric literal (JSON, protobuf text).
+    Num(String),
+    /// Quoted text.
+    Str(String),
+    /// Unquoted text whose type is inferred (KV, CSV).
+    Bare(String),
+    Obj(Vec<(String, Node)>),
+    Arr(Vec<Node>),
+}
+
+pub(crate) struct Cursor<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub(crate) fn new(src: &'a str) -> Self {
+        Self { src, pos: 0 }
+    }
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+    pub(crate) fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+    pub(crate) fn is_eof(&self) -> bool {
+        self.pos >= self.src.len()
+    }
+    pub(crate) fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+    pub(crate) fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+    pub(crate) fn starts_with(&self, s: &str) -> bool {
+        self.rest().starts_with(s)
+    }
+    pub(crate) fn eat(&mut self, s: &str) -> bool {
+        if self.starts_with(s) {
+            self.pos += s.len();
+            true
+        } else {
+            false
+        }
+    }
+    pub(crate) fn expect(&mut self, s: &str) -> Result<(), ParseError> {
+        if self.eat(s) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{}'", s)))
+        }
+    }
+    pub(crate) fn skip_ws(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+    /// Consumes up to (not including) the first character matching `stop`.
+    pub(crate) fn take_until(&mut self, stop: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let end = rest.find(stop).unwrap_or(rest.len());
+        self.pos += end;
+        &rest[..end]
+    }
+    pub(crate) fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError::at(self.pos, message)
+    }
+}
+
+/// Builds a record from top-level entries, applying `hints`.
+pub(crate) fn build_record(
+    entries: Vec<(String, Node)>,
+    hints: &ParseHints,
+    time_format: &TimeFormat,
+) -> Result<DataRecord, ParseError> {
+    let items = entries
+        .into_iter()
+        .map(|(name, node)| to_field(&name, &name, node, hints, time_format))
+        .collect::<Result<_, _>>()?;
+    Ok(DataRecord { id: 0, items })
+}
+
+fn to_field(
+    name: &str,
+    path: &str,
+    node: Node,
+    hints: &ParseHints,
+    time_format: &TimeFormat,
+) -> Result<DataField, ParseError> {
+    let field = match node {
+        Node::Obj(entries) => {
+            let mut obj = ObjectValue::new();
+            for (key, child) in entries {
+                let child_path = format!("{}.{}", path, key);
+                let child = to_field(&key, &child_path, child, hints, time_format)?;
+                obj.insert(key, child);
+            }
+            DataField::from_obj(name, obj)
+        }
+        Node::Arr(items) => DataField::from_arr(
+            name,
+            items
+                .into_iter()
+                .map(|item| to_field("", path, item, hints, time_format))
+                .collect::<Result<_, _>>()?,
+        ),
+        Node::Null => DataField::new(DataType::Auto, name, Value::Null),
+        node => match hints.type_of(path) {
+            Some(hint) => hinted(name, path, node, hint, time_format)?,
+            None => inferred(name, node),
+        },
+    };
+    Ok(field)
+}
+
+fn inferred(name: &str, node: Node) -> DataField {
+    match node {
+        Node::Bool(v) => DataField::from_bool(name, v),
+        Node::Str(s) => DataField::from_chars(name, s),
+        Node::Num(s) => match s.parse::<i64>() {
+            Ok(v) => DataField::from_digit(name, v),
+            Err(_) => match s.parse::<f64>() {
+                Ok(v) => DataField::from_float(name, v),
+                Err(_) => DataField::from_chars(name, s),
+            },
+        },
+        Node::Bare(s) => {
+            if s.is_empty() {
+                DataField::new(DataType::Auto, name, Value::Null)
+            } else if s == "true" || s == "false" {
+                DataField::from_bool(name, s == "true")
+            } else if let Ok(v) = s.parse::<i64>() {
+                DataField::from_digit(name, v)
+            } else if looks_decimal(&s)
+                && let Ok(v) = s.parse::<f64>()
+            {
+                DataField::from_float(name, v)
+            } else {
+                DataField::from_chars(name, s)
+            }
+        }
+        Node::Null | Node::Obj(_) | Node::Arr(_) => unreachable!("handled by to_field"),
+    }
+}
+
+/// `1.5`, `-2e3`: digits with a fraction or exponent, but not `inf`/`nan`,
+/// which unquoted text only means with a float hint.
+fn looks_decimal(s: &str) -> bool {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    digits.starts_with(|c: char| c.is_ascii_digit())
+        && digits
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'))
+}
+
+fn hinted(
+    name: &str,
+    path: &str,
+    node: Node,
+    hint: &DataType,
+    time_format: &TimeFormat,
+) -> Result<DataField, ParseError> {
+    let text = match node {
+        Node::Bool(v) => v.to_string(),
+        Node::Num(s) | Node::Str(s) | Node::Bare(s) => s,
+        Node::Null | Node::Obj(_) | Node::Arr(_) => unreachable!("handled by to_field"),
+    };
+    let invalid = || ParseError::value(format!("{}: '{}' is not a valid {}", path, text, hint));
+    let field = match hint {
+        DataType::Chars => DataField::from_chars(name, text.as_str()),
+        DataType::Digit => DataField::from_digit(name, text.parse().map_err(|_| invalid())?),
+        DataType::Float => DataField::from_float(name, text.parse().map_err(|_| invalid())?),
+        DataType::Bool => match text.as_str() {
+            "true" => DataField::from_bool(name, true),
+            "false" => DataField::from_bool(name, false),
+            _ => return Err(invalid()),
+        },
+        DataType::IP => DataField::from_ip(name, text.parse::<IpAddr>().map_err(|_| invalid())?),
+        DataType::Time
+        | DataType::TimeISO
+        | DataType::TimeRFC3339
+        | DataType::TimeRFC2822
+        | DataType::TimeTIMESTAMP
+        | DataType::TimeCLF => {
+            DataField::from_time(name, time_format.parse(&text).ok_or_else(invalid)?)
+        }
+        other => {
+            return Err(ParseError::value(format!(
+                "{}: type hint {} is not supported",
+                path, other
+            )));
+        }
+    };
+    Ok(field)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inference() {
+        let field = |node| inferred("v", node);
+        assert_eq!(
+            field(Node::Bare("42".into())),
+            DataField::from_digit("v", 42)
+        );
+        assert_eq!(
+            field(Node::Bare("-1.5".into())),
+            DataField::from_float("v", -1.5)
+        );
+        assert_eq!(
+            field(Node::Bare("inf".into())),
+            DataField::from_chars("v", "inf")
+        );
+        assert_eq!(
+            field(Node::Bare("true".into())),
+            DataField::from_bool("v", true)
+        );
+        assert_eq!(
+            field(Node::Str("42".into())),
+            DataField::from_chars("v", "42")
+        );
+        assert_eq!(
+            field(Node::Num("1.0".into())),
+            DataField::from_float("v", 1.0)
+        );
+        assert_eq!(
+            field(Node::Bare(String::new())),
+            DataField::new(DataType::Auto, "v", Value::Null)
+        );
+    }
+
+    #[test]
+    fn test_hints_by_path() {
+        let hints = ParseHints::new()
+            .with_type("up.addr", DataType::IP)
+            .with_type("n", DataType::Float);
+        let record = build_record(
+            vec![
+                (
+                    "up".into(),
+                    Node::Obj(vec![("addr".into(), Node::Str("10.0.0.1".into()))]),
+                ),
+                ("n".into(), Node::Bare("1".into())),
+            ],
+            &hints,
+            &TimeFormat::default(),
+        )
+        .unwrap();
+        let mut up = ObjectValue::new();
+        up.insert(
+            "addr",
+            DataField::from_ip("addr", "10.0.0.1".parse().unwrap()),
+        );
+        assert_eq!(
+            record.items,
+            vec![
+                DataField::from_obj("up", up),
+                DataField::from_float("n", 1.0)
+            ]
+        );
+
+        let err = build_record(
+            vec![("n".into(), Node::Bare("x".into()))],
+            &hints,
+            &TimeFormat::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "n: 'x' is not a valid float");
+    }
+}
@@ -0,0 +1,681 @@
+use crate::formatter::{DataFormat, StreamDataFormat};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::sync::{Mutex, MutexGuard};
+use wp_model_core::model::{DataField, DataRecord, DataType, Value, types::value::ObjectValue};
+
+const WIRE_VARINT: u8 = 0;
+const WIRE_FIXED64: u8 = 1;
+const WIRE_LEN: u8 = 2;
+
+/// Protobuf type of a field, locked together with its number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtoKind {
+    /// `int64` or `bool` (varint).
+    Int64,
+    Double,
+    /// Also accepts any scalar, written as its text.
+    String,
+    /// `google.protobuf.Timestamp`.
+    Timestamp,
+    Message,
+}
+
+impl ProtoKind {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Bool(_) | Value::Digit(_) => ProtoKind::Int64,
+            Value::Float(_) => ProtoKind::Double,
+            Value::Time(_) => ProtoKind::Timestamp,
+            Value::Obj(_) => ProtoKind::Message,
+            _ => ProtoKind::String,
+        }
+    }
+}
+
+/// Field-number mapping for one protobuf message.
+///
+/// Object fields (and arrays of objects) carry their own nested schema.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProtoSchema {
+    fields: BTreeMap<String, ProtoFieldDef>,
+}
+
+/// `kind` is locked by the first value encoded when left unset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProtoFieldDef {
+    pub number: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ProtoKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<ProtoSchema>,
+}
+
+impl ProtoFieldDef {
+    fn new(number: u32) -> Self {
+        Self {
+            number,
+            kind: None,
+            message: None,
+        }
+    }
+
+    /// The kind to write `value` as, locking it on first use.
+    fn lock(&mut self, name: &str, value: &Value) -> Result<ProtoKind, String> {
+        let kind = ProtoKind::of(value);
+        match self.kind {
+            None => {
+                self.kind = Some(kind);
+                Ok(kind)
+            }
+            Some(locked) if locked == kind => Ok(kind),
+            Some(ProtoKind::String) if kind != ProtoKind::Message => Ok(ProtoKind::String),
+            Some(locked) => Err(format!(
+                "field `{}` (#{}) is {:?}, got a {:?} value",
+                name, self.number, locked, kind
+            )),
+        }
+    }
+}
+
+impl ProtoSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_field(mut self, name: impl Into<String>, number: u32) -> Self {
+        self.fields.insert(name.into(), ProtoFieldDef::new(number));
+        self
+    }
+    pub fn with_typed_field(
+        mut self,
+        name: impl Into<String>,
+        number: u32,
+        kind: ProtoKind,
+    ) -> Self {
+        let mut def = ProtoFieldDef::new(number);
+        def.kind = Some(kind);
+        self.fields.insert(name.into(), def);
+        self
+    }
+    pub fn with_message(mut self, name: impl Into<String>, number: u32, schema: Self) -> Self {
+        self.fields.insert(
+            name.into(),
+            ProtoFieldDef {
+                number,
+                kind: Some(ProtoKind::Message),
+                message: Some(schema),
+            },
+        );
+        self
+    }
+
+    /// Derives a schema from a sample record: fields are numbered from 1 in
+    /// record order, object members from 1 in key order, and each takes the
+    /// kind of its first value.
+    pub fn from_record(record: &DataRecord) -> Self {
+        let mut schema = Self::new();
+        // a name repeated with another type keeps its first kind
+        let _ = schema.encode_message(record_fields(record), false, &mut Vec::new());
+        schema
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ProtoFieldDef> {
+        self.fields.get(name)
+    }
+
+    fn next_number(&self) -> u32 {
+        self.fields.values().map(|d| d.number).max().unwrap_or(0) + 1
+    }
+
+    /// Encodes `fields` as this message. With `fixed` the schema is the
+    /// contract and a field it does not name is an error; otherwise the
+    /// field is appended under the next free number.
+    fn encode_message<'a>(
+        &mut self,
+        fields: impl Iterator<Item = (&'a str, &'a Value)>,
+        fixed: bool,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), String> {
+        for (name, value) in fields {
+            if !self.fields.contains_key(name) {
+                if fixed {
+                    return Err(format!("field `{}` is not in the protobuf schema", name));
+                }
+                let number = self.next_number();
+                self.fields
+                    .insert(name.to_string(), ProtoFieldDef::new(number));
+            }
+            let def = self.fields.get_mut(name).expect("inserted above");
+            encode_field(name, def, value, fixed, buf)?;
+        }
+        Ok(())
+    }
+}
+
+fn record_fields(record: &DataRecord) -> impl Iterator<Item = (&str, &Value)> {
+    record
+        .items
+        .iter()
+        .filter(|f| *f.get_meta() != DataType::Ignore)
+        .map(|f| (f.get_name(), f.get_value()))
+}
+
+fn object_fields(obj: &ObjectValue) -> impl Iterator<Item = (&str, &Value)> {
+    obj.iter().map(|(k, f)| (k.as_str(), f.get_value()))
+}
+
+fn encode_field(
+    name: &str,
+    def: &mut ProtoFieldDef,
+    value: &Value,
+    fixed: bool,
+    buf: &mut Vec<u8>,
+) -> Result<(), String> {
+    let number = def.number;
+    match value {
+        Value::Null | Value::Ignore(_) => return Ok(()),
+        Value::Array(items) => {
+            for item in items {
+                if matches!(item.get_value(), Value::Array(_)) {
+                    return Err(format!(
+                        "field `{}`: nested arrays have no protobuf encoding",
+                        name
+                    ));
+                }
+                encode_field(name, def, item.get_value(), fixed, buf)?;
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
+    match (def.lock(name, value)?, value) {
+        (ProtoKind::Int64, Value::Bool(v)) => {
+            put_key(buf, number, WIRE_VARINT);
+            put_varint(buf, *v as u64);
+        }
+        (ProtoKind::Int64, Value::Digit(v)) => {
+            put_key(buf, number, WIRE_VARINT);
+            put_varint(buf, *v as u64);
+        }
+        (ProtoKind::Double, Value::Float(v)) => {
+            put_key(buf, number, WIRE_FIXED64);
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        (ProtoKind::Timestamp, Value::Time(v)) => {
+            put_key(buf, number, WIRE_LEN);
+            put_bytes(buf, &encode_timestamp(v));
+        }
+        (ProtoKind::Message, Value::Obj(obj)) => {
+            let message = match &mut def.message {
+                Some(message) => message,
+                None if fixed => {
+                    return Err(format!(
+                        "field `{}` is an object but the schema has no message for it",
+                        name
+                    ));
+                }
+                none => none.insert(ProtoSchema::new()),
+            };
+            let mut inner = Vec::new();
+            message.encode_message(object_fields(obj), fixed, &mut inner)?;
+            put_key(buf, number, WIRE_LEN);
+            put_bytes(buf, &inner);
+        }
+        (_, Value::Chars(s)) => {
+            put_key(buf, number, WIRE_LEN);
+            put_bytes(buf, s.as_bytes());
+        }
+        (_, other) => {
+            put_key(buf, number, WIRE_LEN);
+            put_bytes(buf, other.to_string().as_bytes());
+        }
+    }
+    Ok(())
+}
+
+/// Binary protobuf (wire format) encoder.
+///
+/// Value mapping: `Bool`/`Digit` as varint (`bool`/`int64`), `Float` as
+/// `double`, `Time` as a nested `google.protobuf.Timestamp` (the naive value
+/// is taken as UTC), `Obj` as a nested message, `Array` as a repeated field,
+/// `Null` is omitted and everything else is written as a `string`.
+///
+/// Each field keeps its number and [`ProtoKind`] across the stream. Without
+/// a [`ProtoSchema`] one is derived from the records as they come (see
+/// [`ProtoSchema::from_record`]): a field first seen later is appended under
+/// the next free number. A supplied schema is not extended; a field it does
+/// not name is an error. A value that does not fit its field's kind is an
+/// error too. [`ProtoBin::try_format_record`] reports these errors; the
+/// [`DataFormat`] methods return no bytes for such input.
+///
+/// `format_record` and `format_field` share the record schema;
+/// `format_object` and `format_array` each derive their own.
+#[derive(Debug)]
+pub struct ProtoBin {
+    record: Mutex<ProtoSchema>,
+    object: Mutex<ProtoSchema>,
+    array: Mutex<ProtoFieldDef>,
+    fixed: bool,
+    delimited: bool,
+}
+
+impl Default for ProtoBin {
+    fn default() -> Self {
+        Self {
+            record: Mutex::default(),
+            object: Mutex::default(),
+            // array items are repeated field 1
+            array: Mutex::new(ProtoFieldDef::new(1)),
+            fixed: false,
+            delimited: false,
+        }
+    }
+}
+
+impl Clone for ProtoBin {
+    fn clone(&self) -> Self {
+        Self {
+            record: Mutex::new(lock(&self.record).clone()),
+            object: Mutex::new(lock(&self.object).clone()),
+            array: Mutex::new(lock(&self.array).clone()),
+            fixed: self.fixed,
+            delimited: self.delimited,
+        }
+    }
+}
+
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl ProtoBin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_schema(mut self, schema: ProtoSchema) -> Self {
+        self.record = Mutex::new(schema);
+        self.fixed = true;
+        self
+    }
+    /// The record schema: the supplied one, or the one derived so far.
+    pub fn schema(&self) -> ProtoSchema {
+        lock(&self.record).clone()
+    }
+    /// Prefixes every record with its varint length, as protobuf's
+    /// `writeDelimitedTo`, so several records can share one byte stream.
+    pub fn with_delimited(mut self, delimited: bool) -> Self {
+        self.delimited = delimited;
+        self
+    }
+
+    pub fn try_format_record(&self, record: &DataRecord) -> Result<Vec<u8>, String> {
+        let mut buf = Vec::new();
+        lock(&self.record).encode_message(record_fields(record), self.fixed, &mut buf)?;
+        Ok(self.frame(buf))
+    }
+
+    fn try_format_field(&self, field: &DataField) -> Result<Vec<u8>, String> {
+        let mut buf = Vec::new();
+        lock(&self.record).encode_message(
+            std::iter::once((field.get_name(), field.get_value())),
+            self.fixed,
+            &mut buf,
+        )?;
+        Ok(buf)
+    }
+
+    fn try_format_object(&self, value: &ObjectValue) -> Result<Vec<u8>, String> {
+        let mut buf = Vec::new();
+        lock(&self.object).encode_message(object_fields(value), false, &mut buf)?;
+        Ok(buf)
+    }
+
+    fn try_format_array(&self, value: &[DataField]) -> Result<Vec<u8>, String> {
+        let mut buf = Vec::new();
+        let mut def = lock(&self.array);
+        for item in value {
+            encode_field("[]", &mut def, item.get_value(), false, &mut buf)?;
+        }
+        Ok(buf)
+    }
+
+    fn frame(&self, message: Vec<u8>) -> Vec<u8> {
+        if self.delimited {
+            let mut out = Vec::with_capacity(message.len() + 5);
+            put_bytes(&mut out, &message);
+            out
+        } else {
+            message
+        }
+    }
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        buf.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8);
+}
+
+fn put_key(buf: &mut Vec<u8>, number: u32, wire_type: u8) {
+    put_varint(buf, ((number as u64) << 3) | wire_type as u64);
+}
+
+fn put_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    put_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn encode_timestamp(v: &NaiveDateTime) -> Vec<u8> {
+    let utc = v.and_utc();
+    let mut buf = Vec::new();
+    if utc.timestamp() != 0 {
+        put_key(&mut buf, 1, WIRE_VARINT);
+        put_varint(&mut buf, utc.timestamp() as u64);
+    }
+    if utc.timestamp_subsec_nanos() != 0 {
+        put_key(&mut buf, 2, WIRE_VARINT);
+        put_varint(&mut buf, utc.timestamp_subsec_nanos() as u64);
+    }
+    buf
+}
+
+/// Scalar methods return the wire payload without a tag; objects and arrays
+/// return message bytes (an array as repeated field 1). Input that cannot be
+/// encoded gives no bytes; see [`ProtoBin::try_format_record`].
+impl DataFormat for ProtoBin {
+    type Output = Vec<u8>;
+    fn format_null(&self) -> Vec<u8> {
+        Vec::new()
+    }
+    fn format_bool(&self, v: &bool) -> Vec<u8> {
+        let mut buf = Vec::new();
+        put_varint(&mut buf, *v as u64);
+        buf
+    }
+    fn format_string(&self, v: &str) -> Vec<u8> {
+        let mut buf = Vec::new();
+        put_bytes(&mut buf, v.as_bytes());
+        buf
+    }
+    fn format_i64(&self, v: &i64) -> Vec<u8> {
+        let mut buf = Vec::new();
+        put_varint(&mut buf, *v as u64);
+        buf
+    }
+    fn format_f64(&self, v: &f64) -> Vec<u8> {
+        v.to_le_bytes().to_vec()
+    }
+    fn format_ip(&self, v: &std::net::IpAddr) -> Vec<u8> {
+        self.format_string(&v.to_string())
+    }
+    fn format_datetime(&self, v: &NaiveDateTime) -> Vec<u8> {
+        encode_timestamp(v)
+    }
+    fn format_object(&self, value: &ObjectValue) -> Vec<u8> {
+        self.try_format_object(value).unwrap_or_default()
+    }
+    fn format_array(&self, value: &[DataField]) -> Vec<u8> {
+        self.try_format_array(value).unwrap_or_default()
+    }
+    fn format_field(&self, field: &DataField) -> Vec<u8> {
+        self.try_format_field(field).unwrap_or_default()
+    }
+    fn format_record(&self, record: &DataRecord) -> Vec<u8> {
+        self.try_format_record(record).unwrap_or_default()
+    }
+}
+
+fn write_hex(bytes: &[u8], out: &mut dyn Write) -> fmt::Result {
+    bytes.iter().try_for_each(|b| write!(out, "{:02x}", b))
+}
+
+/// Text rendering of the wire bytes as lowercase hex, for logs and
+/// previews. Input that cannot be encoded is a [`fmt::Error`].
+impl StreamDataFormat for ProtoBin {
+    fn format_null_into(&self, out: &mut dyn Write) -> fmt::Result {
+        write_hex(&self.format_null(), out)
+    }
+    fn format_bool_into(&self, v: &bool, out: &mut dyn Write) -> fmt::Result {
+        write_hex(&self.format_bool(v), out)
+    }
+    fn format_string_into(&self, v: &str, out: &mut dyn Write) -> fmt::Result {
+        write_hex(&self.format_string(v), out)
+    }
+    fn format_i64_into(&self, v: &i64, out: &mut dyn Write) -> fmt::Result {
+        write_hex(&self.format_i64(v), out)
+    }
+    fn format_f64_into(&self, v: &f64, out: &mut dyn Write) -> fmt::Result {
+        write_hex(&self.format_f64(v), out)
+    }
+    fn format_ip_into(&self, v: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result {
+        write_hex(&self.format_ip(v), out)
+    }
+    fn format_datetime_into(&self, v: &NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+        write_hex(&self.format_datetime(v), out)
+    }
+    fn format_object_into(&self, value: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        write_hex(&self.try_format_object(value).map_err(|_| fmt::Error)?, out)
+    }
+    fn format_array_into(&self, value: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        write_hex(&self.try_format_array(value).map_err(|_| fmt::Error)?, out)
+    }
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        write_hex(&self.try_format_field(field).map_err(|_| fmt::Error)?, out)
+    }
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        write_hex(
+            &self.try_format_record(record).map_err(|_| fmt::Error)?,
+            out,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(items: Vec<DataField>) -> DataRecord {
+        DataRecord { id: 0, items }
+    }
+
+    #[test]
+    fn test_varint_and_string_encoding() {
+        // Examples from the protobuf encoding guide.
+        let r = rec(vec![
+            DataField::from_digit("a", 150),
+            DataField::from_chars("b", "testing"),
+        ]);
+        assert_eq!(
+            ProtoBin::new().format_record(&r),
+            vec![
+                0x08, 0x96, 0x01, 0x12, 0x07, b't', b'e', b's', b't', b'i', b'n', b'g'
+            ]
+        );
+    }
+
+    #[test]
+    fn test_negative_bool_and_double() {
+        let r = rec(vec![
+            DataField::from_digit("n", -1),
+            DataField::from_bool("b", true),
+            DataField::from_float("f", 1.0),
+        ]);
+        let mut expected = vec![0x08];
+        expected.extend([0xff; 9]);
+        expected.push(0x01);
+        expected.extend([0x10, 0x01, 0x19]);
+        expected.extend(1.0f64.to_le_bytes());
+        assert_eq!(ProtoBin::new().format_record(&r), expected);
+    }
+
+    #[test]
+    fn test_supplied_schema_numbers_and_rejects_unknown() {
+        let ac = rec(vec![
+            DataField::from_digit("a", 1),
+            DataField::from_digit("c", 3),
+        ]);
+        let schema = ProtoSchema::new().with_field("a", 4).with_field("c", 16);
+        let proto = ProtoBin::new().with_schema(schema.clone());
+        assert_eq!(proto.format_record(&ac), vec![0x20, 0x01, 0x80, 0x01, 0x03]);
+        let extra = rec(vec![
+            DataField::from_digit("a", 1),
+            DataField::from_digit("skip", 2),
+        ]);
+        assert!(proto.try_format_record(&extra).is_err());
+        assert!(proto.format_record(&extra).is_empty());
+        // not extended by the rejected record
+        assert!(proto.schema().get("skip").is_none());
+    }
+
+    #[test]
+    fn test_nested_message_and_repeated() {
+        let mut obj = ObjectValue::new();
+        obj.insert("x", DataField::from_digit("x", 1));
+        let r = rec(vec![
+            DataField::from_obj("o", obj),
+            DataField::from_arr(
+                "r",
+                vec![DataField::from_digit("", 5), DataField::from_digit("", 6)],
+            ),
+        ]);
+        assert_eq!(
+            ProtoBin::new().format_record(&r),
+            vec![0x0a, 0x02, 0x08, 0x01, 0x10, 0x05, 0x10, 0x06]
+        );
+    }
+
+    #[test]
+    fn test_schema_from_record() {
+        let mut obj = ObjectValue::new();
+        obj.insert("y", DataField::from_digit("y", 1));
+        obj.insert("x", DataField::from_digit("x", 1));
+        let r = rec(vec![
+            DataField::from_chars("s", "v"),
+            DataField::from_obj("o", obj),
+        ]);
+        let schema = ProtoSchema::from_record(&r);
+        assert_eq!(schema.get("s").unwrap().number, 1);
+        let o = schema.get("o").unwrap();
+        assert_eq!(o.number, 2);
+        let inner = o.message.as_ref().unwrap();
+        assert_eq!(inner.get("x").unwrap().number, 1);
+        assert_eq!(inner.get("y").unwrap().number, 2);
+    }
+
+    #[test]
+    fn test_timestamp_and_null() {
+        let ts =
+            NaiveDateTime::parse_from_str("1970-01-01 00:00:01.5", "%Y-%m-%d %H:%M:%S%.f").unwrap();
+        let r = rec(vec![
+            DataField::new(DataType::Auto, "n", Value::Null),
+            DataField::from_time("t", ts),
+        ]);
+        assert_eq!(
+            ProtoBin::new().format_record(&r),
+            vec![0x12, 0x08, 0x08, 0x01, 0x10, 0x80, 0xca, 0xb5, 0xee, 0x01]
+        );
+    }
+
+    #[test]
+    fn test_derived_schema_is_extended() {
+        let proto = ProtoBin::new();
+        let xy = rec(vec![
+            DataField::from_digit("x", 1),
+            DataField::from_digit("y", 2),
+        ]);
+        assert_eq!(proto.format_record(&xy), vec![0x08, 0x01, 0x10, 0x02]);
+        // `y` keeps field 2; `z` is appended as field 3
+        let yz = rec(vec![
+            DataField::from_digit("y", 2),
+            DataField::from_digit("z", 3),
+        ]);
+        assert_eq!(proto.format_record(&yz), vec![0x10, 0x02, 0x18, 0x03]);
+        assert_eq!(proto.schema().get("z").unwrap().number, 3);
+    }
+
+    #[test]
+    fn test_kind_is_locked_with_number() {
+        let proto = ProtoBin::new();
+        proto
+            .try_format_record(&rec(vec![DataField::from_digit("n", 1)]))
+            .unwrap();
+        let err = proto
+            .try_format_record(&rec(vec![DataField::from_chars("n", "x")]))
+            .unwrap_err();
+        assert!(err.contains("`n`"), "{}", err);
+        assert_eq!(
+            proto.schema().get("n").unwrap().kind,
+            Some(ProtoKind::Int64)
+        );
+
+        // a string field takes any scalar as text
+        let proto = ProtoBin::new();
+        proto
+            .try_format_record(&rec(vec![DataField::from_chars("s", "x")]))
+            .unwrap();
+        assert_eq!(
+            proto
+                .try_format_record(&rec(vec![DataField::from_digit("s", 7)]))
+                .unwrap(),
+            vec![0x0a, 0x01, b'7']
+        );
+    }
+
+    #[test]
+    fn test_object_without_message_is_derived() {
+        let mut obj = ObjectValue::new();
+        obj.insert("x", DataField::from_digit("x", 1));
+        let schema = ProtoSchema::new().with_field("o", 1);
+        let r = rec(vec![DataField::from_obj("o", obj)]);
+        // a supplied schema must name the nested message
+        assert!(
+            ProtoBin::new()
+                .with_schema(schema)
+                .try_format_record(&r)
+                .is_err()
+        );
+        let proto = ProtoBin::new();
+        assert_eq!(proto.format_record(&r), vec![0x0a, 0x02, 0x08, 0x01]);
+        let o = proto.schema().get("o").cloned().unwrap();
+        assert_eq!(o.message.unwrap().get("x").unwrap().number, 1);
+    }
+
+    #[test]
+    fn test_entry_points_keep_their_own_schema() {
+        let proto = ProtoBin::new();
+        let mut obj = ObjectValue::new();
+        obj.insert("b", DataField::from_digit("b", 1));
+        assert_eq!(proto.format_object(&obj), vec![0x08, 0x01]);
+        // the record numbering does not start from the object's
+        let r = rec(vec![
+            DataField::from_digit("a", 1),
+            DataField::from_digit("b", 2),
+        ]);
+        assert_eq!(proto.format_record(&r), vec![0x08, 0x01, 0x10, 0x02]);
+        // array items are repeated field 1 with their own nested message
+        let mut item = ObjectValue::new();
+        item.insert("z", DataField::from_digit("z", 3));
+        assert_eq!(
+            proto.format_array(&[DataField::from_obj("", item)]),
+            vec![0x0a, 0x02, 0x08, 0x03]
+        );
+        assert_eq!(
+            proto.format_array(&[DataField::from_arr("", vec![])]),
+            Vec::<u8>::new()
+        );
+    }
+
+    #[test]
+    fn test_delimited() {
+        let r = rec(vec![DataField::from_digit("a", 1)]);
+        assert_eq!(
+            ProtoBin::new().with_delimited(true).format_record(&r),
+            vec![0x02, 0x08, 0x01]
+        );
+    }
+}
@@ -1,13 +1,100 @@
-use crate::formatter::DataFormat;
+use crate::formatter::{DataFormat, StreamDataFormat, render};
+use crate::time_fmt::{TimeFormat, TimeStyle};
+use std::fmt::{self, Write};
 use wp_model_core::model::types::value::ObjectValue;
 use wp_model_core::model::{DataField, DataRecord, DataType, Value};
 
-#[derive(Debug, Default)]
-pub struct Raw;
+#[derive(Debug, Clone, Default)]
+pub struct Raw {
+    time_format: TimeFormat,
+}
 
 impl Raw {
-    pub fn new() -> Self {
-        Self
+    pub const fn new() -> Self {
+        Self {
+            time_format: TimeFormat::new(TimeStyle::Naive),
+        }
+    }
+    pub fn with_time_format(mut self, time_format: impl Into<TimeFormat>) -> Self {
+        self.time_format = time_format.into();
+        self
+    }
+
+    fn write_record<'a>(
+        &self,
+        fields: impl Iterator<Item = &'a DataField>,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        let mut first = true;
+        for field in fields.filter(|f| *f.get_meta() != DataType::Ignore) {
+            if !first {
+                out.write_char(' ')?;
+            }
+            first = false;
+            self.format_field_into(field, out)?;
+        }
+        Ok(())
+    }
+}
+
+impl StreamDataFormat for Raw {
+    fn format_null_into(&self, _out: &mut dyn Write) -> fmt::Result {
+        Ok(())
+    }
+    fn format_bool_into(&self, v: &bool, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_string_into(&self, v: &str, out: &mut dyn Write) -> fmt::Result {
+        out.write_str(v)
+    }
+    fn format_i64_into(&self, v: &i64, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_f64_into(&self, v: &f64, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_ip_into(&self, v: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_datetime_into(&self, v: &chrono::NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+        self.time_format.write_into(v, out)
+    }
+    fn format_object_into(&self, value: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        out.write_char('{')?;
+        for (i, (k, v)) in value.iter().enumerate() {
+            if i > 0 {
+                out.write_str(", ")?;
+            }
+            write!(out, "{}=", k)?;
+            self.fmt_value_into(v.get_value(), out)?;
+        }
+        out.write_char('}')
+    }
+    fn format_array_into(&self, value: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        out.write_char('[')?;
+        for (i, field) in value.iter().enumerate() {
+            if i > 0 {
+                out.write_str(", ")?;
+            }
+            self.fmt_value_into(field.get_value(), out)?;
+        }
+        out.write_char(']')
+    }
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        match field.get_value() {
+            Value::Chars(s) => out.write_str(s),
+            _ => self.fmt_value_into(field.get_value(), out),
+        }
+    }
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        self.write_record(record.items.iter(), out)
+    }
+    fn format_fields_into(
+        &self,
+        fields: &[(&str, &DataField)],
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        self.write_record(fields.iter().map(|(_, f)| *f), out)
     }
 }
 
@@ -32,42 +119,22 @@ impl DataFormat for Raw {
         v.to_string()
     }
     fn format_datetime(&self, v: &chrono::NaiveDateTime) -> String {
-        v.to_string()
+        self.time_format.render(v)
     }
     fn format_object(&self, value: &ObjectValue) -> String {
-        if value.is_empty() {
-            return "{}".to_string();
-        }
-        let segments: Vec<String> = value
-            .iter()
-            .map(|(k, v)| format!("{}={}", k, self.fmt_value(v.get_value())))
-            .collect();
-        format!("{{{}}}", segments.join(", "))
+        render(|out| self.format_object_into(value, out))
     }
     fn format_array(&self, value: &[DataField]) -> String {
-        if value.is_empty() {
-            return "[]".to_string();
-        }
-        let content: Vec<String> = value
-            .iter()
-            .map(|field| self.fmt_value(field.get_value()))
-            .collect();
-        format!("[{}]", content.join(", "))
+        render(|out| self.format_array_into(value, out))
     }
     fn format_field(&self, field: &DataField) -> String {
-        match field.get_value() {
-            Value::Chars(s) => s.to_string(),
-            _ => self.fmt_value(field.get_value()),
-        }
+        render(|out| self.format_field_into(field, out))
     }
     fn format_record(&self, record: &DataRecord) -> String {
-        record
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-            .map(|f| self.format_field(f))
-            .collect::<Vec<_>>()
-            .join(" ")
+        render(|out| self.format_record_into(record, out))
+    }
+    fn format_fields(&self, fields: &[(&str, &DataField)]) -> String {
+        render(|out| self.format_fields_into(fields, out))
     }
 }
 
@@ -85,26 +152,26 @@ mod tests {
 
     #[test]
     fn test_raw_default() {
-        let raw = Raw;
+        let raw = Raw::new();
         assert_eq!(raw.format_null(), "");
     }
 
     #[test]
     fn test_format_null() {
-        let raw = Raw;
+        let raw = Raw::new();
         assert_eq!(raw.format_null(), "");
     }
 
     #[test]
     fn test_format_bool() {
-        let raw = Raw;
+        let raw = Raw::new();
         assert_eq!(raw.format_bool(&true), "true");
         assert_eq!(raw.format_bool(&false), "false");
     }
 
     #[test]
     fn test_format_string() {
-        let raw = Raw;
+        let raw = Raw::new();
         assert_eq!(raw.format_string("hello"), "hello");
         assert_eq!(raw.format_string("world"), "world");
         assert_eq!(raw.format_string(""), "");
@@ -112,7 +179,7 @@ mod tests {
 
     #[test]
     fn test_format_i64() {
-        let raw = Raw;
+        let raw = Raw::new();
         assert_eq!(raw.format_i64(&0), "0");
         assert_eq!(raw.format_i64(&42), "42");
         assert_eq!(raw.format_i64(&-100), "-100");
@@ -121,7 +188,7 @@ mod tests {
 
     #[test]
     fn test_format_f64() {
-        let raw = Raw;
+        let raw = Raw::new();
         assert_eq!(raw.format_f64(&3.24), "3.24");
         assert_eq!(raw.format_f64(&0.0), "0");
         assert_eq!(raw.format_f64(&-2.5), "-2.5");
@@ -129,7 +196,7 @@ mod tests {
 
     #[test]
     fn test_format_ip() {
-        let raw = Raw;
+        let raw = Raw::new();
         let ipv4 = IpAddr::from_str("192.168.1.1").unwrap();
         assert_eq!(raw.format_ip(&ipv4), "192.168.1.1");
 
@@ -139,7 +206,7 @@ mod tests {
 
     #[test]
     fn test_format_datetime() {
-        let raw = Raw;
+        let raw = Raw::new();
         let dt = chrono::NaiveDateTime::parse_from_str("2024-01-15 10:30:45", "%Y-%m-%d %H:%M:%S")
             .unwrap();
         let result = raw.format_datetime(&dt);
@@ -150,7 +217,7 @@ mod tests {
 
     #[test]
     fn test_format_field_chars() {
-        let raw = Raw;
+        let raw = Raw::new();
         let field = DataField::from_chars("name", "Alice");
         let result = raw.format_field(&field);
         assert_eq!(result, "Alice");
@@ -158,7 +225,7 @@ mod tests {
 
     #[test]
     fn test_format_field_digit() {
-        let raw = Raw;
+        let raw = Raw::new();
         let field = DataField::from_digit("age", 30);
         let result = raw.format_field(&field);
         assert_eq!(result, "30");
@@ -166,8 +233,9 @@ mod tests {
 
     #[test]
     fn test_format_record() {
-        let raw = Raw;
+        let raw = Raw::new();
         let record = DataRecord {
+            id: 0,
             items: vec![
                 DataField::from_chars("name", "Alice"),
                 DataField::from_digit("age", 30),
@@ -179,14 +247,14 @@ mod tests {
 
     #[test]
     fn test_format_array_empty() {
-        let raw = Raw;
+        let raw = Raw::new();
         let arr: Vec<DataField> = vec![];
         assert_eq!(raw.format_array(&arr), "[]");
     }
 
     #[test]
     fn test_format_array_with_values() {
-        let raw = Raw;
+        let raw = Raw::new();
         let arr = vec![
             DataField::from_digit("", 1),
             DataField::from_digit("", 2),
@@ -198,8 +266,19 @@ mod tests {
 
     #[test]
     fn test_format_object_empty() {
-        let raw = Raw;
+        let raw = Raw::new();
         let obj = ObjectValue::new();
         assert_eq!(raw.format_object(&obj), "{}");
     }
+
+    #[test]
+    fn test_time_format_nested() {
+        let dt = chrono::NaiveDateTime::parse_from_str("2024-01-15 10:30:45", "%Y-%m-%d %H:%M:%S")
+            .unwrap();
+        let raw = Raw::new().with_time_format(TimeStyle::EpochMillis);
+        let mut obj = ObjectValue::new();
+        obj.insert("at", DataField::from_time("at", dt));
+        assert_eq!(raw.format_object(&obj), "{at=1705314645000}");
+        assert_eq!(raw.format_datetime(&dt), "1705314645000");
+    }
 }
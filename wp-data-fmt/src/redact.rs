@@ -0,0 +1,678 @@
+use crate::formatter::{DataFormat, StreamDataFormat};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fmt::{self, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use wildmatch::WildMatch;
+use wp_model_core::model::{DataField, DataRecord, DataType, Value, types::value::ObjectValue};
+
+fn default_mask() -> String {
+    "***".to_string()
+}
+fn default_keep() -> usize {
+    4
+}
+fn default_mask_char() -> char {
+    '*'
+}
+fn default_v4_prefix() -> u8 {
+    24
+}
+fn default_v6_prefix() -> u8 {
+    48
+}
+
+/// What a matching rule does to a value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+pub enum RedactAction {
+    /// Remove the field.
+    Drop,
+    /// Replace the value with fixed text.
+    Mask {
+        #[serde(default = "default_mask")]
+        mask: String,
+    },
+    /// Keep the last `keep` characters, mask the rest; values no longer than
+    /// `keep` are masked entirely.
+    Partial {
+        #[serde(default = "default_keep")]
+        keep: usize,
+        #[serde(default = "default_mask_char")]
+        mask_char: char,
+    },
+    /// HMAC-SHA256 of the value's text, hex encoded. Equal inputs give equal
+    /// outputs, so the field can still be joined on.
+    Hash { key: String },
+    /// Zero the host bits of an address; values that are not IP addresses
+    /// are left unchanged.
+    TruncateIp {
+        #[serde(default = "default_v4_prefix")]
+        v4_prefix: u8,
+        #[serde(default = "default_v6_prefix")]
+        v6_prefix: u8,
+    },
+}
+
+/// Selects values by name and/or type. A rule matches when every selector
+/// it sets matches, so a rule with neither matches nothing.
+///
+/// Field patterns are compiled once, when the rule is built or
+/// deserialized.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "RuleConf", into = "RuleConf")]
+pub struct RedactRule {
+    fields: Vec<String>,
+    patterns: Vec<WildMatch>,
+    types: Vec<DataType>,
+    action: RedactAction,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct RuleConf {
+    #[serde(default)]
+    fields: Vec<String>,
+    #[serde(default)]
+    types: Vec<DataType>,
+    #[serde(flatten)]
+    action: RedactAction,
+}
+
+impl From<RuleConf> for RedactRule {
+    fn from(conf: RuleConf) -> Self {
+        RedactRule::new(conf.action)
+            .with_fields(conf.fields)
+            .with_types(conf.types)
+    }
+}
+
+impl From<RedactRule> for RuleConf {
+    fn from(rule: RedactRule) -> Self {
+        RuleConf {
+            fields: rule.fields,
+            types: rule.types,
+            action: rule.action,
+        }
+    }
+}
+
+impl RedactRule {
+    pub fn new(action: RedactAction) -> Self {
+        Self {
+            fields: Vec::new(),
+            patterns: Vec::new(),
+            types: Vec::new(),
+            action,
+        }
+    }
+    /// Wildcard patterns (`*`, `?`) tried against the field name and its
+    /// dotted path (`user.phone`); array elements share the array's path.
+    pub fn with_fields<S: Into<String>>(mut self, fields: impl IntoIterator<Item = S>) -> Self {
+        self.fields = fields.into_iter().map(Into::into).collect();
+        self.patterns = self.fields.iter().map(|f| WildMatch::new(f)).collect();
+        self
+    }
+    /// Value types by their model names (`ip`, `chars`, `mobile_phone`, ...).
+    pub fn with_types(mut self, types: impl IntoIterator<Item = DataType>) -> Self {
+        self.types = types.into_iter().collect();
+        self
+    }
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+    pub fn types(&self) -> &[DataType] {
+        &self.types
+    }
+    pub fn action(&self) -> &RedactAction {
+        &self.action
+    }
+
+    fn matches(&self, name: Option<&str>, path: &str, field: &DataField) -> bool {
+        if self.patterns.is_empty() && self.types.is_empty() {
+            return false;
+        }
+        // A value formatted on its own has neither name nor path.
+        let by_name = self.patterns.is_empty()
+            || self.patterns.iter().any(|pattern| {
+                name.is_some_and(|n| pattern.matches(n))
+                    || (!path.is_empty() && pattern.matches(path))
+            });
+        let by_type = self.types.is_empty()
+            || self.types.iter().any(|t| {
+                t == field.get_meta() || value_type(field.get_value()).is_some_and(|v| *t == v)
+            });
+        by_name && by_type
+    }
+}
+
+/// Type a value has regardless of the field's declared meta.
+fn value_type(value: &Value) -> Option<DataType> {
+    Some(match value {
+        Value::Bool(_) => DataType::Bool,
+        Value::Chars(_) => DataType::Chars,
+        Value::Digit(_) => DataType::Digit,
+        Value::Float(_) => DataType::Float,
+        Value::Time(_) => DataType::Time,
+        Value::IpAddr(_) => DataType::IP,
+        Value::IpNet(_) => DataType::IpNet,
+        Value::Domain(_) => DataType::Domain,
+        Value::Url(_) => DataType::Url,
+        Value::Email(_) => DataType::Email,
+        Value::IdCard(_) => DataType::IdCard,
+        Value::MobilePhone(_) => DataType::MobilePhone,
+        Value::Hex(_) => DataType::Hex,
+        Value::Obj(_) => DataType::Obj,
+        _ => return None,
+    })
+}
+
+/// Masks sensitive values before records reach a sink.
+///
+/// Rules are tried in order and the first match wins. A rule matching an
+/// `Obj` or `Array` applies to everything inside it; otherwise nested
+/// values are matched one by one.
+///
+/// ```toml
+/// [[rules]]
+/// fields = ["password", "*token*"]
+/// action = "drop"
+///
+/// [[rules]]
+/// fields = ["phone"]
+/// action = "partial"
+///
+/// [[rules]]
+/// types = ["ip"]
+/// action = "truncate_ip"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedactPolicy {
+    pub rules: Vec<RedactRule>,
+}
+
+impl RedactPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_rule(mut self, rule: RedactRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Wraps a formatter so every field and record it formats is redacted
+    /// first.
+    pub fn wrap<F>(self, inner: F) -> Redacted<F> {
+        Redacted {
+            policy: self,
+            inner,
+        }
+    }
+
+    pub fn apply(&self, record: &DataRecord) -> DataRecord {
+        DataRecord {
+            id: record.id,
+            items: record
+                .items
+                .iter()
+                .filter_map(|f| self.apply_field(f))
+                .collect(),
+        }
+    }
+
+    /// The redacted field, or `None` when a rule drops it.
+    pub fn apply_field(&self, field: &DataField) -> Option<DataField> {
+        self.redact(field, Some(field.get_name()), field.get_name(), None)
+    }
+
+    /// A value redacted on its own: only type rules apply to it, while
+    /// keys inside an object are still matched by name. `None` when a rule
+    /// drops it.
+    pub fn apply_value(&self, value: Value) -> Option<Value> {
+        if self.rules.is_empty() {
+            return Some(value);
+        }
+        let field = DataField::new(DataType::default(), "", value);
+        self.redact(&field, None, "", None).map(|f| f.value)
+    }
+
+    fn redact(
+        &self,
+        field: &DataField,
+        name: Option<&str>,
+        path: &str,
+        inherited: Option<&RedactAction>,
+    ) -> Option<DataField> {
+        if *field.get_meta() == DataType::Ignore {
+            return Some(field.clone());
+        }
+        let action = inherited.or_else(|| {
+            self.rules
+                .iter()
+                .find(|r| r.matches(name, path, field))
+                .map(|r| &r.action)
+        });
+        if action == Some(&RedactAction::Drop) {
+            return None;
+        }
+        let value = match field.get_value() {
+            Value::Obj(obj) => {
+                let mut out = ObjectValue::new();
+                for (key, child) in obj.iter() {
+                    let child_path = if path.is_empty() {
+                        key.to_string()
+                    } else {
+                        format!("{}.{}", path, key)
+                    };
+                    if let Some(child) = self.redact(child, Some(key), &child_path, action) {
+                        out.insert(key.clone(), child);
+                    }
+                }
+                Value::Obj(out)
+            }
+            Value::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .filter_map(|item| self.redact(item, None, path, action))
+                    .collect(),
+            ),
+            value => match action {
+                Some(action) => return Some(redact_value(action, field, value)),
+                None => return Some(field.clone()),
+            },
+        };
+        Some(DataField::new(
+            field.get_meta().clone(),
+            field.get_name(),
+            value,
+        ))
+    }
+}
+
+fn redact_value(action: &RedactAction, field: &DataField, value: &Value) -> DataField {
+    if matches!(value, Value::Null) {
+        return field.clone();
+    }
+    let text = || match value {
+        Value::Chars(s) => s.to_string(),
+        other => other.to_string(),
+    };
+    let masked = match action {
+        RedactAction::Drop => unreachable!("dropped before reaching values"),
+        RedactAction::Mask { mask } => mask.clone(),
+        RedactAction::Partial { keep, mask_char } => {
+            let text = text();
+            let len = text.chars().count();
+            let hidden = if len > *keep { len - keep } else { len };
+            text.chars()
+                .enumerate()
+                .map(|(i, c)| if i < hidden { *mask_char } else { c })
+                .collect()
+        }
+        RedactAction::Hash { key } => {
+            let mut mac =
+                Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("hmac takes any key length");
+            mac.update(text().as_bytes());
+            mac.finalize().into_bytes()
+        }
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect(),
+        RedactAction::TruncateIp {
+            v4_prefix,
+            v6_prefix,
+        } => {
+            let truncate = |ip: IpAddr| match ip {
+                IpAddr::V4(v4) => {
+                    IpAddr::V4(Ipv4Addr::from(u32::from(v4) & prefix_mask32(*v4_prefix)))
+                }
+                IpAddr::V6(v6) => {
+                    IpAddr::V6(Ipv6Addr::from(u128::from(v6) & prefix_mask128(*v6_prefix)))
+                }
+            };
+            match value {
+                Value::IpAddr(ip) => {
+                    return DataField::new(
+                        field.get_meta().clone(),
+                        field.get_name(),
+                        Value::IpAddr(truncate(*ip)),
+                    );
+                }
+                Value::Chars(s) => match s.parse::<IpAddr>() {
+                    Ok(ip) => truncate(ip).to_string(),
+                    Err(_) => return field.clone(),
+                },
+                _ => return field.clone(),
+            }
+        }
+    };
+    DataField::from_chars(field.get_name(), masked.as_str())
+}
+
+fn prefix_mask32(prefix: u8) -> u32 {
+    match prefix.min(32) {
+        0 => 0,
+        p => u32::MAX << (32 - p),
+    }
+}
+
+fn prefix_mask128(prefix: u8) -> u128 {
+    match prefix.min(128) {
+        0 => 0,
+        p => u128::MAX << (128 - p),
+    }
+}
+
+/// A formatter behind a [`RedactPolicy`]. Fields and records are redacted
+/// before the inner formatter sees them, and values formatted on their own
+/// go through [`RedactPolicy::apply_value`]; anything dropped formats as
+/// nothing.
+#[derive(Debug, Clone)]
+pub struct Redacted<F> {
+    policy: RedactPolicy,
+    inner: F,
+}
+
+impl<F> Redacted<F> {
+    pub fn policy(&self) -> &RedactPolicy {
+        &self.policy
+    }
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+}
+
+impl<F: StreamDataFormat> Redacted<F> {
+    fn value_into(&self, value: Value, out: &mut dyn Write) -> fmt::Result {
+        match self.policy.apply_value(value) {
+            Some(value) => self.inner.fmt_value_into(&value, out),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<F: StreamDataFormat> StreamDataFormat for Redacted<F> {
+    fn format_null_into(&self, out: &mut dyn Write) -> fmt::Result {
+        self.value_into(Value::Null, out)
+    }
+    fn format_bool_into(&self, v: &bool, out: &mut dyn Write) -> fmt::Result {
+        self.value_into(Value::Bool(*v), out)
+    }
+    fn format_string_into(&self, v: &str, out: &mut dyn Write) -> fmt::Result {
+        self.value_into(Value::from(v), out)
+    }
+    fn format_i64_into(&self, v: &i64, out: &mut dyn Write) -> fmt::Result {
+        self.value_into(Value::Digit(*v), out)
+    }
+    fn format_f64_into(&self, v: &f64, out: &mut dyn Write) -> fmt::Result {
+        self.value_into(Value::Float(*v), out)
+    }
+    fn format_ip_into(&self, v: &IpAddr, out: &mut dyn Write) -> fmt::Result {
+        self.value_into(Value::IpAddr(*v), out)
+    }
+    fn format_datetime_into(&self, v: &chrono::NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+        self.value_into(Value::Time(*v), out)
+    }
+    fn format_object_into(&self, value: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        self.value_into(Value::Obj(value.clone()), out)
+    }
+    fn format_array_into(&self, value: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        self.value_into(Value::Array(value.to_vec()), out)
+    }
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        match self.policy.apply_field(field) {
+            Some(field) => self.inner.format_field_into(&field, out),
+            None => Ok(()),
+        }
+    }
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        self.inner
+            .format_record_into(&self.policy.apply(record), out)
+    }
+}
+
+impl<F> Redacted<F>
+where
+    F: DataFormat,
+    F::Output: Default,
+{
+    fn value(&self, value: Value) -> F::Output {
+        match self.policy.apply_value(value) {
+            Some(value) => self.inner.fmt_value(&value),
+            None => F::Output::default(),
+        }
+    }
+}
+
+impl<F> DataFormat for Redacted<F>
+where
+    F: DataFormat,
+    F::Output: Default,
+{
+    type Output = F::Output;
+    fn format_null(&self) -> F::Output {
+        self.value(Value::Null)
+    }
+    fn format_bool(&self, v: &bool) -> F::Output {
+        self.value(Value::Bool(*v))
+    }
+    fn format_string(&self, v: &str) -> F::Output {
+        self.value(Value::from(v))
+    }
+    fn format_i64(&self, v: &i64) -> F::Output {
+        self.value(Value::Digit(*v))
+    }
+    fn format_f64(&self, v: &f64) -> F::Output {
+        self.value(Value::Float(*v))
+    }
+    fn format_ip(&self, v: &IpAddr) -> F::Output {
+        self.value(Value::IpAddr(*v))
+    }
+    fn format_datetime(&self, v: &chrono::NaiveDateTime) -> F::Output {
+        self.value(Value::Time(*v))
+    }
+    fn format_object(&self, value: &ObjectValue) -> F::Output {
+        self.value(Value::Obj(value.clone()))
+    }
+    fn format_array(&self, value: &[DataField]) -> F::Output {
+        self.value(Value::Array(value.to_vec()))
+    }
+    fn format_field(&self, field: &DataField) -> F::Output {
+        match self.policy.apply_field(field) {
+            Some(field) => self.inner.format_field(&field),
+            None => F::Output::default(),
+        }
+    }
+    fn format_record(&self, record: &DataRecord) -> F::Output {
+        self.inner.format_record(&self.policy.apply(record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Json;
+
+    fn record() -> DataRecord {
+        let mut user = ObjectValue::new();
+        user.insert("phone", DataField::from_chars("phone", "13812345678"));
+        user.insert("password", DataField::from_chars("password", "hunter2"));
+        DataRecord {
+            id: 3,
+            items: vec![
+                DataField::from_ip("ip", "10.1.2.3".parse().unwrap()),
+                DataField::from_chars("api_token", "abc"),
+                DataField::from_obj("user", user),
+                DataField::from_arr(
+                    "peers",
+                    vec![
+                        DataField::from_ip("", "2001:db8:1:2::7".parse().unwrap()),
+                        DataField::from_chars("", "192.168.9.9"),
+                    ],
+                ),
+                DataField::from_digit("status", 200),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_empty_policy_is_identity() {
+        assert_eq!(RedactPolicy::new().apply(&record()), record());
+    }
+
+    #[test]
+    fn test_rules_reach_nested_values() {
+        let policy = RedactPolicy::new()
+            .with_rule(RedactRule::new(RedactAction::Drop).with_fields(["password", "*token*"]))
+            .with_rule(
+                RedactRule::new(RedactAction::Partial {
+                    keep: 4,
+                    mask_char: '*',
+                })
+                .with_fields(["user.phone"]),
+            )
+            .with_rule(
+                RedactRule::new(RedactAction::TruncateIp {
+                    v4_prefix: 24,
+                    v6_prefix: 48,
+                })
+                .with_types([DataType::IP]),
+            )
+            .with_rule(
+                RedactRule::new(RedactAction::TruncateIp {
+                    v4_prefix: 16,
+                    v6_prefix: 48,
+                })
+                .with_fields(["peers"]),
+            );
+        assert_eq!(
+            Json::new().format_record(&policy.apply(&record())),
+            r#"{"ip":"10.1.2.0","user":{"phone":"*******5678"},"peers":["2001:db8:1::","192.168.0.0"],"status":200}"#
+        );
+    }
+
+    #[test]
+    fn test_mask_and_hash() {
+        let mask = RedactAction::Mask {
+            mask: default_mask(),
+        };
+        let f = DataField::from_chars("card", "4111");
+        assert_eq!(
+            redact_value(&mask, &f, f.get_value()).get_value(),
+            &Value::from("***")
+        );
+
+        let partial = RedactAction::Partial {
+            keep: 4,
+            mask_char: '#',
+        };
+        assert_eq!(
+            redact_value(&partial, &f, f.get_value()).get_value(),
+            &Value::from("####")
+        );
+
+        let hash = RedactAction::Hash { key: "Jefe".into() };
+        let f = DataField::from_chars("q", "what do ya want for nothing?");
+        assert_eq!(
+            redact_value(&hash, &f, f.get_value()).get_value(),
+            &Value::from("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+        );
+    }
+
+    #[test]
+    fn test_selectors_combine() {
+        let rule = RedactRule::new(RedactAction::Drop)
+            .with_fields(["user.*"])
+            .with_types([DataType::Chars]);
+        let policy = RedactPolicy::new().with_rule(rule);
+        let mut user = ObjectValue::new();
+        user.insert("name", DataField::from_chars("name", "bob"));
+        user.insert("age", DataField::from_digit("age", 30));
+        let r = DataRecord {
+            id: 0,
+            items: vec![
+                DataField::from_obj("user", user),
+                DataField::from_chars("name", "bob"),
+            ],
+        };
+        assert_eq!(
+            Json::new().format_record(&policy.apply(&r)),
+            r#"{"user":{"age":30},"name":"bob"}"#
+        );
+        // no selector: matches nothing
+        let policy = RedactPolicy::new().with_rule(RedactRule::new(RedactAction::Drop));
+        assert_eq!(policy.apply(&r), r);
+    }
+
+    #[test]
+    fn test_wrap_formatter() {
+        let policy = RedactPolicy::new()
+            .with_rule(RedactRule::new(RedactAction::Drop).with_fields(["user", "peers", "ip"]));
+        let f = policy.wrap(Json::new());
+        assert_eq!(
+            f.format_record(&record()),
+            r#"{"api_token":"abc","status":200}"#
+        );
+        let mut buf = String::new();
+        f.format_record_into(&record(), &mut buf).unwrap();
+        assert_eq!(buf, r#"{"api_token":"abc","status":200}"#);
+        assert_eq!(f.format_field(&DataField::from_chars("ip", "x")), "");
+    }
+
+    #[test]
+    fn test_wrap_formats_values() {
+        let policy = RedactPolicy::new()
+            .with_rule(RedactRule::new(RedactAction::Drop).with_fields(["password", "*"]))
+            .with_rule(
+                RedactRule::new(RedactAction::TruncateIp {
+                    v4_prefix: 24,
+                    v6_prefix: 48,
+                })
+                .with_types([DataType::IP]),
+            )
+            .with_rule(
+                RedactRule::new(RedactAction::Mask {
+                    mask: default_mask(),
+                })
+                .with_types([DataType::Chars]),
+            );
+        let f = policy.wrap(Json::new());
+        // name patterns have nothing to match on a bare value
+        assert_eq!(f.format_ip(&"10.1.2.3".parse().unwrap()), r#""10.1.2.0""#);
+        assert_eq!(f.format_string("secret"), r#""***""#);
+        assert_eq!(f.format_i64(&7), "7");
+
+        let mut user = ObjectValue::new();
+        user.insert("password", DataField::from_chars("password", "hunter2"));
+        user.insert("ip", DataField::from_ip("ip", "10.1.2.3".parse().unwrap()));
+        let mut buf = String::new();
+        f.format_object_into(&user, &mut buf).unwrap();
+        assert_eq!(buf, "{}");
+
+        let peers = [DataField::from_ip("", "10.9.8.7".parse().unwrap())];
+        assert_eq!(f.format_array(&peers), r#"["10.9.8.0"]"#);
+    }
+
+    #[test]
+    fn test_rule_serde_compiles_patterns() {
+        let policy: RedactPolicy = toml::from_str(
+            r#"
+            [[rules]]
+            fields = ["*token*"]
+            action = "drop"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            policy,
+            RedactPolicy::new()
+                .with_rule(RedactRule::new(RedactAction::Drop).with_fields(["*token*"]))
+        );
+        assert_eq!(policy.rules[0].fields(), ["*token*"]);
+        assert_eq!(
+            policy.apply_field(&DataField::from_chars("api_token", "x")),
+            None
+        );
+    }
+}
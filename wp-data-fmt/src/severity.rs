@@ -0,0 +1,38 @@
+use wp_model_core::model::Value;
+
+/// Syslog severity (0-7) from a number or a level name such as `warn`,
+/// `ERROR` or `critical`.
+pub(crate) fn parse_severity(value: &Value) -> Option<u8> {
+    let code = match value {
+        Value::Digit(n) => return u8::try_from(*n).ok().filter(|n| *n <= 7),
+        Value::Chars(s) => s.trim().to_ascii_lowercase(),
+        _ => return None,
+    };
+    if let Ok(n) = code.parse::<u8>() {
+        return (n <= 7).then_some(n);
+    }
+    match code.as_str() {
+        "emerg" | "emergency" | "panic" => Some(0),
+        "alert" => Some(1),
+        "crit" | "critical" | "fatal" => Some(2),
+        "err" | "error" => Some(3),
+        "warning" | "warn" => Some(4),
+        "notice" => Some(5),
+        "info" | "informational" => Some(6),
+        "debug" | "trace" => Some(7),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_severity() {
+        assert_eq!(parse_severity(&Value::Chars("WARN".into())), Some(4));
+        assert_eq!(parse_severity(&Value::Digit(7)), Some(7));
+        assert_eq!(parse_severity(&Value::Digit(8)), None);
+        assert_eq!(parse_severity(&Value::Chars("loud".into())), None);
+    }
+}
@@ -1,11 +1,54 @@
-use crate::formatter::DataFormat;
+use crate::SqlDialect;
+use crate::formatter::{DataFormat, StreamDataFormat, render};
+use crate::time_fmt::TimeFormat;
+use crate::{SchemaChange, SqlColumn, SqlSchema};
+use std::fmt::{self, Write};
 use wp_model_core::model::fmt_def::TextFmt;
 use wp_model_core::model::{DataField, DataRecord, DataType, Value, types::value::ObjectValue};
 
+/// A value bound to a statement placeholder instead of inlined as a literal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlParam {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Timestamp(chrono::NaiveDateTime),
+    Inet(std::net::IpAddr),
+    /// Objects and arrays, rendered by the `obj_formatter`.
+    Json(String),
+}
+
+/// Statement text with dialect placeholders and one row of bound values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlStatement {
+    pub sql: String,
+    pub params: Vec<SqlParam>,
+}
+
+/// One statement executed once per parameter row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlBatch {
+    pub sql: String,
+    pub rows: Vec<Vec<SqlParam>>,
+}
+
+/// DDL bringing an existing table in line with a batch schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlMigration {
+    pub statements: Vec<String>,
+    /// Changes left for an operator: destructive ones, alterations when
+    /// widening is off, and alterations the dialect cannot express.
+    pub skipped: Vec<SchemaChange>,
+}
+
 pub struct SqlInsert {
     pub table_name: String,
     pub quote_identifiers: bool,
     pub obj_formatter: crate::SqlFormat,
+    pub dialect: SqlDialect,
+    pub time_format: TimeFormat,
 }
 
 impl Default for SqlInsert {
@@ -14,6 +57,8 @@ impl Default for SqlInsert {
             table_name: String::new(),
             quote_identifiers: true,
             obj_formatter: crate::SqlFormat::from(&TextFmt::Json),
+            dialect: SqlDialect::default(),
+            time_format: TimeFormat::default(),
         }
     }
 }
@@ -24,18 +69,145 @@ impl SqlInsert {
             table_name: table.into(),
             quote_identifiers: true,
             obj_formatter: crate::SqlFormat::from(&TextFmt::Json),
+            dialect: SqlDialect::default(),
+            time_format: TimeFormat::default(),
         }
     }
+    pub fn with_dialect(mut self, dialect: SqlDialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+    /// Applies to time columns and to times nested in rendered objects and
+    /// arrays. Epoch styles are written as numbers, the others as strings.
+    pub fn with_time_format(mut self, time_format: impl Into<TimeFormat>) -> Self {
+        let time_format = time_format.into();
+        self.obj_formatter = match self.obj_formatter {
+            crate::SqlFormat::Json(f) => {
+                crate::SqlFormat::Json(f.with_time_format(time_format.clone()))
+            }
+            crate::SqlFormat::Kv(f) => {
+                crate::SqlFormat::Kv(f.with_time_format(time_format.clone()))
+            }
+            crate::SqlFormat::Raw(f) => {
+                crate::SqlFormat::Raw(f.with_time_format(time_format.clone()))
+            }
+            crate::SqlFormat::ProtoText(f) => {
+                crate::SqlFormat::ProtoText(f.with_time_format(time_format.clone()))
+            }
+        };
+        self.time_format = time_format;
+        self
+    }
     fn quote_identifier(&self, name: &str) -> String {
+        render(|out| self.write_identifier(name, out))
+    }
+    fn write_identifier(&self, name: &str, out: &mut dyn Write) -> fmt::Result {
         if self.quote_identifiers {
-            let escaped = name.replace('"', "\"\"");
-            format!("\"{}\"", escaped)
+            self.dialect.write_identifier(name, out)
+        } else {
+            out.write_str(name)
+        }
+    }
+    fn write_quoted(&self, value: &str, out: &mut dyn Write) -> fmt::Result {
+        self.dialect.write_string(value, out)
+    }
+    fn render_object(&self, value: &ObjectValue) -> String {
+        render(|buf| match &self.obj_formatter {
+            crate::SqlFormat::Json(f) => f.format_object_into(value, buf),
+            crate::SqlFormat::Kv(f) => f.format_object_into(value, buf),
+            crate::SqlFormat::Raw(f) => f.format_object_into(value, buf),
+            crate::SqlFormat::ProtoText(f) => f.format_object_into(value, buf),
+        })
+    }
+    fn render_array(&self, value: &[DataField]) -> String {
+        render(|buf| match &self.obj_formatter {
+            crate::SqlFormat::Json(f) => f.format_array_into(value, buf),
+            crate::SqlFormat::Kv(f) => f.format_array_into(value, buf),
+            crate::SqlFormat::Raw(f) => f.format_array_into(value, buf),
+            crate::SqlFormat::ProtoText(f) => f.format_array_into(value, buf),
+        })
+    }
+    fn write_columns(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        for (i, f) in record
+            .items
+            .iter()
+            .filter(|f| *f.get_meta() != DataType::Ignore)
+            .enumerate()
+        {
+            if i > 0 {
+                out.write_str(", ")?;
+            }
+            self.write_identifier(f.get_name(), out)?;
+        }
+        Ok(())
+    }
+    fn write_values(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        for (i, f) in record
+            .items
+            .iter()
+            .filter(|f| *f.get_meta() != DataType::Ignore)
+            .enumerate()
+        {
+            if i > 0 {
+                out.write_str(", ")?;
+            }
+            self.format_field_into(f, out)?;
+        }
+        Ok(())
+    }
+}
+
+impl StreamDataFormat for SqlInsert {
+    fn format_null_into(&self, out: &mut dyn Write) -> fmt::Result {
+        out.write_str("NULL")
+    }
+    fn format_bool_into(&self, value: &bool, out: &mut dyn Write) -> fmt::Result {
+        out.write_str(self.dialect.bool_literal(*value))
+    }
+    fn format_string_into(&self, value: &str, out: &mut dyn Write) -> fmt::Result {
+        self.write_quoted(value, out)
+    }
+    fn format_i64_into(&self, value: &i64, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", value)
+    }
+    fn format_f64_into(&self, value: &f64, out: &mut dyn Write) -> fmt::Result {
+        self.dialect.write_f64(*value, out)
+    }
+    fn format_ip_into(&self, value: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "'{}'", value)
+    }
+    fn format_datetime_into(
+        &self,
+        value: &chrono::NaiveDateTime,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        if self.time_format.is_numeric() {
+            self.time_format.write_into(value, out)
         } else {
-            name.to_string()
+            self.write_quoted(&self.time_format.render(value), out)
         }
     }
-    fn escape_string(&self, value: &str) -> String {
-        value.replace('\'', "''")
+    fn format_object_into(&self, value: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        self.write_quoted(&self.render_object(value), out)
+    }
+    fn format_array_into(&self, value: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        self.write_quoted(&self.render_array(value), out)
+    }
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        out.write_str("INSERT INTO ")?;
+        self.write_identifier(&self.table_name, out)?;
+        out.write_str(" (")?;
+        self.write_columns(record, out)?;
+        out.write_str(") VALUES (")?;
+        self.write_values(record, out)?;
+        out.write_str(");")
+    }
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        if *field.get_meta() == DataType::Ignore {
+            Ok(())
+        } else {
+            self.fmt_value_into(field.get_value(), out)
+        }
     }
 }
 
@@ -45,148 +217,278 @@ impl DataFormat for SqlInsert {
         "NULL".to_string()
     }
     fn format_bool(&self, value: &bool) -> String {
-        if *value { "TRUE" } else { "FALSE" }.to_string()
+        self.dialect.bool_literal(*value).to_string()
     }
     fn format_string(&self, value: &str) -> String {
-        format!("'{}'", self.escape_string(value))
+        render(|out| self.format_string_into(value, out))
     }
     fn format_i64(&self, value: &i64) -> String {
         value.to_string()
     }
     fn format_f64(&self, value: &f64) -> String {
-        if value.is_nan() {
-            "NULL".into()
-        } else if value.is_infinite() {
-            if value.is_sign_positive() {
-                "'Infinity'".into()
-            } else {
-                "'-Infinity'".into()
-            }
-        } else {
-            value.to_string()
-        }
+        render(|out| self.format_f64_into(value, out))
     }
     fn format_ip(&self, value: &std::net::IpAddr) -> String {
-        self.format_string(&value.to_string())
+        render(|out| self.format_ip_into(value, out))
     }
     fn format_datetime(&self, value: &chrono::NaiveDateTime) -> String {
-        self.format_string(&value.to_string())
+        render(|out| self.format_datetime_into(value, out))
     }
     fn format_object(&self, value: &ObjectValue) -> String {
-        let inner = match &self.obj_formatter {
-            crate::SqlFormat::Json(f) => f.format_object(value),
-            crate::SqlFormat::Kv(f) => f.format_object(value),
-            crate::SqlFormat::Raw(f) => f.format_object(value),
-            crate::SqlFormat::ProtoText(f) => f.format_object(value),
-        };
-        format!("'{}'", self.escape_string(&inner))
+        render(|out| self.format_object_into(value, out))
     }
     fn format_array(&self, value: &[DataField]) -> String {
-        let inner = match &self.obj_formatter {
-            crate::SqlFormat::Json(f) => f.format_array(value),
-            crate::SqlFormat::Kv(f) => f.format_array(value),
-            crate::SqlFormat::Raw(f) => f.format_array(value),
-            crate::SqlFormat::ProtoText(f) => f.format_array(value),
-        };
-        format!("'{}'", self.escape_string(&inner))
+        render(|out| self.format_array_into(value, out))
     }
     fn format_record(&self, record: &DataRecord) -> String {
-        let columns: Vec<String> = record
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-            .map(|f| self.quote_identifier(f.get_name()))
-            .collect();
-        let values: Vec<String> = record
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-            .map(|f| self.format_field(f))
-            .collect();
-        format!(
-            "INSERT INTO {} ({}) VALUES ({});",
-            self.quote_identifier(&self.table_name),
-            columns.join(", "),
-            values.join(", ")
-        )
+        render(|out| self.format_record_into(record, out))
     }
     fn format_field(&self, field: &DataField) -> String {
-        if *field.get_meta() == DataType::Ignore {
-            String::new()
-        } else {
-            self.fmt_value(field.get_value())
-        }
+        render(|out| self.format_field_into(field, out))
     }
 }
 
 impl SqlInsert {
     pub fn format_batch(&self, records: &[DataRecord]) -> String {
+        render(|out| self.format_batch_into(records, out))
+    }
+    /// Multi-row insert over the union of the records' columns (see
+    /// [`SqlSchema::infer`]); a record without a column gets `NULL` there.
+    pub fn format_batch_into(&self, records: &[DataRecord], out: &mut dyn Write) -> fmt::Result {
         if records.is_empty() {
-            return String::new();
+            return Ok(());
+        }
+        let schema = SqlSchema::infer(records);
+        out.write_str("INSERT INTO ")?;
+        self.write_identifier(&self.table_name, out)?;
+        out.write_str(" (")?;
+        for (i, column) in schema.columns().iter().enumerate() {
+            if i > 0 {
+                out.write_str(", ")?;
+            }
+            self.write_identifier(&column.name, out)?;
         }
-        let mut output = String::new();
-        let columns: Vec<String> = records[0]
+        out.write_str(") VALUES\n")?;
+        for (i, record) in records.iter().enumerate() {
+            if i > 0 {
+                out.write_str(",\n")?;
+            }
+            out.write_str("  (")?;
+            for (j, column) in schema.columns().iter().enumerate() {
+                if j > 0 {
+                    out.write_str(", ")?;
+                }
+                match find_column(record, &column.name) {
+                    Some(field) => self.format_field_into(field, out)?,
+                    None => self.format_null_into(out)?,
+                }
+            }
+            out.write_char(')')?;
+        }
+        out.write_char(';')
+    }
+    /// Converts a value into the parameter bound for it.
+    pub fn to_param(&self, value: &Value) -> SqlParam {
+        match value {
+            Value::Null | Value::Ignore(_) => SqlParam::Null,
+            Value::Bool(v) => SqlParam::Bool(*v),
+            Value::Digit(v) => SqlParam::Int(*v),
+            Value::Float(v) => SqlParam::Float(*v),
+            Value::Chars(v) => SqlParam::Text(v.to_string()),
+            Value::Time(v) => SqlParam::Timestamp(*v),
+            Value::IpAddr(v) => SqlParam::Inet(*v),
+            Value::Obj(v) => SqlParam::Json(self.render_object(v)),
+            Value::Array(v) => SqlParam::Json(self.render_array(v)),
+            other => SqlParam::Text(other.to_string()),
+        }
+    }
+    /// `INSERT` with placeholders (`$1` for PostgreSQL, `?` elsewhere) and
+    /// the record's values as parameters. The statement has no trailing `;`
+    /// since drivers prepare single statements.
+    pub fn format_record_params(&self, record: &DataRecord) -> SqlStatement {
+        let fields: Vec<&DataField> = record
             .items
             .iter()
             .filter(|f| *f.get_meta() != DataType::Ignore)
-            .map(|f| self.quote_identifier(f.get_name()))
             .collect();
-        use std::fmt::Write;
-        writeln!(
-            output,
-            "INSERT INTO {} ({}) VALUES",
-            self.quote_identifier(&self.table_name),
-            columns.join(", ")
-        )
-        .unwrap();
-        for (i, record) in records.iter().enumerate() {
-            if i > 0 {
-                output.push_str(",\n");
-            }
-            let values: Vec<String> = record
-                .items
+        let columns: Vec<&str> = fields.iter().map(|f| f.get_name()).collect();
+        SqlStatement {
+            sql: self.param_insert(&columns),
+            params: fields
                 .iter()
-                .filter(|f| *f.get_meta() != DataType::Ignore)
-                .map(|f| self.format_field(f))
-                .collect();
-            write!(output, "  ({})", values.join(", ")).unwrap();
+                .map(|f| self.to_param(f.get_value()))
+                .collect(),
         }
-        output.push(';');
-        output
     }
-    pub fn generate_create_table(&self, records: &[DataRecord]) -> String {
+    /// One statement for the whole batch, with a parameter row per record.
+    /// Columns are the union inferred by [`SqlSchema::infer`]; a record
+    /// without a column binds `Null` there.
+    pub fn format_batch_params(&self, records: &[DataRecord]) -> SqlBatch {
         if records.is_empty() {
-            return String::new();
+            return SqlBatch {
+                sql: String::new(),
+                rows: Vec::new(),
+            };
+        }
+        let schema = SqlSchema::infer(records);
+        let columns: Vec<&str> = schema.columns().iter().map(|c| c.name.as_str()).collect();
+        let rows = records
+            .iter()
+            .map(|record| {
+                columns
+                    .iter()
+                    .map(|col| {
+                        find_column(record, col)
+                            .map_or(SqlParam::Null, |f| self.to_param(f.get_value()))
+                    })
+                    .collect()
+            })
+            .collect();
+        SqlBatch {
+            sql: self.param_insert(&columns),
+            rows,
         }
-        let mut columns = Vec::new();
-        for field in &records[0].items {
-            if *field.get_meta() == DataType::Ignore {
-                continue;
+    }
+    fn param_insert(&self, columns: &[&str]) -> String {
+        render(|out| {
+            out.write_str("INSERT INTO ")?;
+            self.write_identifier(&self.table_name, out)?;
+            out.write_str(" (")?;
+            for (i, col) in columns.iter().enumerate() {
+                if i > 0 {
+                    out.write_str(", ")?;
+                }
+                self.write_identifier(col, out)?;
             }
-            let sql_type = &match field.get_value() {
-                Value::Bool(_) => "BOOLEAN",
-                Value::Chars(_) => "TEXT",
-                Value::Digit(_) => "BIGINT",
-                Value::Float(_) => "DOUBLE PRECISION",
-                Value::Time(_) => "TIMESTAMP",
-                Value::IpAddr(_) => "INET",
-                Value::Obj(_) | Value::Array(_) => "JSONB",
-                _ => "TEXT",
-            };
-            columns.push(format!(
-                "  {} {}",
-                self.quote_identifier(field.get_name()),
-                sql_type
-            ));
+            out.write_str(") VALUES (")?;
+            for i in 0..columns.len() {
+                if i > 0 {
+                    out.write_str(", ")?;
+                }
+                self.dialect.write_placeholder(i + 1, out)?;
+            }
+            out.write_char(')')
+        })
+    }
+    /// `CREATE TABLE` for the schema inferred from all records, so later
+    /// records can add columns or widen their types.
+    pub fn generate_create_table(&self, records: &[DataRecord]) -> String {
+        if records.is_empty() {
+            return String::new();
         }
+        let columns: Vec<String> = SqlSchema::infer(records)
+            .columns()
+            .iter()
+            .map(|column| {
+                format!(
+                    "  {} {}",
+                    self.quote_identifier(&column.name),
+                    self.dialect.column_definition(column)
+                )
+            })
+            .collect();
+        // ClickHouse tables need an engine; MergeTree without a key is the neutral choice
+        let engine = match self.dialect {
+            SqlDialect::ClickHouse => " ENGINE = MergeTree ORDER BY tuple()",
+            _ => "",
+        };
         format!(
-            "CREATE TABLE IF NOT EXISTS {} (\n{}\n);",
+            "CREATE TABLE IF NOT EXISTS {} (\n{}\n){};",
             self.quote_identifier(&self.table_name),
-            columns.join(",\n")
+            columns.join(",\n"),
+            engine
         )
     }
+    /// `ALTER TABLE` statements letting `existing` accept records of
+    /// `target` (usually [`SqlSchema::infer`] over a batch). Missing columns
+    /// are always added, as nullable; widening type changes and dropped
+    /// `NOT NULL` constraints only when `widen` is set. SQLite cannot alter
+    /// a column, so its alterations are always skipped.
+    pub fn generate_alter_table(
+        &self,
+        existing: &SqlSchema,
+        target: &SqlSchema,
+        widen: bool,
+    ) -> SqlMigration {
+        let mut migration = SqlMigration {
+            statements: Vec::new(),
+            skipped: Vec::new(),
+        };
+        for change in existing.diff(target) {
+            match change {
+                SchemaChange::Add(column) => {
+                    let column = SqlColumn {
+                        nullable: true,
+                        ..column
+                    };
+                    migration.statements.push(format!(
+                        "ALTER TABLE {} ADD COLUMN {} {};",
+                        self.quote_identifier(&self.table_name),
+                        self.quote_identifier(&column.name),
+                        self.dialect.column_definition(&column)
+                    ));
+                }
+                SchemaChange::Alter { from, to } if widen && self.dialect != SqlDialect::Sqlite => {
+                    migration.statements.push(self.alter_column(&from, &to));
+                }
+                other => migration.skipped.push(other),
+            }
+        }
+        migration
+    }
+    fn alter_column(&self, from: &SqlColumn, to: &SqlColumn) -> String {
+        let table = self.quote_identifier(&self.table_name);
+        let col = self.quote_identifier(&to.name);
+        match self.dialect {
+            SqlDialect::Postgres => {
+                let mut clauses = Vec::new();
+                if from.kind != to.kind {
+                    let ty = self.dialect.kind_type(to.kind);
+                    clauses.push(format!(
+                        "ALTER COLUMN {} TYPE {} USING {}::{}",
+                        col, ty, col, ty
+                    ));
+                }
+                if to.nullable && !from.nullable {
+                    clauses.push(format!("ALTER COLUMN {} DROP NOT NULL", col));
+                }
+                format!("ALTER TABLE {} {};", table, clauses.join(", "))
+            }
+            // MODIFY restates the whole column, nullability included
+            SqlDialect::MySql => format!(
+                "ALTER TABLE {} MODIFY COLUMN {} {}{};",
+                table,
+                col,
+                self.dialect.column_definition(to),
+                if to.nullable { " NULL" } else { " NOT NULL" }
+            ),
+            SqlDialect::ClickHouse | SqlDialect::Sqlite => format!(
+                "ALTER TABLE {} MODIFY COLUMN {} {};",
+                table,
+                col,
+                self.dialect.column_definition(to)
+            ),
+        }
+    }
+    /// Insert that updates existing rows. PostgreSQL uses
+    /// `ON CONFLICT (..) DO UPDATE`, which needs a conflict target: without
+    /// `conflict_columns` a plain insert is returned. MySQL uses
+    /// `ON DUPLICATE KEY UPDATE` with a row alias (`AS new .. col = new.col`),
+    /// which needs MySQL 8.0.19 or later and is not understood by MariaDB;
+    /// it is keyed by the table's unique indexes, so `conflict_columns` only
+    /// selects which columns are left untouched. SQLite uses
+    /// `INSERT OR REPLACE`. ClickHouse has no upsert; a plain insert is
+    /// returned and deduplication is left to a `ReplacingMergeTree` table.
     pub fn format_upsert(&self, record: &DataRecord, conflict_columns: &[&str]) -> String {
         let insert = self.format_record(record);
+        match self.dialect {
+            SqlDialect::Sqlite => {
+                return insert.replacen("INSERT INTO", "INSERT OR REPLACE INTO", 1);
+            }
+            SqlDialect::ClickHouse => return insert,
+            SqlDialect::Postgres if conflict_columns.is_empty() => return insert,
+            SqlDialect::Postgres | SqlDialect::MySql => {}
+        }
         let mut update_parts = Vec::new();
         for field in record
             .items
@@ -196,11 +498,21 @@ impl SqlInsert {
             let name = field.get_name();
             if !conflict_columns.contains(&name) {
                 let col = self.quote_identifier(name);
-                update_parts.push(format!("{} = EXCLUDED.{}", &col, &col));
+                if self.dialect == SqlDialect::MySql {
+                    update_parts.push(format!("{} = new.{}", &col, &col));
+                } else {
+                    update_parts.push(format!("{} = EXCLUDED.{}", &col, &col));
+                }
             }
         }
         if update_parts.is_empty() {
             insert
+        } else if self.dialect == SqlDialect::MySql {
+            format!(
+                "{} AS new ON DUPLICATE KEY UPDATE {};",
+                insert.trim_end_matches(';'),
+                update_parts.join(", ")
+            )
         } else {
             let quoted_conflicts: Vec<String> = conflict_columns
                 .iter()
@@ -216,6 +528,13 @@ impl SqlInsert {
     }
 }
 
+fn find_column<'a>(record: &'a DataRecord, name: &str) -> Option<&'a DataField> {
+    record
+        .items
+        .iter()
+        .find(|f| f.get_name() == name && *f.get_meta() != DataType::Ignore)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -227,8 +546,11 @@ mod tests {
             table_name: "t".into(),
             quote_identifiers: true,
             obj_formatter: crate::SqlFormat::from(&TextFmt::Json),
+            dialect: SqlDialect::Postgres,
+            time_format: TimeFormat::default(),
         };
         let r = DataRecord {
+            id: 0,
             items: vec![
                 DataField::from_chars("name", "Alice"),
                 DataField::from_digit("age", 30),
@@ -323,6 +645,26 @@ mod tests {
         assert!(result.contains("2024"));
     }
 
+    #[test]
+    fn test_time_format() {
+        use crate::TimeStyle;
+        let dt = chrono::NaiveDateTime::parse_from_str("2024-01-15 10:30:45", "%Y-%m-%d %H:%M:%S")
+            .unwrap();
+        let mut obj = ObjectValue::new();
+        obj.insert("at", DataField::from_time("at", dt));
+        let record = DataRecord {
+            id: 0,
+            items: vec![DataField::from_time("t", dt), DataField::from_obj("o", obj)],
+        };
+        let sql = SqlInsert::new_with_json("e").with_time_format(TimeStyle::Rfc3339);
+        assert_eq!(
+            sql.format_record(&record),
+            r#"INSERT INTO "e" ("t", "o") VALUES ('2024-01-15T10:30:45Z', '{"at":"2024-01-15T10:30:45Z"}');"#
+        );
+        let sql = SqlInsert::new_with_json("e").with_time_format(TimeStyle::EpochSecs);
+        assert_eq!(sql.format_datetime(&dt), "1705314645");
+    }
+
     #[test]
     fn test_quote_identifier() {
         let sql = SqlInsert::new_with_json("t");
@@ -343,6 +685,8 @@ mod tests {
             table_name: "t".into(),
             quote_identifiers: false,
             obj_formatter: crate::SqlFormat::from(&TextFmt::Json),
+            dialect: SqlDialect::Postgres,
+            time_format: TimeFormat::default(),
         };
         assert_eq!(sql.quote_identifier("name"), "name");
     }
@@ -351,6 +695,7 @@ mod tests {
     fn test_format_record() {
         let sql = SqlInsert::new_with_json("users");
         let record = DataRecord {
+            id: 0,
             items: vec![
                 DataField::from_chars("name", "Alice"),
                 DataField::from_digit("age", 30),
@@ -376,12 +721,14 @@ mod tests {
         let sql = SqlInsert::new_with_json("users");
         let records = vec![
             DataRecord {
+                id: 0,
                 items: vec![
                     DataField::from_chars("name", "Alice"),
                     DataField::from_digit("age", 30),
                 ],
             },
             DataRecord {
+                id: 0,
                 items: vec![
                     DataField::from_chars("name", "Bob"),
                     DataField::from_digit("age", 25),
@@ -395,6 +742,106 @@ mod tests {
         assert!(result.ends_with(';'));
     }
 
+    #[test]
+    fn test_format_batch_union_columns() {
+        let sql = SqlInsert::new_with_json("t");
+        let records = vec![
+            DataRecord {
+                id: 0,
+                items: vec![DataField::from_digit("a", 1)],
+            },
+            DataRecord {
+                id: 0,
+                items: vec![
+                    DataField::from_chars("b", "x"),
+                    DataField::from_digit("a", 2),
+                ],
+            },
+        ];
+        assert_eq!(
+            sql.format_batch(&records),
+            "INSERT INTO \"t\" (\"a\", \"b\") VALUES\n  (1, NULL),\n  (2, 'x');"
+        );
+    }
+
+    #[test]
+    fn test_generate_create_table_widening() {
+        let records = vec![
+            DataRecord {
+                id: 0,
+                items: vec![DataField::from_digit("n", 1), DataField::from_digit("v", 1)],
+            },
+            DataRecord {
+                id: 0,
+                items: vec![
+                    DataField::from_float("n", 1.5),
+                    DataField::from_bool("v", true),
+                    DataField::from_digit("late", 3),
+                ],
+            },
+        ];
+        assert_eq!(
+            SqlInsert::new_with_json("t").generate_create_table(&records),
+            "CREATE TABLE IF NOT EXISTS \"t\" (\n  \"n\" DOUBLE PRECISION,\n  \"v\" TEXT,\n  \"late\" BIGINT\n);"
+        );
+        assert_eq!(
+            SqlInsert::new_with_json("t")
+                .with_dialect(SqlDialect::ClickHouse)
+                .generate_create_table(&records),
+            "CREATE TABLE IF NOT EXISTS `t` (\n  `n` Float64,\n  `v` String,\n  `late` Nullable(Int64)\n) ENGINE = MergeTree ORDER BY tuple();"
+        );
+    }
+
+    #[test]
+    fn test_generate_alter_table() {
+        use crate::ColumnKind;
+        let table = SqlSchema::new(vec![
+            SqlColumn::new("n", ColumnKind::Int, false),
+            SqlColumn::new("gone", ColumnKind::Text, true),
+        ]);
+        let target = SqlSchema::new(vec![
+            SqlColumn::new("n", ColumnKind::Float, true),
+            SqlColumn::new("m", ColumnKind::Int, false),
+        ]);
+        let sql = SqlInsert::new_with_json("t");
+        let m = sql.generate_alter_table(&table, &target, true);
+        assert_eq!(
+            m.statements,
+            vec![
+                "ALTER TABLE \"t\" ALTER COLUMN \"n\" TYPE DOUBLE PRECISION USING \"n\"::DOUBLE PRECISION, ALTER COLUMN \"n\" DROP NOT NULL;",
+                "ALTER TABLE \"t\" ADD COLUMN \"m\" BIGINT;",
+            ]
+        );
+        assert_eq!(
+            m.skipped,
+            vec![SchemaChange::Drop(SqlColumn::new(
+                "gone",
+                ColumnKind::Text,
+                true
+            ))]
+        );
+
+        let m = sql.generate_alter_table(&table, &target, false);
+        assert_eq!(m.statements.len(), 1);
+        assert!(matches!(m.skipped[0], SchemaChange::Alter { .. }));
+
+        let m = SqlInsert::new_with_json("t")
+            .with_dialect(SqlDialect::MySql)
+            .generate_alter_table(&table, &target, true);
+        assert_eq!(
+            m.statements[0],
+            "ALTER TABLE `t` MODIFY COLUMN `n` DOUBLE NULL;"
+        );
+        let m = SqlInsert::new_with_json("t")
+            .with_dialect(SqlDialect::Sqlite)
+            .generate_alter_table(&table, &target, true);
+        assert_eq!(
+            m.statements,
+            vec!["ALTER TABLE \"t\" ADD COLUMN \"m\" INTEGER;"]
+        );
+        assert_eq!(m.skipped.len(), 2);
+    }
+
     #[test]
     fn test_generate_create_table_empty() {
         let sql = SqlInsert::new_with_json("users");
@@ -406,6 +853,7 @@ mod tests {
     fn test_generate_create_table() {
         let sql = SqlInsert::new_with_json("users");
         let records = vec![DataRecord {
+            id: 0,
             items: vec![
                 DataField::from_chars("name", "Alice"),
                 DataField::from_digit("age", 30),
@@ -425,6 +873,7 @@ mod tests {
     fn test_format_upsert() {
         let sql = SqlInsert::new_with_json("users");
         let record = DataRecord {
+            id: 0,
             items: vec![
                 DataField::from_chars("id", "u1"),
                 DataField::from_chars("name", "Alice"),
@@ -439,10 +888,138 @@ mod tests {
         assert!(result.contains("\"age\" = EXCLUDED.\"age\""));
     }
 
+    #[test]
+    fn test_dialect_record() {
+        let record = DataRecord {
+            id: 0,
+            items: vec![
+                DataField::from_chars("msg", "it's \\n"),
+                DataField::from_bool("ok", true),
+            ],
+        };
+        let mysql = SqlInsert::new_with_json("t").with_dialect(SqlDialect::MySql);
+        assert_eq!(
+            mysql.format_record(&record),
+            "INSERT INTO `t` (`msg`, `ok`) VALUES ('it''s \\\\n', 1);"
+        );
+        let ch = SqlInsert::new_with_json("t").with_dialect(SqlDialect::ClickHouse);
+        assert_eq!(
+            ch.format_record(&record),
+            "INSERT INTO `t` (`msg`, `ok`) VALUES ('it\\'s \\\\n', true);"
+        );
+    }
+
+    #[test]
+    fn test_dialect_upsert() {
+        let record = DataRecord {
+            id: 0,
+            items: vec![
+                DataField::from_chars("id", "u1"),
+                DataField::from_digit("age", 30),
+            ],
+        };
+        let mysql = SqlInsert::new_with_json("users").with_dialect(SqlDialect::MySql);
+        assert_eq!(
+            mysql.format_upsert(&record, &["id"]),
+            "INSERT INTO `users` (`id`, `age`) VALUES ('u1', 30) AS new ON DUPLICATE KEY UPDATE `age` = new.`age`;"
+        );
+        let sqlite = SqlInsert::new_with_json("users").with_dialect(SqlDialect::Sqlite);
+        assert_eq!(
+            sqlite.format_upsert(&record, &["id"]),
+            "INSERT OR REPLACE INTO \"users\" (\"id\", \"age\") VALUES ('u1', 30);"
+        );
+        let ch = SqlInsert::new_with_json("users").with_dialect(SqlDialect::ClickHouse);
+        assert_eq!(
+            ch.format_upsert(&record, &["id"]),
+            ch.format_record(&record)
+        );
+        // ON CONFLICT DO UPDATE needs a target
+        let pg = SqlInsert::new_with_json("users").with_dialect(SqlDialect::Postgres);
+        assert_eq!(pg.format_upsert(&record, &[]), pg.format_record(&record));
+    }
+
+    #[test]
+    fn test_format_record_params() {
+        let mut obj = ObjectValue::new();
+        obj.insert("k", DataField::from_digit("k", 1));
+        let record = DataRecord {
+            id: 0,
+            items: vec![
+                DataField::from_chars("name", "O'Reilly"),
+                DataField::from_digit("age", 30),
+                DataField::from_obj("meta", obj),
+                DataField::from_ignore("skip"),
+            ],
+        };
+        let stmt = SqlInsert::new_with_json("users").format_record_params(&record);
+        assert_eq!(
+            stmt.sql,
+            "INSERT INTO \"users\" (\"name\", \"age\", \"meta\") VALUES ($1, $2, $3)"
+        );
+        assert_eq!(
+            stmt.params,
+            vec![
+                SqlParam::Text("O'Reilly".into()),
+                SqlParam::Int(30),
+                SqlParam::Json("{\"k\":1}".into()),
+            ]
+        );
+        let mysql = SqlInsert::new_with_json("users").with_dialect(SqlDialect::MySql);
+        assert!(
+            mysql
+                .format_record_params(&record)
+                .sql
+                .ends_with("VALUES (?, ?, ?)")
+        );
+    }
+
+    #[test]
+    fn test_format_batch_params() {
+        let records = vec![
+            DataRecord {
+                id: 0,
+                items: vec![
+                    DataField::from_chars("name", "Alice"),
+                    DataField::from_digit("age", 30),
+                ],
+            },
+            DataRecord {
+                id: 0,
+                items: vec![
+                    DataField::from_digit("age", 25),
+                    DataField::from_chars("extra", "x"),
+                ],
+            },
+        ];
+        let batch = SqlInsert::new_with_json("users").format_batch_params(&records);
+        assert_eq!(
+            batch.sql,
+            "INSERT INTO \"users\" (\"name\", \"age\", \"extra\") VALUES ($1, $2, $3)"
+        );
+        assert_eq!(
+            batch.rows,
+            vec![
+                vec![
+                    SqlParam::Text("Alice".into()),
+                    SqlParam::Int(30),
+                    SqlParam::Null
+                ],
+                vec![
+                    SqlParam::Null,
+                    SqlParam::Int(25),
+                    SqlParam::Text("x".into())
+                ],
+            ]
+        );
+        let empty = SqlInsert::new_with_json("users").format_batch_params(&[]);
+        assert!(empty.sql.is_empty() && empty.rows.is_empty());
+    }
+
     #[test]
     fn test_format_upsert_no_update_columns() {
         let sql = SqlInsert::new_with_json("users");
         let record = DataRecord {
+            id: 0,
             items: vec![DataField::from_chars("id", "u1")],
         };
         // When all columns are conflict columns, no update is needed
@@ -0,0 +1,348 @@
+use crate::SqlDialect;
+use crate::formatter::{DataFormat, StreamDataFormat, render};
+use crate::json::Json;
+use std::fmt::{self, Write};
+use wp_model_core::model::{DataField, DataRecord, DataType, Value, types::value::ObjectValue};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CopyFormat {
+    /// PostgreSQL `COPY` text format: tab separated, `\N` for NULL.
+    #[default]
+    PgText,
+    /// PostgreSQL `COPY ... WITH (FORMAT csv)`: unquoted empty for NULL.
+    PgCsv,
+    /// Tab separated rows for MySQL `LOAD DATA` with its default
+    /// `FIELDS TERMINATED BY '\t' ESCAPED BY '\\'` settings.
+    MySqlTsv,
+}
+
+/// Bulk-load payloads for PostgreSQL `COPY FROM STDIN` and MySQL
+/// `LOAD DATA`, one row per record.
+///
+/// Columns are locked when the formatter is built: every row has the same
+/// columns in the same order, a missing field is written as NULL and a
+/// field outside the schema is dropped. Objects and arrays are written as
+/// JSON text.
+#[derive(Debug, Clone)]
+pub struct SqlCopy {
+    table_name: String,
+    columns: Vec<String>,
+    format: CopyFormat,
+}
+
+impl SqlCopy {
+    pub fn new<T, I, S>(table: T, columns: I) -> Self
+    where
+        T: Into<String>,
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            table_name: table.into(),
+            columns: columns.into_iter().map(Into::into).collect(),
+            format: CopyFormat::default(),
+        }
+    }
+    /// Locks the schema to the non-ignored fields of `record`, in order.
+    pub fn from_record(table: impl Into<String>, record: &DataRecord) -> Self {
+        Self::new(
+            table,
+            record
+                .items
+                .iter()
+                .filter(|f| *f.get_meta() != DataType::Ignore)
+                .map(|f| f.get_name().to_string()),
+        )
+    }
+    pub fn with_format(mut self, format: CopyFormat) -> Self {
+        self.format = format;
+        self
+    }
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// `COPY "t" ("a", "b") FROM STDIN` matching the payload format.
+    pub fn copy_statement(&self) -> String {
+        let options = match self.format {
+            CopyFormat::PgCsv => " WITH (FORMAT csv)",
+            _ => "",
+        };
+        format!(
+            "COPY {} ({}) FROM STDIN{};",
+            self.identifier(SqlDialect::Postgres, &self.table_name),
+            self.column_list(SqlDialect::Postgres),
+            options
+        )
+    }
+
+    /// `LOAD DATA LOCAL INFILE` statement for a [`CopyFormat::MySqlTsv`]
+    /// payload stored at `infile`.
+    pub fn load_data_statement(&self, infile: &str) -> String {
+        let file = render(|out| SqlDialect::MySql.write_string(infile, out));
+        format!(
+            "LOAD DATA LOCAL INFILE {} INTO TABLE {} CHARACTER SET utf8mb4 \
+             FIELDS TERMINATED BY '\\t' ESCAPED BY '\\\\' LINES TERMINATED BY '\\n' ({});",
+            file,
+            self.identifier(SqlDialect::MySql, &self.table_name),
+            self.column_list(SqlDialect::MySql)
+        )
+    }
+
+    /// Writes one `\n`-terminated row per record.
+    pub fn format_batch_into(&self, records: &[DataRecord], out: &mut dyn Write) -> fmt::Result {
+        for record in records {
+            self.format_record_into(record, out)?;
+            out.write_char('\n')?;
+        }
+        Ok(())
+    }
+    pub fn format_batch(&self, records: &[DataRecord]) -> String {
+        render(|out| self.format_batch_into(records, out))
+    }
+
+    fn identifier(&self, dialect: SqlDialect, name: &str) -> String {
+        render(|out| dialect.write_identifier(name, out))
+    }
+
+    fn column_list(&self, dialect: SqlDialect) -> String {
+        self.columns
+            .iter()
+            .map(|c| self.identifier(dialect, c))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn delimiter(&self) -> char {
+        match self.format {
+            CopyFormat::PgCsv => ',',
+            CopyFormat::PgText | CopyFormat::MySqlTsv => '\t',
+        }
+    }
+
+    fn write_cell(&self, value: &Value, out: &mut dyn Write) -> fmt::Result {
+        match value {
+            Value::Null | Value::Ignore(_) => self.format_null_into(out),
+            v => self.fmt_value_into(v, out),
+        }
+    }
+}
+
+/// Scalar methods write a single escaped cell.
+impl StreamDataFormat for SqlCopy {
+    fn format_null_into(&self, out: &mut dyn Write) -> fmt::Result {
+        match self.format {
+            CopyFormat::PgCsv => Ok(()),
+            CopyFormat::PgText | CopyFormat::MySqlTsv => out.write_str("\\N"),
+        }
+    }
+    fn format_bool_into(&self, v: &bool, out: &mut dyn Write) -> fmt::Result {
+        out.write_str(match (self.format, v) {
+            (CopyFormat::MySqlTsv, true) => "1",
+            (CopyFormat::MySqlTsv, false) => "0",
+            (_, true) => "t",
+            (_, false) => "f",
+        })
+    }
+    fn format_string_into(&self, v: &str, out: &mut dyn Write) -> fmt::Result {
+        match self.format {
+            CopyFormat::PgCsv => {
+                // an unquoted empty cell means NULL, and `\.` alone ends the data
+                let quote = v.is_empty() || v == "\\." || v.contains([',', '"', '\n', '\r']);
+                if !quote {
+                    return out.write_str(v);
+                }
+                out.write_char('"')?;
+                for c in v.chars() {
+                    if c == '"' {
+                        out.write_char('"')?;
+                    }
+                    out.write_char(c)?;
+                }
+                out.write_char('"')
+            }
+            CopyFormat::PgText | CopyFormat::MySqlTsv => {
+                for c in v.chars() {
+                    match c {
+                        '\\' => out.write_str("\\\\")?,
+                        '\t' => out.write_str("\\t")?,
+                        '\n' => out.write_str("\\n")?,
+                        '\r' => out.write_str("\\r")?,
+                        '\0' if self.format == CopyFormat::MySqlTsv => out.write_str("\\0")?,
+                        c => out.write_char(c)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+    fn format_i64_into(&self, v: &i64, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_f64_into(&self, v: &f64, out: &mut dyn Write) -> fmt::Result {
+        if v.is_finite() {
+            return write!(out, "{}", v);
+        }
+        if self.format == CopyFormat::MySqlTsv {
+            return self.format_null_into(out);
+        }
+        out.write_str(if v.is_nan() {
+            "NaN"
+        } else if v.is_sign_positive() {
+            "Infinity"
+        } else {
+            "-Infinity"
+        })
+    }
+    fn format_ip_into(&self, v: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_datetime_into(&self, v: &chrono::NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_object_into(&self, value: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        self.format_string_into(&render(|w| Json::new().format_object_into(value, w)), out)
+    }
+    fn format_array_into(&self, value: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        self.format_string_into(&render(|w| Json::new().format_array_into(value, w)), out)
+    }
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        self.write_cell(field.get_value(), out)
+    }
+    /// Writes the row without its line terminator.
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        for (i, column) in self.columns.iter().enumerate() {
+            if i > 0 {
+                out.write_char(self.delimiter())?;
+            }
+            match record
+                .items
+                .iter()
+                .find(|f| f.get_name() == column.as_str() && *f.get_meta() != DataType::Ignore)
+            {
+                Some(field) => self.write_cell(field.get_value(), out)?,
+                None => self.format_null_into(out)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DataFormat for SqlCopy {
+    type Output = String;
+    fn format_null(&self) -> String {
+        render(|out| self.format_null_into(out))
+    }
+    fn format_bool(&self, v: &bool) -> String {
+        render(|out| self.format_bool_into(v, out))
+    }
+    fn format_string(&self, v: &str) -> String {
+        render(|out| self.format_string_into(v, out))
+    }
+    fn format_i64(&self, v: &i64) -> String {
+        v.to_string()
+    }
+    fn format_f64(&self, v: &f64) -> String {
+        render(|out| self.format_f64_into(v, out))
+    }
+    fn format_ip(&self, v: &std::net::IpAddr) -> String {
+        v.to_string()
+    }
+    fn format_datetime(&self, v: &chrono::NaiveDateTime) -> String {
+        v.to_string()
+    }
+    fn format_object(&self, value: &ObjectValue) -> String {
+        render(|out| self.format_object_into(value, out))
+    }
+    fn format_array(&self, value: &[DataField]) -> String {
+        render(|out| self.format_array_into(value, out))
+    }
+    fn format_field(&self, field: &DataField) -> String {
+        render(|out| self.format_field_into(field, out))
+    }
+    fn format_record(&self, record: &DataRecord) -> String {
+        render(|out| self.format_record_into(record, out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(items: Vec<DataField>) -> DataRecord {
+        DataRecord { id: 0, items }
+    }
+
+    fn copy(format: CopyFormat) -> SqlCopy {
+        SqlCopy::new("t", ["a", "b", "c"]).with_format(format)
+    }
+
+    #[test]
+    fn test_pg_text_escaping() {
+        let f = copy(CopyFormat::PgText);
+        assert_eq!(f.format_string("a\tb\\c\nd"), "a\\tb\\\\c\\nd");
+        assert_eq!(f.format_null(), "\\N");
+        assert_eq!(f.format_bool(&true), "t");
+        assert_eq!(f.format_f64(&f64::NEG_INFINITY), "-Infinity");
+    }
+
+    #[test]
+    fn test_pg_csv_quoting() {
+        let f = copy(CopyFormat::PgCsv);
+        assert_eq!(f.format_string("plain"), "plain");
+        assert_eq!(f.format_string(""), "\"\"");
+        assert_eq!(f.format_string("a,\"b\""), "\"a,\"\"b\"\"\"");
+        assert_eq!(f.format_string("\\."), "\"\\.\"");
+        assert_eq!(f.format_null(), "");
+    }
+
+    #[test]
+    fn test_mysql_tsv() {
+        let f = copy(CopyFormat::MySqlTsv);
+        assert_eq!(f.format_string("x\0y\t"), "x\\0y\\t");
+        assert_eq!(f.format_bool(&false), "0");
+        assert_eq!(f.format_f64(&f64::NAN), "\\N");
+    }
+
+    #[test]
+    fn test_locked_columns() {
+        let r = rec(vec![
+            DataField::from_digit("c", 3),
+            DataField::from_chars("extra", "dropped"),
+            DataField::from_chars("a", "x"),
+        ]);
+        assert_eq!(copy(CopyFormat::PgText).format_record(&r), "x\t\\N\t3");
+        assert_eq!(copy(CopyFormat::PgCsv).format_record(&r), "x,,3");
+        assert_eq!(
+            copy(CopyFormat::PgText).format_batch(&[r.clone(), r]),
+            "x\t\\N\t3\nx\t\\N\t3\n"
+        );
+    }
+
+    #[test]
+    fn test_statements() {
+        assert_eq!(
+            copy(CopyFormat::PgText).copy_statement(),
+            "COPY \"t\" (\"a\", \"b\", \"c\") FROM STDIN;"
+        );
+        assert_eq!(
+            copy(CopyFormat::PgCsv).copy_statement(),
+            "COPY \"t\" (\"a\", \"b\", \"c\") FROM STDIN WITH (FORMAT csv);"
+        );
+        assert_eq!(
+            copy(CopyFormat::MySqlTsv).load_data_statement("/tmp/t.tsv"),
+            "LOAD DATA LOCAL INFILE '/tmp/t.tsv' INTO TABLE `t` CHARACTER SET utf8mb4 \
+             FIELDS TERMINATED BY '\\t' ESCAPED BY '\\\\' LINES TERMINATED BY '\\n' (`a`, `b`, `c`);"
+        );
+    }
+
+    #[test]
+    fn test_from_record() {
+        let r = rec(vec![
+            DataField::from_digit("x", 1),
+            DataField::from_ignore("skip"),
+            DataField::from_digit("y", 2),
+        ]);
+        assert_eq!(SqlCopy::from_record("t", &r).columns(), ["x", "y"]);
+    }
+}
@@ -0,0 +1,238 @@
+use crate::sql_schema::{ColumnKind, SqlColumn};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write};
+use wp_model_core::model::Value;
+
+/// Target database for [`crate::SqlInsert`] output.
+///
+/// The dialect decides identifier quoting, string escaping, boolean and
+/// non-finite float literals, upsert syntax and the column type mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SqlDialect {
+    #[default]
+    Postgres,
+    MySql,
+    Sqlite,
+    ClickHouse,
+}
+
+impl SqlDialect {
+    pub fn write_identifier(&self, name: &str, out: &mut dyn Write) -> fmt::Result {
+        match self {
+            SqlDialect::Postgres | SqlDialect::Sqlite => {
+                out.write_char('"')?;
+                write_doubled(name, '"', out)?;
+                out.write_char('"')
+            }
+            SqlDialect::MySql => {
+                out.write_char('`')?;
+                write_doubled(name, '`', out)?;
+                out.write_char('`')
+            }
+            SqlDialect::ClickHouse => {
+                out.write_char('`')?;
+                write_backslashed(name, '`', out)?;
+                out.write_char('`')
+            }
+        }
+    }
+
+    /// Writes a single-quoted string literal.
+    pub fn write_string(&self, value: &str, out: &mut dyn Write) -> fmt::Result {
+        out.write_char('\'')?;
+        match self {
+            SqlDialect::Postgres | SqlDialect::Sqlite => write_doubled(value, '\'', out)?,
+            // MySQL treats `\` as an escape unless NO_BACKSLASH_ESCAPES is set
+            SqlDialect::MySql => {
+                for c in value.chars() {
+                    match c {
+                        '\\' => out.write_str("\\\\")?,
+                        '\'' => out.write_str("''")?,
+                        '\0' => out.write_str("\\0")?,
+                        c => out.write_char(c)?,
+                    }
+                }
+            }
+            SqlDialect::ClickHouse => write_backslashed(value, '\'', out)?,
+        }
+        out.write_char('\'')
+    }
+
+    /// Writes the placeholder for the `n`-th (1-based) bound parameter.
+    pub fn write_placeholder(&self, n: usize, out: &mut dyn Write) -> fmt::Result {
+        match self {
+            SqlDialect::Postgres => write!(out, "${}", n),
+            _ => out.write_char('?'),
+        }
+    }
+
+    pub fn bool_literal(&self, value: bool) -> &'static str {
+        match (self, value) {
+            (SqlDialect::Postgres, true) => "TRUE",
+            (SqlDialect::Postgres, false) => "FALSE",
+            (SqlDialect::MySql | SqlDialect::Sqlite, true) => "1",
+            (SqlDialect::MySql | SqlDialect::Sqlite, false) => "0",
+            (SqlDialect::ClickHouse, true) => "true",
+            (SqlDialect::ClickHouse, false) => "false",
+        }
+    }
+
+    /// NaN is written as NULL everywhere; infinities as the dialect's
+    /// literal, or NULL where none exists (MySQL).
+    pub fn write_f64(&self, value: f64, out: &mut dyn Write) -> fmt::Result {
+        if value.is_nan() {
+            return out.write_str("NULL");
+        }
+        if value.is_finite() {
+            return write!(out, "{}", value);
+        }
+        let positive = value.is_sign_positive();
+        out.write_str(match (self, positive) {
+            (SqlDialect::Postgres, true) => "'Infinity'",
+            (SqlDialect::Postgres, false) => "'-Infinity'",
+            (SqlDialect::MySql, _) => "NULL",
+            (SqlDialect::Sqlite, true) => "9e999",
+            (SqlDialect::Sqlite, false) => "-9e999",
+            (SqlDialect::ClickHouse, true) => "inf",
+            (SqlDialect::ClickHouse, false) => "-inf",
+        })
+    }
+
+    /// Column type used by `CREATE TABLE` for a value.
+    pub fn column_type(&self, value: &Value) -> &'static str {
+        self.kind_type(ColumnKind::of(value).unwrap_or(ColumnKind::Text))
+    }
+
+    pub fn kind_type(&self, kind: ColumnKind) -> &'static str {
+        use ColumnKind as K;
+        match (self, kind) {
+            (SqlDialect::Postgres, K::Bool) => "BOOLEAN",
+            (SqlDialect::Postgres, K::Int) => "BIGINT",
+            (SqlDialect::Postgres, K::Float) => "DOUBLE PRECISION",
+            (SqlDialect::Postgres, K::Time) => "TIMESTAMP",
+            (SqlDialect::Postgres, K::Ip) => "INET",
+            (SqlDialect::Postgres, K::Json) => "JSONB",
+            (SqlDialect::Postgres, K::Text) => "TEXT",
+
+            (SqlDialect::MySql, K::Bool) => "BOOLEAN",
+            (SqlDialect::MySql, K::Int) => "BIGINT",
+            (SqlDialect::MySql, K::Float) => "DOUBLE",
+            (SqlDialect::MySql, K::Time) => "DATETIME(6)",
+            (SqlDialect::MySql, K::Ip) => "VARCHAR(45)",
+            (SqlDialect::MySql, K::Json) => "JSON",
+            (SqlDialect::MySql, K::Text) => "TEXT",
+
+            (SqlDialect::Sqlite, K::Bool | K::Int) => "INTEGER",
+            (SqlDialect::Sqlite, K::Float) => "REAL",
+            (SqlDialect::Sqlite, _) => "TEXT",
+
+            (SqlDialect::ClickHouse, K::Bool) => "Bool",
+            (SqlDialect::ClickHouse, K::Int) => "Int64",
+            (SqlDialect::ClickHouse, K::Float) => "Float64",
+            (SqlDialect::ClickHouse, K::Time) => "DateTime64(6)",
+            (SqlDialect::ClickHouse, _) => "String",
+        }
+    }
+
+    /// Type clause of a `CREATE TABLE` column. Columns are nullable by
+    /// default except in ClickHouse, where a nullable column has to be
+    /// declared `Nullable(T)` to accept NULL.
+    pub fn column_definition(&self, column: &SqlColumn) -> String {
+        let ty = self.kind_type(column.kind);
+        match self {
+            SqlDialect::ClickHouse if column.nullable => format!("Nullable({})", ty),
+            _ => ty.to_string(),
+        }
+    }
+}
+
+/// Writes `value` with every `quote` doubled, the SQL escaping rule for both
+/// string literals and quoted identifiers.
+fn write_doubled(value: &str, quote: char, out: &mut dyn Write) -> fmt::Result {
+    let mut rest = value;
+    while let Some(pos) = rest.find(quote) {
+        out.write_str(&rest[..=pos])?;
+        out.write_char(quote)?;
+        rest = &rest[pos + 1..];
+    }
+    out.write_str(rest)
+}
+
+/// Backslash-escapes `quote` and `\` itself.
+fn write_backslashed(value: &str, quote: char, out: &mut dyn Write) -> fmt::Result {
+    for c in value.chars() {
+        if c == quote || c == '\\' {
+            out.write_char('\\')?;
+        }
+        out.write_char(c)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatter::render;
+
+    const ALL: [SqlDialect; 4] = [
+        SqlDialect::Postgres,
+        SqlDialect::MySql,
+        SqlDialect::Sqlite,
+        SqlDialect::ClickHouse,
+    ];
+
+    #[test]
+    fn test_identifier_quoting() {
+        let quoted: Vec<String> = ALL
+            .iter()
+            .map(|d| render(|out| d.write_identifier("a\"b`c", out)))
+            .collect();
+        assert_eq!(
+            quoted,
+            vec!["\"a\"\"b`c\"", "`a\"b``c`", "\"a\"\"b`c\"", "`a\"b\\`c`"]
+        );
+    }
+
+    #[test]
+    fn test_string_escaping() {
+        let escaped: Vec<String> = ALL
+            .iter()
+            .map(|d| render(|out| d.write_string("it's a\\b", out)))
+            .collect();
+        assert_eq!(
+            escaped,
+            vec![
+                "'it''s a\\b'",
+                "'it''s a\\\\b'",
+                "'it''s a\\b'",
+                "'it\\'s a\\\\b'"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_literals() {
+        let bools: Vec<_> = ALL.iter().map(|d| d.bool_literal(true)).collect();
+        assert_eq!(bools, vec!["TRUE", "1", "1", "true"]);
+        let inf: Vec<String> = ALL
+            .iter()
+            .map(|d| render(|out| d.write_f64(f64::NEG_INFINITY, out)))
+            .collect();
+        assert_eq!(inf, vec!["'-Infinity'", "NULL", "-9e999", "-inf"]);
+    }
+
+    #[test]
+    fn test_column_types() {
+        let v = Value::Float(1.0);
+        let types: Vec<_> = ALL.iter().map(|d| d.column_type(&v)).collect();
+        assert_eq!(types, vec!["DOUBLE PRECISION", "DOUBLE", "REAL", "Float64"]);
+        let column = SqlColumn {
+            name: "a".into(),
+            kind: ColumnKind::Int,
+            nullable: true,
+        };
+        let defs: Vec<_> = ALL.iter().map(|d| d.column_definition(&column)).collect();
+        assert_eq!(defs, vec!["BIGINT", "BIGINT", "INTEGER", "Nullable(Int64)"]);
+    }
+}
@@ -0,0 +1,253 @@
+use wp_model_core::model::{DataRecord, DataType, Value};
+
+/// Storage class of a column, independent of the target dialect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Bool,
+    Int,
+    Float,
+    Time,
+    Ip,
+    Json,
+    Text,
+}
+
+impl ColumnKind {
+    /// `None` for values that say nothing about the type (`Null`, ignored).
+    pub fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null | Value::Ignore(_) => None,
+            Value::Bool(_) => Some(ColumnKind::Bool),
+            Value::Digit(_) => Some(ColumnKind::Int),
+            Value::Float(_) => Some(ColumnKind::Float),
+            Value::Time(_) => Some(ColumnKind::Time),
+            Value::IpAddr(_) => Some(ColumnKind::Ip),
+            Value::Obj(_) | Value::Array(_) => Some(ColumnKind::Json),
+            _ => Some(ColumnKind::Text),
+        }
+    }
+
+    /// Smallest kind holding both: integers widen to floats, any other mix
+    /// falls back to text.
+    pub fn widen(self, other: ColumnKind) -> ColumnKind {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (ColumnKind::Int, ColumnKind::Float) | (ColumnKind::Float, ColumnKind::Int) => {
+                ColumnKind::Float
+            }
+            _ => ColumnKind::Text,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqlColumn {
+    pub name: String,
+    pub kind: ColumnKind,
+    /// Missing from, or `Null` in, at least one record.
+    pub nullable: bool,
+}
+
+impl SqlColumn {
+    pub fn new(name: impl Into<String>, kind: ColumnKind, nullable: bool) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            nullable,
+        }
+    }
+}
+
+/// One difference between a table and the schema its records need.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaChange {
+    /// Column the table lacks.
+    Add(SqlColumn),
+    /// Widened type and/or dropped `NOT NULL`; `to` accepts every value
+    /// `from` held.
+    Alter { from: SqlColumn, to: SqlColumn },
+    /// Table column the records never use.
+    Drop(SqlColumn),
+}
+
+impl SchemaChange {
+    /// Changes that could lose data; they are reported, never applied.
+    pub fn is_destructive(&self) -> bool {
+        matches!(self, SchemaChange::Drop(_))
+    }
+}
+
+/// Columns of a batch: the union of all non-ignored fields, in the order
+/// they are first seen.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SqlSchema {
+    columns: Vec<SqlColumn>,
+}
+
+impl SqlSchema {
+    /// Schema of an existing table.
+    pub fn new(columns: Vec<SqlColumn>) -> Self {
+        Self { columns }
+    }
+
+    pub fn infer(records: &[DataRecord]) -> Self {
+        // kinds stay `None` until a column shows a typed value
+        let mut columns: Vec<(SqlColumn, Option<ColumnKind>, usize)> = Vec::new();
+        for record in records {
+            for field in record
+                .items
+                .iter()
+                .filter(|f| *f.get_meta() != DataType::Ignore)
+            {
+                let kind = ColumnKind::of(field.get_value());
+                let pos = match columns
+                    .iter()
+                    .position(|(c, _, _)| c.name == field.get_name())
+                {
+                    Some(pos) => pos,
+                    None => {
+                        let column = SqlColumn {
+                            name: field.get_name().to_string(),
+                            kind: ColumnKind::Text,
+                            nullable: false,
+                        };
+                        columns.push((column, None, 0));
+                        columns.len() - 1
+                    }
+                };
+                let (column, seen, count) = &mut columns[pos];
+                *count += 1;
+                match (kind, *seen) {
+                    (None, _) => column.nullable = true,
+                    (Some(k), None) => *seen = Some(k),
+                    (Some(k), Some(s)) => *seen = Some(s.widen(k)),
+                }
+            }
+        }
+        let columns = columns
+            .into_iter()
+            .map(|(mut column, seen, count)| {
+                column.kind = seen.unwrap_or(ColumnKind::Text);
+                column.nullable |= count < records.len();
+                column
+            })
+            .collect();
+        Self { columns }
+    }
+
+    pub fn columns(&self) -> &[SqlColumn] {
+        &self.columns
+    }
+    pub fn column(&self, name: &str) -> Option<&SqlColumn> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// Changes that take this (table) schema to one accepting `target`:
+    /// additions and alterations in `target`'s column order, then the
+    /// destructive ones.
+    pub fn diff(&self, target: &SqlSchema) -> Vec<SchemaChange> {
+        let mut changes = Vec::new();
+        for want in &target.columns {
+            let Some(have) = self.column(&want.name) else {
+                changes.push(SchemaChange::Add(want.clone()));
+                continue;
+            };
+            // a narrower record type already fits the column
+            let kind = have.kind.widen(want.kind);
+            let nullable = have.nullable || want.nullable;
+            if kind != have.kind || nullable != have.nullable {
+                changes.push(SchemaChange::Alter {
+                    from: have.clone(),
+                    to: SqlColumn::new(&have.name, kind, nullable),
+                });
+            }
+        }
+        for have in &self.columns {
+            if target.column(&have.name).is_none() {
+                changes.push(SchemaChange::Drop(have.clone()));
+            }
+        }
+        changes.sort_by_key(SchemaChange::is_destructive);
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wp_model_core::model::DataField;
+
+    fn rec(items: Vec<DataField>) -> DataRecord {
+        DataRecord { id: 0, items }
+    }
+
+    #[test]
+    fn test_widen() {
+        assert_eq!(ColumnKind::Int.widen(ColumnKind::Float), ColumnKind::Float);
+        assert_eq!(ColumnKind::Bool.widen(ColumnKind::Int), ColumnKind::Text);
+        assert_eq!(ColumnKind::Time.widen(ColumnKind::Time), ColumnKind::Time);
+    }
+
+    #[test]
+    fn test_infer_union_and_nullability() {
+        let records = vec![
+            rec(vec![
+                DataField::from_digit("a", 1),
+                DataField::from_chars("b", "x"),
+                DataField::from_ignore("skip"),
+            ]),
+            rec(vec![
+                DataField::from_float("a", 1.5),
+                DataField::from_digit("b", 2),
+                DataField::new(DataType::Auto, "c", Value::Null),
+            ]),
+        ];
+        let schema = SqlSchema::infer(&records);
+        let summary: Vec<_> = schema
+            .columns()
+            .iter()
+            .map(|c| (c.name.as_str(), c.kind, c.nullable))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("a", ColumnKind::Float, false),
+                ("b", ColumnKind::Text, false),
+                ("c", ColumnKind::Text, true),
+            ]
+        );
+        assert!(schema.column("skip").is_none());
+        assert!(SqlSchema::infer(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_diff() {
+        let table = SqlSchema::new(vec![
+            SqlColumn::new("id", ColumnKind::Int, false),
+            SqlColumn::new("score", ColumnKind::Int, false),
+            SqlColumn::new("note", ColumnKind::Text, true),
+            SqlColumn::new("old", ColumnKind::Time, true),
+        ]);
+        let target = SqlSchema::new(vec![
+            SqlColumn::new("id", ColumnKind::Int, false),
+            SqlColumn::new("score", ColumnKind::Float, true),
+            SqlColumn::new("note", ColumnKind::Int, false),
+            SqlColumn::new("tag", ColumnKind::Text, true),
+        ]);
+        assert_eq!(
+            table.diff(&target),
+            vec![
+                SchemaChange::Alter {
+                    from: SqlColumn::new("score", ColumnKind::Int, false),
+                    to: SqlColumn::new("score", ColumnKind::Float, true),
+                },
+                SchemaChange::Add(SqlColumn::new("tag", ColumnKind::Text, true)),
+                SchemaChange::Drop(SqlColumn::new("old", ColumnKind::Time, true)),
+            ]
+        );
+        assert!(table.diff(&table).is_empty());
+    }
+}
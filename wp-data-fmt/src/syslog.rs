@@ -0,0 +1,629 @@
+use crate::formatter::{DataFormat, StreamDataFormat, render};
+use crate::raw::Raw;
+use crate::severity::parse_severity;
+use chrono::{NaiveDateTime, Timelike, Utc};
+use std::fmt::{self, Write};
+use wp_model_core::model::{DataField, DataRecord, DataType, Value, types::value::ObjectValue};
+
+const NIL: &str = "-";
+
+/// How messages are delimited on a stream transport (RFC 6587).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyslogFraming {
+    /// One message per datagram, nothing added (UDP).
+    #[default]
+    None,
+    /// `LEN SP MSG`, where `LEN` is the message length in bytes (TCP).
+    OctetCounting,
+    /// Message followed by `\n`; only safe when messages contain no newlines.
+    NonTransparent,
+}
+
+impl SyslogFraming {
+    /// Framing for a transport named as `wp_conf_base::Protocol` displays
+    /// and parses it, so `for_transport(&protocol.to_string())` works:
+    /// octet counting for `"tcp"`, one message per datagram for `"udp"`.
+    pub fn for_transport(transport: &str) -> Option<Self> {
+        match transport {
+            "tcp" => Some(SyslogFraming::OctetCounting),
+            "udp" => Some(SyslogFraming::None),
+            _ => None,
+        }
+    }
+}
+
+/// Syslog output following RFC 5424:
+/// `<PRI>1 TIMESTAMP HOSTNAME APP-NAME PROCID MSGID [SD-ID k="v" ..] MSG`.
+///
+/// Header parts can be taken from record fields; fields not used by the
+/// header become SD-PARAMs of a single SD-ELEMENT, with nested objects and
+/// arrays flattened into dotted names. Missing header parts are written as
+/// the `-` nil value.
+///
+/// In RFC 3164 mode the output is `<PRI>Mmm dd hh:mm:ss HOST TAG[PID]: MSG`,
+/// and the remaining fields are appended to the message as `k="v"` pairs.
+#[derive(Debug, Clone)]
+pub struct Syslog5424 {
+    rfc3164: bool,
+    framing: SyslogFraming,
+    facility: u8,
+    severity: u8,
+    sd_id: String,
+    hostname: Option<String>,
+    app_name: Option<String>,
+    pri_field: Option<String>,
+    severity_field: Option<String>,
+    timestamp_field: Option<String>,
+    host_field: Option<String>,
+    app_field: Option<String>,
+    procid_field: Option<String>,
+    msgid_field: Option<String>,
+    msg_field: Option<String>,
+    clock: fn() -> NaiveDateTime,
+}
+
+fn utc_now() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+impl Default for Syslog5424 {
+    fn default() -> Self {
+        Self {
+            rfc3164: false,
+            framing: SyslogFraming::None,
+            facility: 1,
+            severity: 6,
+            sd_id: "wp@32473".to_string(),
+            hostname: None,
+            app_name: None,
+            pri_field: None,
+            severity_field: None,
+            timestamp_field: Some("time".to_string()),
+            host_field: None,
+            app_field: None,
+            procid_field: None,
+            msgid_field: None,
+            msg_field: Some("msg".to_string()),
+            clock: utc_now,
+        }
+    }
+}
+
+impl Syslog5424 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Switches to the legacy BSD format of RFC 3164, which has no nil
+    /// timestamp: a record without a time is stamped by
+    /// [`Syslog5424::with_clock`].
+    pub fn with_rfc3164(mut self, legacy: bool) -> Self {
+        self.rfc3164 = legacy;
+        self
+    }
+    /// Time written in RFC 3164 mode when the record has none; the current
+    /// UTC time by default. RFC 5424 writes the `-` nil value instead.
+    pub fn with_clock(mut self, clock: fn() -> NaiveDateTime) -> Self {
+        self.clock = clock;
+        self
+    }
+    pub fn with_framing(mut self, framing: SyslogFraming) -> Self {
+        self.framing = framing;
+        self
+    }
+    /// Facility code (0..=23) used when no PRI field is configured.
+    pub fn with_facility(mut self, facility: u8) -> Self {
+        self.facility = facility.min(23);
+        self
+    }
+    /// Severity code (0..=7) used when no severity field is present.
+    pub fn with_severity(mut self, severity: u8) -> Self {
+        self.severity = severity.min(7);
+        self
+    }
+    pub fn with_sd_id(mut self, sd_id: impl Into<String>) -> Self {
+        self.sd_id = sd_id.into();
+        self
+    }
+    /// Fixed hostname, used when the host field is absent.
+    pub fn with_hostname(mut self, host: impl Into<String>) -> Self {
+        self.hostname = Some(host.into());
+        self
+    }
+    /// Fixed app name, used when the app field is absent.
+    pub fn with_app_name(mut self, app: impl Into<String>) -> Self {
+        self.app_name = Some(app.into());
+        self
+    }
+    /// Field holding a complete numeric PRI (0..=191).
+    pub fn with_pri_field(mut self, name: impl Into<String>) -> Self {
+        self.pri_field = Some(name.into());
+        self
+    }
+    /// Field holding the severity as a code (`3`) or keyword (`err`).
+    pub fn with_severity_field(mut self, name: impl Into<String>) -> Self {
+        self.severity_field = Some(name.into());
+        self
+    }
+    /// Field holding the timestamp; `time` by default.
+    pub fn with_timestamp_field(mut self, name: impl Into<String>) -> Self {
+        self.timestamp_field = Some(name.into());
+        self
+    }
+    pub fn with_host_field(mut self, name: impl Into<String>) -> Self {
+        self.host_field = Some(name.into());
+        self
+    }
+    pub fn with_app_field(mut self, name: impl Into<String>) -> Self {
+        self.app_field = Some(name.into());
+        self
+    }
+    pub fn with_procid_field(mut self, name: impl Into<String>) -> Self {
+        self.procid_field = Some(name.into());
+        self
+    }
+    pub fn with_msgid_field(mut self, name: impl Into<String>) -> Self {
+        self.msgid_field = Some(name.into());
+        self
+    }
+    /// Field holding the free-form message; `msg` by default.
+    pub fn with_msg_field(mut self, name: impl Into<String>) -> Self {
+        self.msg_field = Some(name.into());
+        self
+    }
+
+    fn is_header_field(&self, name: &str) -> bool {
+        [
+            &self.pri_field,
+            &self.severity_field,
+            &self.timestamp_field,
+            &self.host_field,
+            &self.app_field,
+            &self.procid_field,
+            &self.msgid_field,
+            &self.msg_field,
+        ]
+        .iter()
+        .any(|f| f.as_deref() == Some(name))
+    }
+
+    fn pri(&self, record: &DataRecord) -> u8 {
+        if let Some(pri) = lookup(record, &self.pri_field).and_then(|v| match v {
+            Value::Digit(n) => u8::try_from(*n).ok().filter(|n| *n <= 191),
+            Value::Chars(s) => s.trim().parse::<u8>().ok().filter(|n| *n <= 191),
+            _ => None,
+        }) {
+            return pri;
+        }
+        let severity = lookup(record, &self.severity_field)
+            .and_then(parse_severity)
+            .unwrap_or(self.severity);
+        self.facility * 8 + severity
+    }
+
+    fn write_message(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "<{}>", self.pri(record))?;
+        let time = lookup(record, &self.timestamp_field).and_then(|v| match v {
+            Value::Time(t) => Some(*t),
+            _ => None,
+        });
+        let host = header_text(lookup(record, &self.host_field), &self.hostname);
+        let app = header_text(lookup(record, &self.app_field), &self.app_name);
+        let procid = header_text(lookup(record, &self.procid_field), &None);
+        let msg = lookup(record, &self.msg_field)
+            .filter(|v| !matches!(v, Value::Null))
+            .map(|v| render(|w| Raw::new().fmt_value_into(v, w)));
+        if self.rfc3164 {
+            let time = time.unwrap_or_else(self.clock);
+            write!(out, "{} ", time.format("%b %e %H:%M:%S"))?;
+            write_header(out, host.as_deref(), 255)?;
+            out.write_char(' ')?;
+            if let Some(app) = &app {
+                let tag: String = app
+                    .chars()
+                    .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+                    .take(32)
+                    .collect();
+                out.write_str(&tag)?;
+                if let Some(pid) = &procid {
+                    write!(out, "[{}]", pid)?;
+                }
+                out.write_str(": ")?;
+            }
+            let mut first = true;
+            if let Some(msg) = &msg {
+                out.write_str(msg)?;
+                first = false;
+            }
+            return self.write_params(record, &mut first, out);
+        }
+        out.write_str("1 ")?;
+        match time {
+            Some(t) => write_timestamp(&t, out)?,
+            None => out.write_str(NIL)?,
+        }
+        out.write_char(' ')?;
+        write_header(out, host.as_deref(), 255)?;
+        out.write_char(' ')?;
+        write_header(out, app.as_deref(), 48)?;
+        out.write_char(' ')?;
+        write_header(out, procid.as_deref(), 128)?;
+        out.write_char(' ')?;
+        let msgid = header_text(lookup(record, &self.msgid_field), &None);
+        write_header(out, msgid.as_deref(), 32)?;
+        out.write_char(' ')?;
+        let has_params = record
+            .items
+            .iter()
+            .any(|f| self.is_param(f) && has_leaf(f.get_value()));
+        if has_params {
+            out.write_char('[')?;
+            write_sd_name(out, &self.sd_id)?;
+            let mut first = false;
+            self.write_params(record, &mut first, out)?;
+            out.write_char(']')?;
+        } else {
+            out.write_str(NIL)?;
+        }
+        if let Some(msg) = &msg {
+            out.write_char(' ')?;
+            out.write_str(msg)?;
+        }
+        Ok(())
+    }
+
+    fn is_param(&self, field: &DataField) -> bool {
+        *field.get_meta() != DataType::Ignore && !self.is_header_field(field.get_name())
+    }
+
+    fn write_params(
+        &self,
+        record: &DataRecord,
+        first: &mut bool,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        let mut prefix = String::new();
+        for field in record.items.iter().filter(|f| self.is_param(f)) {
+            prefix.clear();
+            prefix.push_str(field.get_name());
+            self.write_param(&mut prefix, field.get_value(), first, out)?;
+        }
+        Ok(())
+    }
+
+    /// Writes ` name="value"` for every leaf under `value`; nulls are left out.
+    fn write_param(
+        &self,
+        prefix: &mut String,
+        value: &Value,
+        first: &mut bool,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        let children: Vec<(String, &Value)> = match value {
+            Value::Null | Value::Ignore(_) => return Ok(()),
+            Value::Obj(obj) => obj
+                .iter()
+                .map(|(k, f)| (k.to_string(), f.get_value()))
+                .collect(),
+            Value::Array(items) => items
+                .iter()
+                .enumerate()
+                .map(|(i, f)| (i.to_string(), f.get_value()))
+                .collect(),
+            _ => {
+                if !*first {
+                    out.write_char(' ')?;
+                }
+                *first = false;
+                write_sd_name(out, prefix)?;
+                out.write_str("=\"")?;
+                self.fmt_value_into(value, out)?;
+                return out.write_char('"');
+            }
+        };
+        for (key, child) in children {
+            let len = prefix.len();
+            prefix.push('.');
+            prefix.push_str(&key);
+            self.write_param(prefix, child, first, out)?;
+            prefix.truncate(len);
+        }
+        Ok(())
+    }
+}
+
+fn lookup<'a>(record: &'a DataRecord, name: &Option<String>) -> Option<&'a Value> {
+    let name = name.as_deref()?;
+    record
+        .items
+        .iter()
+        .find(|f| f.get_name() == name && *f.get_meta() != DataType::Ignore)
+        .map(|f| f.get_value())
+}
+
+fn header_text(value: Option<&Value>, fallback: &Option<String>) -> Option<String> {
+    value
+        .filter(|v| !matches!(v, Value::Null))
+        .map(|v| render(|w| Raw::new().fmt_value_into(v, w)))
+        .filter(|s| !s.is_empty())
+        .or_else(|| fallback.clone())
+}
+
+fn has_leaf(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Ignore(_) => false,
+        Value::Obj(obj) => obj.values().any(|f| has_leaf(f.get_value())),
+        Value::Array(items) => items.iter().any(|f| has_leaf(f.get_value())),
+        _ => true,
+    }
+}
+
+/// RFC 3339 in UTC, with microseconds only when present.
+fn write_timestamp(t: &NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+    if t.nanosecond() / 1000 == 0 {
+        write!(out, "{}Z", t.format("%Y-%m-%dT%H:%M:%S"))
+    } else {
+        write!(out, "{}Z", t.format("%Y-%m-%dT%H:%M:%S%.6f"))
+    }
+}
+
+/// Header fields allow printable US-ASCII only; anything else becomes `_`.
+fn write_header(out: &mut dyn Write, value: Option<&str>, max: usize) -> fmt::Result {
+    let Some(value) = value else {
+        return out.write_str(NIL);
+    };
+    for c in value.chars().take(max) {
+        out.write_char(if c.is_ascii_graphic() { c } else { '_' })?;
+    }
+    Ok(())
+}
+
+/// SD-NAME: up to 32 printable US-ASCII characters except `=`, ` `, `]`, `"`.
+fn write_sd_name(out: &mut dyn Write, name: &str) -> fmt::Result {
+    if name.is_empty() {
+        return out.write_char('_');
+    }
+    for c in name.chars().take(32) {
+        let ok = c.is_ascii_graphic() && !matches!(c, '=' | ']' | '"');
+        out.write_char(if ok { c } else { '_' })?;
+    }
+    Ok(())
+}
+
+/// Scalar methods render SD-PARAM values: `"`, `\` and `]` are escaped.
+impl StreamDataFormat for Syslog5424 {
+    fn format_null_into(&self, _out: &mut dyn Write) -> fmt::Result {
+        Ok(())
+    }
+    fn format_bool_into(&self, v: &bool, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_string_into(&self, v: &str, out: &mut dyn Write) -> fmt::Result {
+        for c in v.chars() {
+            if matches!(c, '"' | '\\' | ']') {
+                out.write_char('\\')?;
+            }
+            out.write_char(c)?;
+        }
+        Ok(())
+    }
+    fn format_i64_into(&self, v: &i64, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_f64_into(&self, v: &f64, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_ip_into(&self, v: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result {
+        write!(out, "{}", v)
+    }
+    fn format_datetime_into(&self, v: &NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+        write_timestamp(v, out)
+    }
+    fn format_object_into(&self, value: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        let mut first = true;
+        for (k, f) in value.iter() {
+            self.write_param(&mut k.to_string(), f.get_value(), &mut first, out)?;
+        }
+        Ok(())
+    }
+    fn format_array_into(&self, value: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        let mut first = true;
+        for (i, f) in value.iter().enumerate() {
+            self.write_param(&mut i.to_string(), f.get_value(), &mut first, out)?;
+        }
+        Ok(())
+    }
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        let mut first = true;
+        self.write_param(
+            &mut field.get_name().to_string(),
+            field.get_value(),
+            &mut first,
+            out,
+        )
+    }
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        match self.framing {
+            SyslogFraming::None => self.write_message(record, out),
+            SyslogFraming::NonTransparent => {
+                self.write_message(record, out)?;
+                out.write_char('\n')
+            }
+            SyslogFraming::OctetCounting => {
+                let msg = render(|w| self.write_message(record, w));
+                write!(out, "{} {}", msg.len(), msg)
+            }
+        }
+    }
+}
+
+impl DataFormat for Syslog5424 {
+    type Output = String;
+    fn format_null(&self) -> String {
+        String::new()
+    }
+    fn format_bool(&self, v: &bool) -> String {
+        v.to_string()
+    }
+    fn format_string(&self, v: &str) -> String {
+        render(|out| self.format_string_into(v, out))
+    }
+    fn format_i64(&self, v: &i64) -> String {
+        v.to_string()
+    }
+    fn format_f64(&self, v: &f64) -> String {
+        v.to_string()
+    }
+    fn format_ip(&self, v: &std::net::IpAddr) -> String {
+        v.to_string()
+    }
+    fn format_datetime(&self, v: &NaiveDateTime) -> String {
+        render(|out| self.format_datetime_into(v, out))
+    }
+    fn format_object(&self, value: &ObjectValue) -> String {
+        render(|out| self.format_object_into(value, out))
+    }
+    fn format_array(&self, value: &[DataField]) -> String {
+        render(|out| self.format_array_into(value, out))
+    }
+    fn format_field(&self, field: &DataField) -> String {
+        render(|out| self.format_field_into(field, out))
+    }
+    fn format_record(&self, record: &DataRecord) -> String {
+        render(|out| self.format_record_into(record, out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn rec(items: Vec<DataField>) -> DataRecord {
+        DataRecord { id: 0, items }
+    }
+
+    #[test]
+    fn test_minimal_message() {
+        let r = rec(vec![DataField::from_chars("msg", "hello")]);
+        assert_eq!(
+            Syslog5424::new().format_record(&r),
+            "<14>1 - - - - - - hello"
+        );
+    }
+
+    #[test]
+    fn test_header_from_fields() {
+        let r = rec(vec![
+            DataField::from_time("time", ts()),
+            DataField::from_chars("host", "web 01"),
+            DataField::from_chars("app", "nginx"),
+            DataField::from_digit("pid", 42),
+            DataField::from_chars("level", "err"),
+            DataField::from_chars("msg", "boom"),
+        ]);
+        let f = Syslog5424::new()
+            .with_facility(16)
+            .with_host_field("host")
+            .with_app_field("app")
+            .with_procid_field("pid")
+            .with_severity_field("level");
+        assert_eq!(
+            f.format_record(&r),
+            "<131>1 2019-08-06T12:12:19Z web_01 nginx 42 - - boom"
+        );
+    }
+
+    #[test]
+    fn test_pri_field_and_fallbacks() {
+        let r = rec(vec![DataField::from_digit("pri", 34)]);
+        let f = Syslog5424::new()
+            .with_pri_field("pri")
+            .with_hostname("h")
+            .with_app_name("a");
+        assert_eq!(f.format_record(&r), "<34>1 - h a - - -");
+        let r = rec(vec![DataField::from_digit("pri", 500)]);
+        assert!(f.format_record(&r).starts_with("<14>"));
+    }
+
+    #[test]
+    fn test_sd_params_escaping_and_flattening() {
+        let mut obj = ObjectValue::new();
+        obj.insert("addr", DataField::from_chars("addr", "10.0.0.9"));
+        obj.insert("none", DataField::new(DataType::Auto, "none", Value::Null));
+        let r = rec(vec![
+            DataField::from_chars("path", "a\"b\\c]d"),
+            DataField::from_chars("a key=x", "v"),
+            DataField::from_obj("up", obj),
+            DataField::from_chars("msg", "m"),
+        ]);
+        assert_eq!(
+            Syslog5424::new().with_sd_id("x@1").format_record(&r),
+            r#"<14>1 - - - - - [x@1 path="a\"b\\c\]d" a_key_x="v" up.addr="10.0.0.9"] m"#
+        );
+    }
+
+    #[test]
+    fn test_framing() {
+        let r = rec(vec![DataField::from_chars("msg", "hi")]);
+        let plain = Syslog5424::new().format_record(&r);
+        let counted = Syslog5424::new()
+            .with_framing(SyslogFraming::OctetCounting)
+            .format_record(&r);
+        assert_eq!(counted, format!("{} {}", plain.len(), plain));
+        let lf = Syslog5424::new()
+            .with_framing(SyslogFraming::NonTransparent)
+            .format_record(&r);
+        assert_eq!(lf, format!("{}\n", plain));
+    }
+
+    #[test]
+    fn test_framing_for_transport() {
+        assert_eq!(
+            SyslogFraming::for_transport("tcp"),
+            Some(SyslogFraming::OctetCounting)
+        );
+        assert_eq!(
+            SyslogFraming::for_transport("udp"),
+            Some(SyslogFraming::None)
+        );
+        assert_eq!(SyslogFraming::for_transport("tls"), None);
+    }
+
+    #[test]
+    fn test_framing_for_every_protocol() {
+        use wp_conf_base::Protocol;
+        for protocol in [Protocol::TCP, Protocol::UDP] {
+            assert!(
+                SyslogFraming::for_transport(&protocol.to_string()).is_some(),
+                "{}",
+                protocol
+            );
+        }
+    }
+
+    #[test]
+    fn test_rfc3164() {
+        let r = rec(vec![
+            DataField::from_time("time", ts()),
+            DataField::from_chars("msg", "started"),
+            DataField::from_digit("port", 80),
+        ]);
+        let f = Syslog5424::new()
+            .with_rfc3164(true)
+            .with_hostname("web01")
+            .with_app_name("nginx");
+        assert_eq!(
+            f.format_record(&r),
+            r#"<14>Aug  6 12:12:19 web01 nginx: started port="80""#
+        );
+        let untimed = rec(vec![DataField::from_chars("msg", "started")]);
+        let f = f.with_clock(ts);
+        assert_eq!(
+            f.format_record(&untimed),
+            "<14>Aug  6 12:12:19 web01 nginx: started"
+        );
+    }
+}
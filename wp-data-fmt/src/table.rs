@@ -0,0 +1,460 @@
+use crate::formatter::{DataFormat, StreamDataFormat, render};
+use crate::raw::Raw;
+use crate::time_fmt::TimeFormat;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write};
+use unicode_segmentation::UnicodeSegmentation;
+use wp_model_core::model::{DataField, DataRecord, DataType, Value, types::value::ObjectValue};
+
+/// Line drawing of [`Table`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TableStyle {
+    /// `+`, `-` and `|`.
+    #[default]
+    Ascii,
+    /// Box drawing characters.
+    Unicode,
+}
+
+struct Border {
+    h: char,
+    v: char,
+    top: [char; 3],
+    mid: [char; 3],
+    bottom: [char; 3],
+    ellipsis: &'static str,
+}
+
+impl TableStyle {
+    fn border(&self) -> Border {
+        match self {
+            TableStyle::Ascii => Border {
+                h: '-',
+                v: '|',
+                top: ['+'; 3],
+                mid: ['+'; 3],
+                bottom: ['+'; 3],
+                ellipsis: "...",
+            },
+            TableStyle::Unicode => Border {
+                h: '─',
+                v: '│',
+                top: ['┌', '┬', '┐'],
+                mid: ['├', '┼', '┤'],
+                bottom: ['└', '┴', '┘'],
+                ellipsis: "…",
+            },
+        }
+    }
+}
+
+/// Human-readable output for previews (`TextFmt::Show`).
+///
+/// A single record is a vertical `name | value` list with nested objects
+/// indented under their field; [`Table::format_batch`] draws one row per
+/// record, with nested objects spread over dotted columns
+/// (`upstream.addr`). Values longer than the maximum width are cut on
+/// grapheme boundaries and end in an ellipsis; widths count graphemes.
+#[derive(Debug, Clone)]
+pub struct Table {
+    style: TableStyle,
+    max_width: usize,
+    indent: usize,
+    raw: Raw,
+}
+
+impl Default for Table {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Table {
+    pub const fn new() -> Self {
+        Self {
+            style: TableStyle::Ascii,
+            max_width: 40,
+            indent: 2,
+            raw: Raw::new(),
+        }
+    }
+    pub fn with_style(mut self, style: TableStyle) -> Self {
+        self.style = style;
+        self
+    }
+    /// Longest value shown, in graphemes; 0 shows values in full.
+    pub fn with_max_width(mut self, max_width: usize) -> Self {
+        self.max_width = max_width;
+        self
+    }
+    /// Spaces per nesting level in the vertical list.
+    pub fn with_indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+    pub fn with_time_format(mut self, time_format: impl Into<TimeFormat>) -> Self {
+        self.raw = Raw::new().with_time_format(time_format);
+        self
+    }
+
+    pub fn format_batch(&self, records: &[DataRecord]) -> String {
+        render(|out| self.format_batch_into(records, out))
+    }
+    /// One row per record over the union of their columns, in the order
+    /// they first appear; a record without a column leaves the cell empty.
+    pub fn format_batch_into(&self, records: &[DataRecord], out: &mut dyn Write) -> fmt::Result {
+        let mut columns: Vec<String> = Vec::new();
+        let mut rows: Vec<Vec<(String, Cell)>> = Vec::with_capacity(records.len());
+        for record in records {
+            let mut row = Vec::new();
+            for field in visible(&record.items) {
+                self.flatten(field.get_name().to_string(), field.get_value(), &mut row);
+            }
+            for (name, _) in &row {
+                if !columns.contains(name) {
+                    columns.push(name.clone());
+                }
+            }
+            rows.push(row);
+        }
+        if columns.is_empty() {
+            return Ok(());
+        }
+        let find = |row: &[(String, Cell)], name: &str| -> Option<Cell> {
+            row.iter().find(|(n, _)| n == name).map(|(_, c)| c.clone())
+        };
+
+        let widths: Vec<usize> = columns
+            .iter()
+            .map(|name| {
+                rows.iter()
+                    .filter_map(|row| find(row, name))
+                    .map(|cell| width(&cell.text))
+                    .chain([width(name)])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let border = self.style.border();
+        let header: Vec<Cell> = columns
+            .iter()
+            .map(|name| Cell {
+                text: name.clone(),
+                numeric: false,
+            })
+            .collect();
+        write_rule(&border, border.top, &widths, out)?;
+        out.write_char('\n')?;
+        write_row(&border, &header, &widths, out)?;
+        out.write_char('\n')?;
+        write_rule(&border, border.mid, &widths, out)?;
+        for row in &rows {
+            let cells: Vec<Cell> = columns
+                .iter()
+                .map(|name| find(row, name).unwrap_or_default())
+                .collect();
+            out.write_char('\n')?;
+            write_row(&border, &cells, &widths, out)?;
+        }
+        out.write_char('\n')?;
+        write_rule(&border, border.bottom, &widths, out)
+    }
+
+    fn flatten(&self, name: String, value: &Value, row: &mut Vec<(String, Cell)>) {
+        match value {
+            Value::Obj(obj) if !obj.is_empty() => {
+                for (key, child) in obj.iter() {
+                    self.flatten(format!("{}.{}", name, key), child.get_value(), row);
+                }
+            }
+            _ => row.push((name, self.cell(value))),
+        }
+    }
+
+    fn cell(&self, value: &Value) -> Cell {
+        Cell {
+            text: self.clip(&one_line(&render(|w| self.raw.fmt_value_into(value, w)))),
+            numeric: matches!(value, Value::Digit(_) | Value::Float(_)),
+        }
+    }
+
+    fn clip(&self, text: &str) -> String {
+        let graphemes: Vec<&str> = text.graphemes(true).collect();
+        if self.max_width == 0 || graphemes.len() <= self.max_width {
+            return text.to_string();
+        }
+        let ellipsis = self.style.border().ellipsis;
+        match self.max_width.checked_sub(width(ellipsis)) {
+            Some(keep) if keep > 0 => graphemes[..keep].concat() + ellipsis,
+            _ => graphemes[..self.max_width].concat(),
+        }
+    }
+
+    /// `(depth, name, value)`; objects and arrays of objects have no value
+    /// of their own, their children follow one level deeper.
+    fn list_lines(&self, name: &str, value: &Value, depth: usize, lines: &mut Vec<ListLine>) {
+        match value {
+            Value::Obj(obj) if !obj.is_empty() => {
+                lines.push((depth, name.to_string(), None));
+                for (key, child) in obj.iter() {
+                    self.list_lines(key, child.get_value(), depth + 1, lines);
+                }
+            }
+            Value::Array(items) if items.iter().any(|f| matches!(f.get_value(), Value::Obj(_))) => {
+                lines.push((depth, name.to_string(), None));
+                for (i, item) in items.iter().enumerate() {
+                    self.list_lines(&format!("[{}]", i), item.get_value(), depth + 1, lines);
+                }
+            }
+            _ => lines.push((depth, name.to_string(), Some(self.cell(value).text))),
+        }
+    }
+
+    fn write_list(&self, fields: &[&DataField], out: &mut dyn Write) -> fmt::Result {
+        let mut lines = Vec::new();
+        for field in fields {
+            self.list_lines(field.get_name(), field.get_value(), 0, &mut lines);
+        }
+        let key_width = lines
+            .iter()
+            .map(|(depth, name, _)| depth * self.indent + width(name))
+            .max()
+            .unwrap_or(0);
+        let v = self.style.border().v;
+        for (i, (depth, name, value)) in lines.iter().enumerate() {
+            if i > 0 {
+                out.write_char('\n')?;
+            }
+            let indent = depth * self.indent;
+            write!(out, "{:indent$}{}", "", name)?;
+            if let Some(value) = value {
+                let fill = key_width - indent - width(name);
+                write!(out, "{:fill$} {}", "", v)?;
+                if !value.is_empty() {
+                    write!(out, " {}", value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+type ListLine = (usize, String, Option<String>);
+
+#[derive(Debug, Clone, Default)]
+struct Cell {
+    text: String,
+    numeric: bool,
+}
+
+fn visible(items: &[DataField]) -> impl Iterator<Item = &DataField> {
+    items.iter().filter(|f| *f.get_meta() != DataType::Ignore)
+}
+
+fn width(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+/// Control characters would break the layout, so they are shown escaped.
+fn one_line(text: &str) -> String {
+    if !text.chars().any(char::is_control) {
+        return text.to_string();
+    }
+    text.chars()
+        .map(|c| {
+            if c.is_control() {
+                c.escape_default().to_string()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+fn write_rule(
+    border: &Border,
+    [l, m, r]: [char; 3],
+    widths: &[usize],
+    out: &mut dyn Write,
+) -> fmt::Result {
+    out.write_char(l)?;
+    for (i, w) in widths.iter().enumerate() {
+        if i > 0 {
+            out.write_char(m)?;
+        }
+        for _ in 0..w + 2 {
+            out.write_char(border.h)?;
+        }
+    }
+    out.write_char(r)
+}
+
+fn write_row(
+    border: &Border,
+    cells: &[Cell],
+    widths: &[usize],
+    out: &mut dyn Write,
+) -> fmt::Result {
+    out.write_char(border.v)?;
+    for (cell, w) in cells.iter().zip(widths) {
+        let fill = w - width(&cell.text);
+        if cell.numeric {
+            write!(out, " {:fill$}{} ", "", cell.text)?;
+        } else {
+            write!(out, " {}{:fill$} ", cell.text, "")?;
+        }
+        out.write_char(border.v)?;
+    }
+    Ok(())
+}
+
+impl StreamDataFormat for Table {
+    fn format_null_into(&self, out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_null_into(out)
+    }
+    fn format_bool_into(&self, v: &bool, out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_bool_into(v, out)
+    }
+    fn format_string_into(&self, v: &str, out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_string_into(v, out)
+    }
+    fn format_i64_into(&self, v: &i64, out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_i64_into(v, out)
+    }
+    fn format_f64_into(&self, v: &f64, out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_f64_into(v, out)
+    }
+    fn format_ip_into(&self, v: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_ip_into(v, out)
+    }
+    fn format_datetime_into(&self, v: &chrono::NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_datetime_into(v, out)
+    }
+    fn format_object_into(&self, v: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_object_into(v, out)
+    }
+    fn format_array_into(&self, v: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_array_into(v, out)
+    }
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        self.write_list(&[field], out)
+    }
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        let fields: Vec<&DataField> = visible(&record.items).collect();
+        self.write_list(&fields, out)
+    }
+}
+
+impl DataFormat for Table {
+    type Output = String;
+    fn format_null(&self) -> String {
+        render(|out| self.format_null_into(out))
+    }
+    fn format_bool(&self, v: &bool) -> String {
+        render(|out| self.format_bool_into(v, out))
+    }
+    fn format_string(&self, v: &str) -> String {
+        render(|out| self.format_string_into(v, out))
+    }
+    fn format_i64(&self, v: &i64) -> String {
+        render(|out| self.format_i64_into(v, out))
+    }
+    fn format_f64(&self, v: &f64) -> String {
+        render(|out| self.format_f64_into(v, out))
+    }
+    fn format_ip(&self, v: &std::net::IpAddr) -> String {
+        render(|out| self.format_ip_into(v, out))
+    }
+    fn format_datetime(&self, v: &chrono::NaiveDateTime) -> String {
+        render(|out| self.format_datetime_into(v, out))
+    }
+    fn format_object(&self, v: &ObjectValue) -> String {
+        render(|out| self.format_object_into(v, out))
+    }
+    fn format_array(&self, v: &[DataField]) -> String {
+        render(|out| self.format_array_into(v, out))
+    }
+    fn format_field(&self, f: &DataField) -> String {
+        render(|out| self.format_field_into(f, out))
+    }
+    fn format_record(&self, r: &DataRecord) -> String {
+        render(|out| self.format_record_into(r, out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_clip_graphemes() {
+        let table = Table::new().with_max_width(5);
+        assert_eq!(table.clip("abcde"), "abcde");
+        assert_eq!(table.clip("abcdef"), "ab...");
+        // a flag and an e with a combining accent are one grapheme each
+        let table = table.with_style(TableStyle::Unicode);
+        assert_eq!(table.clip("🇨🇳e\u{301}xyzw"), "🇨🇳e\u{301}xy…");
+        assert_eq!(Table::new().with_max_width(2).clip("abc"), "ab");
+        assert_eq!(Table::new().with_max_width(0).clip("abcdef"), "abcdef");
+        assert_eq!(one_line("a\nb\tc"), "a\\nb\\tc");
+    }
+
+    #[test]
+    fn test_table_vertical_list() {
+        let mut inner = ObjectValue::new();
+        inner.insert("port", DataField::from_digit("port", 80));
+        let mut outer = ObjectValue::new();
+        outer.insert("host", DataField::from_chars("host", "a"));
+        outer.insert("inner", DataField::from_obj("inner", inner));
+        let record = DataRecord {
+            id: 0,
+            items: vec![
+                DataField::from_chars("name", "x"),
+                DataField::from_obj("outer", outer),
+                DataField::from_arr("tags", vec![DataField::from_chars("", "t")]),
+                DataField::from_chars("empty", ""),
+                DataField::from_ignore("skip"),
+            ],
+        };
+        assert_eq!(
+            Table::new().format_record(&record),
+            "name     | x\nouter\n  host   | a\n  inner\n    port | 80\ntags     | [t]\nempty    |"
+        );
+        assert_eq!(
+            Table::new().format_field(&DataField::from_digit("n", 1)),
+            "n | 1"
+        );
+        assert_eq!(Table::new().format_record(&DataRecord::default()), "");
+    }
+
+    #[test]
+    fn test_table_batch_columns() {
+        let records = [
+            DataRecord {
+                id: 0,
+                items: vec![
+                    DataField::from_chars("a", "x"),
+                    DataField::from_digit("n", 5),
+                ],
+            },
+            DataRecord {
+                id: 1,
+                items: vec![
+                    DataField::from_digit("n", 12345),
+                    DataField::from_chars("b", "new"),
+                ],
+            },
+        ];
+        assert_eq!(
+            Table::new().format_batch(&records),
+            "+---+-------+-----+\n\
+             | a | n     | b   |\n\
+             +---+-------+-----+\n\
+             | x |     5 |     |\n\
+             |   | 12345 | new |\n\
+             +---+-------+-----+"
+        );
+        assert_eq!(Table::new().format_batch(&[]), "");
+    }
+}
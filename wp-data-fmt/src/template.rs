@@ -0,0 +1,474 @@
+use crate::formatter::{DataFormat, StreamDataFormat, render};
+use crate::json::write_json_string;
+use crate::raw::Raw;
+use crate::time_fmt::TimeFormat;
+use chrono::format::{Item, StrftimeItems};
+use std::fmt::{self, Write};
+use wp_model_core::model::{DataField, DataRecord, DataType, Value, types::value::ObjectValue};
+
+/// A fixed line layout with `{field}` placeholders, compiled once.
+///
+/// ```text
+/// {ip} - - [{time:%d/%b/%Y:%H:%M:%S %z}] "{http/request|escape=nginx}" {http/status}
+/// ```
+///
+/// A placeholder names a field, or a dotted path into objects
+/// (`upstream.addr`), and writes its value as [`Raw`] does. It may add:
+/// - `:spec`: a strftime pattern for times, or `[<>][0][width][.precision]`
+///   for other values (numbers align right, text left; precision rounds
+///   floats and cuts text)
+/// - `|default=text`: written when the field is missing, null or empty
+/// - `|escape=json` (string contents without quotes) or `|escape=nginx`
+///   (`"`, `\` and non-printable bytes as `\xHH`)
+///
+/// `{{` and `}}` are literal braces.
+#[derive(Debug, Clone, Default)]
+pub struct Template {
+    segments: Vec<Segment>,
+    time_format: TimeFormat,
+    raw: Raw,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Field(Placeholder),
+}
+
+#[derive(Debug, Clone)]
+struct Placeholder {
+    name: String,
+    /// `name` split on `.`, when it has more than one part.
+    path: Vec<String>,
+    spec: Spec,
+    default: Option<String>,
+    escape: Escape,
+}
+
+#[derive(Debug, Clone)]
+enum Spec {
+    None,
+    Strftime(Vec<Item<'static>>),
+    Pad {
+        align: Option<Align>,
+        zero: bool,
+        width: usize,
+        precision: Option<usize>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Align {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Escape {
+    None,
+    Json,
+    Nginx,
+}
+
+impl Template {
+    /// Parses `pattern`; errors name the byte offset of the bad placeholder.
+    pub fn compile(pattern: &str) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut pos = 0;
+        while let Some(c) = pattern[pos..].chars().next() {
+            let rest = &pattern[pos..];
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                literal.push(c);
+                pos += 2;
+                continue;
+            }
+            match c {
+                '{' => {
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| format!("unclosed '{{' at byte {}", pos))?;
+                    let body = &rest[1..end];
+                    if body.contains('{') {
+                        return Err(format!("nested '{{' at byte {}", pos));
+                    }
+                    let placeholder =
+                        Placeholder::parse(body).map_err(|e| format!("{} at byte {}", e, pos))?;
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Field(placeholder));
+                    pos += end + 1;
+                }
+                '}' => return Err(format!("unmatched '}}' at byte {}", pos)),
+                c => {
+                    literal.push(c);
+                    pos += c.len_utf8();
+                }
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Self {
+            segments,
+            ..Self::default()
+        })
+    }
+
+    /// Used for times without a strftime spec; its offset also applies to
+    /// the strftime specs.
+    pub fn with_time_format(mut self, time_format: impl Into<TimeFormat>) -> Self {
+        self.time_format = time_format.into();
+        self.raw = Raw::new().with_time_format(self.time_format.clone());
+        self
+    }
+
+    /// Field names and paths the template reads, in order.
+    pub fn fields(&self) -> impl Iterator<Item = &str> {
+        self.segments.iter().filter_map(|s| match s {
+            Segment::Field(p) => Some(p.name.as_str()),
+            Segment::Literal(_) => None,
+        })
+    }
+
+    fn write_placeholder(
+        &self,
+        p: &Placeholder,
+        record: &DataRecord,
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        let value = p.lookup(record).filter(|v| !is_blank(v));
+        let Some(value) = value else {
+            let default = p.default.as_deref().unwrap_or("");
+            return p.spec.pad(default, false, out);
+        };
+        if matches!(p.spec, Spec::None) && p.escape == Escape::None {
+            return self.write_value(value, &p.spec, out);
+        }
+        let text = render(|w| self.write_value(value, &p.spec, w));
+        let text = match p.escape {
+            Escape::None => text,
+            Escape::Json => {
+                let quoted = render(|w| write_json_string(w, &text));
+                quoted[1..quoted.len() - 1].to_string()
+            }
+            Escape::Nginx => nginx_escape(&text),
+        };
+        p.spec.pad(
+            &text,
+            matches!(value, Value::Digit(_) | Value::Float(_)),
+            out,
+        )
+    }
+
+    fn write_value(&self, value: &Value, spec: &Spec, out: &mut dyn Write) -> fmt::Result {
+        match (value, spec) {
+            (Value::Time(t), Spec::Strftime(items)) => self.time_format.write_items(t, items, out),
+            (
+                Value::Float(f),
+                Spec::Pad {
+                    precision: Some(p), ..
+                },
+            ) => write!(out, "{:.*}", p, f),
+            _ => self.raw.fmt_value_into(value, out),
+        }
+    }
+}
+
+impl Placeholder {
+    fn parse(body: &str) -> Result<Self, String> {
+        let mut parts = body.split('|');
+        let head = parts.next().unwrap_or_default();
+        let (name, spec) = match head.split_once(':') {
+            Some((name, spec)) => (name.trim(), Spec::parse(spec)?),
+            None => (head.trim(), Spec::None),
+        };
+        if name.is_empty() {
+            return Err("empty field name".to_string());
+        }
+        let path = if name.contains('.') {
+            name.split('.').map(str::to_string).collect()
+        } else {
+            Vec::new()
+        };
+        let mut placeholder = Placeholder {
+            name: name.to_string(),
+            path,
+            spec,
+            default: None,
+            escape: Escape::None,
+        };
+        for option in parts {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| format!("option '{}' of '{}' has no value", option, name))?;
+            match key.trim() {
+                "default" => placeholder.default = Some(value.to_string()),
+                "escape" => {
+                    placeholder.escape = match value.trim() {
+                        "none" => Escape::None,
+                        "json" => Escape::Json,
+                        "nginx" => Escape::Nginx,
+                        other => return Err(format!("unknown escape mode '{}'", other)),
+                    }
+                }
+                other => return Err(format!("unknown option '{}' of '{}'", other, name)),
+            }
+        }
+        Ok(placeholder)
+    }
+
+    /// The field named exactly `name` wins over a dotted path into objects.
+    fn lookup<'a>(&self, record: &'a DataRecord) -> Option<&'a Value> {
+        let top = |name: &str| {
+            record
+                .items
+                .iter()
+                .find(|f| f.get_name() == name && *f.get_meta() != DataType::Ignore)
+                .map(|f| f.get_value())
+        };
+        if let Some(value) = top(&self.name) {
+            return Some(value);
+        }
+        let (head, keys) = self.path.split_first()?;
+        let mut value = top(head)?;
+        for key in keys {
+            let Value::Obj(obj) = value else {
+                return None;
+            };
+            value = obj.get(key.as_str())?.get_value();
+        }
+        Some(value)
+    }
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Result<Self, String> {
+        if spec.contains('%') {
+            let items = StrftimeItems::new(spec)
+                .parse_to_owned()
+                .map_err(|_| format!("invalid strftime pattern '{}'", spec))?;
+            return Ok(Spec::Strftime(items));
+        }
+        let invalid = || format!("invalid spec '{}'", spec);
+        let mut rest = spec;
+        let align = match rest.chars().next() {
+            Some('<') => Some(Align::Left),
+            Some('>') => Some(Align::Right),
+            _ => None,
+        };
+        if align.is_some() {
+            rest = &rest[1..];
+        }
+        let zero = rest.starts_with('0');
+        let (width, precision) = match rest.split_once('.') {
+            Some((width, precision)) => (width, Some(precision)),
+            None => (rest, None),
+        };
+        let width = if width.is_empty() {
+            0
+        } else {
+            width.parse().map_err(|_| invalid())?
+        };
+        let precision = precision
+            .map(|p| p.parse().map_err(|_| invalid()))
+            .transpose()?;
+        Ok(Spec::Pad {
+            align,
+            zero,
+            width,
+            precision,
+        })
+    }
+
+    fn pad(&self, text: &str, numeric: bool, out: &mut dyn Write) -> fmt::Result {
+        let Spec::Pad {
+            align,
+            zero,
+            width,
+            precision,
+        } = self
+        else {
+            return out.write_str(text);
+        };
+        let text = match precision {
+            Some(p) if !numeric => text
+                .char_indices()
+                .nth(*p)
+                .map_or(text, |(i, _)| &text[..i]),
+            _ => text,
+        };
+        let fill = width.saturating_sub(text.chars().count());
+        if *zero && numeric {
+            let (sign, digits) = match text.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", text),
+            };
+            return write!(out, "{}{}{}", sign, "0".repeat(fill), digits);
+        }
+        let align = align.unwrap_or(if numeric { Align::Right } else { Align::Left });
+        match align {
+            Align::Left => write!(out, "{}{:fill$}", text, ""),
+            Align::Right => write!(out, "{:fill$}{}", "", text),
+        }
+    }
+}
+
+fn is_blank(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Chars(s) => s.is_empty(),
+        _ => false,
+    }
+}
+
+/// nginx `escape=default`: `"`, `\` and bytes outside printable ASCII.
+fn nginx_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for b in text.bytes() {
+        if b == b'"' || b == b'\\' || !(0x20..0x7f).contains(&b) {
+            let _ = write!(out, "\\x{:02X}", b);
+        } else {
+            out.push(b as char);
+        }
+    }
+    out
+}
+
+impl StreamDataFormat for Template {
+    fn format_null_into(&self, out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_null_into(out)
+    }
+    fn format_bool_into(&self, v: &bool, out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_bool_into(v, out)
+    }
+    fn format_string_into(&self, v: &str, out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_string_into(v, out)
+    }
+    fn format_i64_into(&self, v: &i64, out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_i64_into(v, out)
+    }
+    fn format_f64_into(&self, v: &f64, out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_f64_into(v, out)
+    }
+    fn format_ip_into(&self, v: &std::net::IpAddr, out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_ip_into(v, out)
+    }
+    fn format_datetime_into(&self, v: &chrono::NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_datetime_into(v, out)
+    }
+    fn format_object_into(&self, v: &ObjectValue, out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_object_into(v, out)
+    }
+    fn format_array_into(&self, v: &[DataField], out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_array_into(v, out)
+    }
+    fn format_field_into(&self, field: &DataField, out: &mut dyn Write) -> fmt::Result {
+        self.raw.format_field_into(field, out)
+    }
+    fn format_record_into(&self, record: &DataRecord, out: &mut dyn Write) -> fmt::Result {
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => out.write_str(text)?,
+                Segment::Field(p) => self.write_placeholder(p, record, out)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DataFormat for Template {
+    type Output = String;
+    fn format_null(&self) -> String {
+        render(|out| self.format_null_into(out))
+    }
+    fn format_bool(&self, v: &bool) -> String {
+        render(|out| self.format_bool_into(v, out))
+    }
+    fn format_string(&self, v: &str) -> String {
+        render(|out| self.format_string_into(v, out))
+    }
+    fn format_i64(&self, v: &i64) -> String {
+        render(|out| self.format_i64_into(v, out))
+    }
+    fn format_f64(&self, v: &f64) -> String {
+        render(|out| self.format_f64_into(v, out))
+    }
+    fn format_ip(&self, v: &std::net::IpAddr) -> String {
+        render(|out| self.format_ip_into(v, out))
+    }
+    fn format_datetime(&self, v: &chrono::NaiveDateTime) -> String {
+        render(|out| self.format_datetime_into(v, out))
+    }
+    fn format_object(&self, v: &ObjectValue) -> String {
+        render(|out| self.format_object_into(v, out))
+    }
+    fn format_array(&self, v: &[DataField]) -> String {
+        render(|out| self.format_array_into(v, out))
+    }
+    fn format_field(&self, f: &DataField) -> String {
+        render(|out| self.format_field_into(f, out))
+    }
+    fn format_record(&self, r: &DataRecord) -> String {
+        render(|out| self.format_record_into(r, out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> DataRecord {
+        let mut upstream = ObjectValue::new();
+        upstream.insert("addr", DataField::from_chars("addr", "10.0.0.8:80"));
+        DataRecord {
+            id: 0,
+            items: vec![
+                DataField::from_chars("msg", "say \"hi\"\n\u{e9}"),
+                DataField::from_digit("n", -42),
+                DataField::from_float("f", 1.23456),
+                DataField::from_chars("empty", ""),
+                DataField::from_obj("upstream", upstream),
+                DataField::from_ignore("hidden"),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_template_specs_defaults_escapes() {
+        let render = |pattern: &str| Template::compile(pattern).unwrap().format_record(&record());
+        assert_eq!(render("{{{n}}} {upstream.addr}"), "{-42} 10.0.0.8:80");
+        assert_eq!(
+            render("[{n:6}|{n:<6}|{n:06}|{f:.2}|{f:>8.1}]"),
+            "[   -42|-42   |-00042|1.23|     1.2]"
+        );
+        assert_eq!(
+            render("[{msg:.3}|{upstream.addr:>12}]"),
+            "[say| 10.0.0.8:80]"
+        );
+        assert_eq!(
+            render("{empty|default=-} {missing|default=-} {hidden} {upstream.port:>3|default=0}"),
+            "- -    0"
+        );
+        assert_eq!(render("{msg|escape=json}"), "say \\\"hi\\\"\\n\u{e9}");
+        assert_eq!(
+            render("{msg|escape=nginx}"),
+            "say \\x22hi\\x22\\x0A\\xC3\\xA9"
+        );
+    }
+
+    #[test]
+    fn test_template_compile_errors() {
+        let err = |pattern: &str| Template::compile(pattern).unwrap_err();
+        assert_eq!(err("a {ip"), "unclosed '{' at byte 2");
+        assert_eq!(err("a } b"), "unmatched '}' at byte 2");
+        assert_eq!(err("{}"), "empty field name at byte 0");
+        assert_eq!(err("x{n:abc}"), "invalid spec 'abc' at byte 1");
+        assert_eq!(err("{t:%Q}"), "invalid strftime pattern '%Q' at byte 0");
+        assert_eq!(err("{n|escape=xml}"), "unknown escape mode 'xml' at byte 0");
+        assert_eq!(err("{n|pad=1}"), "unknown option 'pad' of 'n' at byte 0");
+        let template = Template::compile("{a} {b.c}").unwrap();
+        assert_eq!(template.fields().collect::<Vec<_>>(), ["a", "b.c"]);
+    }
+}
@@ -0,0 +1,209 @@
+use crate::timestamp::{TimestampFormat, TimestampParser};
+use chrono::format::Item;
+use chrono::{DateTime, FixedOffset, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write};
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeStyle {
+    /// `2019-08-06 12:12:19`, the value as parsed, without an offset.
+    #[default]
+    Naive,
+    /// `2019-08-06T12:12:19+08:00`; `Z` for UTC.
+    Rfc3339,
+    EpochSecs,
+    EpochMillis,
+    EpochNanos,
+    /// chrono strftime pattern; `%z` / `%:z` print the source offset.
+    Strftime(String),
+}
+
+/// How `Value::Time` is rendered, shared by the text formatters.
+///
+/// Parsed times carry no zone, so `offset` says which one they were
+/// recorded in; it decides the RFC 3339 offset and the epoch values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeFormat {
+    style: TimeStyle,
+    offset: FixedOffset,
+}
+
+const UTC: FixedOffset = match FixedOffset::east_opt(0) {
+    Some(utc) => utc,
+    None => unreachable!(),
+};
+
+impl Default for TimeFormat {
+    fn default() -> Self {
+        Self::new(TimeStyle::Naive)
+    }
+}
+
+impl TimeFormat {
+    pub const fn new(style: TimeStyle) -> Self {
+        Self { style, offset: UTC }
+    }
+    /// Source timezone of the naive values; UTC by default.
+    pub fn with_offset(mut self, offset: FixedOffset) -> Self {
+        self.offset = offset;
+        self
+    }
+    pub fn style(&self) -> &TimeStyle {
+        &self.style
+    }
+
+    /// Epoch styles render as bare numbers, the others as text that
+    /// formatters quote and escape like any string.
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self.style,
+            TimeStyle::EpochSecs | TimeStyle::EpochMillis | TimeStyle::EpochNanos
+        )
+    }
+
+    fn attach(&self, v: &NaiveDateTime) -> DateTime<FixedOffset> {
+        // a fixed offset maps every local time to exactly one instant
+        self.offset
+            .from_local_datetime(v)
+            .single()
+            .unwrap_or_else(|| self.offset.from_utc_datetime(v))
+    }
+
+    /// Writes the rendered value, unquoted.
+    pub fn write_into(&self, v: &NaiveDateTime, out: &mut dyn Write) -> fmt::Result {
+        match &self.style {
+            TimeStyle::Naive => write!(out, "{}", v),
+            TimeStyle::Rfc3339 => {
+                out.write_str(&self.attach(v).to_rfc3339_opts(SecondsFormat::AutoSi, true))
+            }
+            TimeStyle::EpochSecs => write!(out, "{}", self.attach(v).timestamp()),
+            TimeStyle::EpochMillis => write!(out, "{}", self.attach(v).timestamp_millis()),
+            TimeStyle::EpochNanos => {
+                let t = self.attach(v);
+                let nanos =
+                    t.timestamp() as i128 * 1_000_000_000 + t.timestamp_subsec_nanos() as i128;
+                write!(out, "{}", nanos)
+            }
+            TimeStyle::Strftime(pattern) => {
+                // an invalid specifier makes chrono's Display fail midway;
+                // render aside and fall back to the naive form
+                let mut buf = String::new();
+                match write!(buf, "{}", self.attach(v).format(pattern)) {
+                    Ok(()) => out.write_str(&buf),
+                    Err(_) => write!(out, "{}", v),
+                }
+            }
+        }
+    }
+
+    /// Writes `v` with precompiled strftime items, in this format's offset.
+    pub(crate) fn write_items(
+        &self,
+        v: &NaiveDateTime,
+        items: &[Item<'_>],
+        out: &mut dyn Write,
+    ) -> fmt::Result {
+        write!(out, "{}", self.attach(v).format_with_items(items.iter()))
+    }
+
+    pub fn render(&self, v: &NaiveDateTime) -> String {
+        let mut buf = String::new();
+        let _ = self.write_into(v, &mut buf);
+        buf
+    }
+
+    /// Reads a value written by [`TimeFormat::write_into`] back into the
+    /// source timezone.
+    pub fn parse(&self, s: &str) -> Option<NaiveDateTime> {
+        let local = |t: DateTime<Utc>| self.offset.from_utc_datetime(&t.naive_utc()).naive_local();
+        match &self.style {
+            TimeStyle::Naive => NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f").ok(),
+            TimeStyle::Rfc3339 => DateTime::parse_from_rfc3339(s)
+                .ok()
+                .map(|t| local(t.to_utc())),
+            TimeStyle::EpochSecs => DateTime::from_timestamp(s.parse().ok()?, 0).map(local),
+            TimeStyle::EpochMillis => DateTime::from_timestamp_millis(s.parse().ok()?).map(local),
+            TimeStyle::EpochNanos => {
+                let nanos: i128 = s.parse().ok()?;
+                let secs = i64::try_from(nanos.div_euclid(1_000_000_000)).ok()?;
+                DateTime::from_timestamp(secs, nanos.rem_euclid(1_000_000_000) as u32).map(local)
+            }
+            TimeStyle::Strftime(pattern) => {
+                TimestampParser::new(vec![TimestampFormat::Custom(pattern.clone())])
+                    .with_default_offset(self.offset)
+                    .parse(s)
+                    .map(|t| local(t.utc.and_utc()))
+            }
+        }
+    }
+}
+
+impl From<TimeStyle> for TimeFormat {
+    fn from(style: TimeStyle) -> Self {
+        Self::new(style)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2019-08-06 12:12:19.5", "%Y-%m-%d %H:%M:%S%.f").unwrap()
+    }
+
+    fn cst() -> FixedOffset {
+        FixedOffset::east_opt(8 * 3600).unwrap()
+    }
+
+    #[test]
+    fn test_styles() {
+        let render = |style: TimeStyle| TimeFormat::new(style).render(&ts());
+        assert_eq!(render(TimeStyle::Naive), "2019-08-06 12:12:19.500");
+        assert_eq!(render(TimeStyle::Rfc3339), "2019-08-06T12:12:19.500Z");
+        assert_eq!(render(TimeStyle::EpochSecs), "1565093539");
+        assert_eq!(render(TimeStyle::EpochMillis), "1565093539500");
+        assert_eq!(render(TimeStyle::EpochNanos), "1565093539500000000");
+        assert_eq!(
+            render(TimeStyle::Strftime("%d/%b/%Y:%H:%M:%S".into())),
+            "06/Aug/2019:12:12:19"
+        );
+        assert_eq!(
+            render(TimeStyle::Strftime("%Q".into())),
+            "2019-08-06 12:12:19.500"
+        );
+    }
+
+    #[test]
+    fn test_parse_inverts_render() {
+        let styles = [
+            TimeStyle::Naive,
+            TimeStyle::Rfc3339,
+            TimeStyle::EpochMillis,
+            TimeStyle::EpochNanos,
+            TimeStyle::Strftime("%d/%b/%Y:%H:%M:%S%.3f %z".into()),
+        ];
+        for style in styles {
+            let f = TimeFormat::new(style).with_offset(cst());
+            assert_eq!(f.parse(&f.render(&ts())), Some(ts()), "{:?}", f.style());
+        }
+        let secs = TimeFormat::new(TimeStyle::EpochSecs).with_offset(cst());
+        assert_eq!(
+            secs.parse("1565064739").unwrap().to_string(),
+            "2019-08-06 12:12:19"
+        );
+        assert_eq!(secs.parse("x"), None);
+    }
+
+    #[test]
+    fn test_source_offset() {
+        let f = TimeFormat::new(TimeStyle::Rfc3339).with_offset(cst());
+        assert_eq!(f.render(&ts()), "2019-08-06T12:12:19.500+08:00");
+        let f = TimeFormat::new(TimeStyle::EpochSecs).with_offset(cst());
+        assert_eq!(f.render(&ts()), "1565064739");
+        let f = TimeFormat::new(TimeStyle::Strftime("%H:%M %z".into())).with_offset(cst());
+        assert_eq!(f.render(&ts()), "12:12 +0800");
+        assert!(!f.is_numeric());
+    }
+}
@@ -0,0 +1,328 @@
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+/// A timestamp layout understood by [`TimestampParser`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// `2019-08-06 12:12:19`, optionally with fractional seconds.
+    DateTime,
+    /// `2019-08-06T12:12:19.250+08:00`; fraction and offset (`Z`, `+08:00`,
+    /// `+0800`) are optional.
+    Iso8601,
+    /// nginx / Apache common log: `06/Aug/2019:12:12:19 +0800`.
+    CommonLog,
+    /// `Tue, 6 Aug 2019 12:12:19 +0800`.
+    Rfc2822,
+    /// BSD syslog `Aug  6 12:12:19`, which has no year.
+    Rfc3164,
+    /// Unix epoch; the unit (s, ms, us, ns) follows from the number of
+    /// integer digits, and seconds may carry a fraction.
+    Epoch,
+    /// chrono strftime pattern; an offset is used when it has `%z`.
+    Custom(String),
+}
+
+impl TimestampFormat {
+    /// Every built-in format, in the order auto-detection tries them.
+    pub fn builtin() -> Vec<TimestampFormat> {
+        vec![
+            TimestampFormat::DateTime,
+            TimestampFormat::Iso8601,
+            TimestampFormat::CommonLog,
+            TimestampFormat::Rfc2822,
+            TimestampFormat::Rfc3164,
+            TimestampFormat::Epoch,
+        ]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedTimestamp {
+    /// The instant, normalized to UTC.
+    pub utc: NaiveDateTime,
+    /// Offset written in the input, or the parser's default offset.
+    pub offset: FixedOffset,
+    /// Format that matched.
+    pub format: TimestampFormat,
+}
+
+impl ParsedTimestamp {
+    pub fn to_datetime(&self) -> DateTime<FixedOffset> {
+        self.offset.from_utc_datetime(&self.utc)
+    }
+}
+
+/// Tries its formats in order and returns the first match.
+///
+/// Inputs without an offset are read in `default_offset` (UTC unless set).
+/// Formats without a year take it from the reference date (today by
+/// default); a result more than a day past the reference is moved back a
+/// year, so December logs read in January keep their year.
+#[derive(Debug, Clone)]
+pub struct TimestampParser {
+    formats: Vec<TimestampFormat>,
+    default_offset: FixedOffset,
+    reference: Option<NaiveDate>,
+}
+
+impl Default for TimestampParser {
+    fn default() -> Self {
+        Self::new(TimestampFormat::builtin())
+    }
+}
+
+impl TimestampParser {
+    pub fn new(formats: Vec<TimestampFormat>) -> Self {
+        Self {
+            formats,
+            default_offset: FixedOffset::east_opt(0).expect("zero offset"),
+            reference: None,
+        }
+    }
+    /// Auto-detection among all built-in formats.
+    pub fn auto() -> Self {
+        Self::default()
+    }
+    pub fn with_default_offset(mut self, offset: FixedOffset) -> Self {
+        self.default_offset = offset;
+        self
+    }
+    pub fn with_reference_date(mut self, date: NaiveDate) -> Self {
+        self.reference = Some(date);
+        self
+    }
+
+    pub fn parse(&self, input: &str) -> Option<ParsedTimestamp> {
+        let input = input.trim();
+        if input.is_empty() {
+            return None;
+        }
+        self.formats.iter().find_map(|format| {
+            let (utc, offset) = self.parse_as(format, input)?;
+            Some(ParsedTimestamp {
+                utc,
+                offset,
+                format: format.clone(),
+            })
+        })
+    }
+
+    fn parse_as(&self, format: &TimestampFormat, s: &str) -> Option<(NaiveDateTime, FixedOffset)> {
+        match format {
+            TimestampFormat::DateTime => {
+                self.local(NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f").ok()?)
+            }
+            TimestampFormat::Iso8601 => {
+                if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+                    return Some(split(t));
+                }
+                if let Ok(t) = DateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f%z") {
+                    return Some(split(t));
+                }
+                self.local(NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f").ok()?)
+            }
+            TimestampFormat::CommonLog => DateTime::parse_from_str(s, "%d/%b/%Y:%H:%M:%S %z")
+                .ok()
+                .map(split),
+            TimestampFormat::Rfc2822 => DateTime::parse_from_rfc2822(s).ok().map(split),
+            TimestampFormat::Rfc3164 => self.parse_yearless(s),
+            TimestampFormat::Epoch => parse_epoch(s).map(|t| (t, self.default_offset)),
+            TimestampFormat::Custom(pattern) => {
+                if let Ok(t) = DateTime::parse_from_str(s, pattern) {
+                    return Some(split(t));
+                }
+                self.local(NaiveDateTime::parse_from_str(s, pattern).ok()?)
+            }
+        }
+    }
+
+    fn local(&self, t: NaiveDateTime) -> Option<(NaiveDateTime, FixedOffset)> {
+        let t = self.default_offset.from_local_datetime(&t).single()?;
+        Some(split(t))
+    }
+
+    fn parse_yearless(&self, s: &str) -> Option<(NaiveDateTime, FixedOffset)> {
+        // the day is space padded (`Aug  6`); collapse runs of spaces
+        let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+        let reference = self.reference.unwrap_or_else(|| Utc::now().date_naive());
+        let parse = |year: i32| {
+            NaiveDateTime::parse_from_str(&format!("{} {}", year, s), "%Y %b %d %H:%M:%S%.f").ok()
+        };
+        // the latest year not ahead of the reference in which the date
+        // exists; Feb 29 can be up to eight years back (1896 to 1904)
+        let t = (0..=8).find_map(|back| {
+            parse(reference.year() - back).filter(|t| t.date() <= reference + Duration::days(1))
+        })?;
+        self.local(t)
+    }
+}
+
+fn split(t: DateTime<FixedOffset>) -> (NaiveDateTime, FixedOffset) {
+    (t.naive_utc(), *t.offset())
+}
+
+fn parse_epoch(s: &str) -> Option<NaiveDateTime> {
+    let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+    if int.is_empty()
+        || !int.bytes().all(|b| b.is_ascii_digit())
+        || !frac.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let n: i64 = int.parse().ok()?;
+    let t = match int.len() {
+        0..=10 => {
+            // up to nanosecond precision from the fraction
+            let digits = &frac[..frac.len().min(9)];
+            let nanos = format!("{:0<9}", digits).parse::<u32>().ok()?;
+            DateTime::from_timestamp(n, nanos)?
+        }
+        _ if !frac.is_empty() => return None,
+        11..=13 => DateTime::from_timestamp_millis(n)?,
+        14..=16 => DateTime::from_timestamp_micros(n)?,
+        _ => DateTime::from_timestamp_nanos(n),
+    };
+    Some(t.naive_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f").unwrap()
+    }
+
+    fn cst() -> FixedOffset {
+        FixedOffset::east_opt(8 * 3600).unwrap()
+    }
+
+    fn auto() -> TimestampParser {
+        TimestampParser::auto().with_reference_date(NaiveDate::from_ymd_opt(2019, 9, 1).unwrap())
+    }
+
+    #[test]
+    fn test_auto_detect() {
+        let cases = [
+            (
+                "2019-08-06 12:12:19",
+                "2019-08-06 12:12:19",
+                TimestampFormat::DateTime,
+            ),
+            (
+                "2019-08-06T12:12:19.25Z",
+                "2019-08-06 12:12:19.25",
+                TimestampFormat::Iso8601,
+            ),
+            (
+                "2019-08-06T20:12:19+0800",
+                "2019-08-06 12:12:19",
+                TimestampFormat::Iso8601,
+            ),
+            (
+                "06/Aug/2019:20:12:19 +0800",
+                "2019-08-06 12:12:19",
+                TimestampFormat::CommonLog,
+            ),
+            (
+                "Tue, 6 Aug 2019 20:12:19 +0800",
+                "2019-08-06 12:12:19",
+                TimestampFormat::Rfc2822,
+            ),
+            (
+                "Aug  6 12:12:19",
+                "2019-08-06 12:12:19",
+                TimestampFormat::Rfc3164,
+            ),
+            ("1565093539", "2019-08-06 12:12:19", TimestampFormat::Epoch),
+            (
+                "1565093539.5",
+                "2019-08-06 12:12:19.5",
+                TimestampFormat::Epoch,
+            ),
+            (
+                "1565093539500",
+                "2019-08-06 12:12:19.5",
+                TimestampFormat::Epoch,
+            ),
+            (
+                "1565093539500000000",
+                "2019-08-06 12:12:19.5",
+                TimestampFormat::Epoch,
+            ),
+        ];
+        for (input, expect, format) in cases {
+            let parsed = auto().parse(input).unwrap_or_else(|| panic!("{}", input));
+            assert_eq!(parsed.utc, utc(expect), "{}", input);
+            assert_eq!(parsed.format, format, "{}", input);
+        }
+        assert!(auto().parse("not a time").is_none());
+        assert!(auto().parse("").is_none());
+    }
+
+    #[test]
+    fn test_offsets() {
+        let parsed = auto().parse("06/Aug/2019:12:12:19 +0800").unwrap();
+        assert_eq!(parsed.offset, cst());
+        assert_eq!(
+            parsed.to_datetime().to_rfc3339(),
+            "2019-08-06T12:12:19+08:00"
+        );
+
+        // no offset in the input: read in the default offset
+        let parsed = auto()
+            .with_default_offset(cst())
+            .parse("2019-08-06 12:12:19")
+            .unwrap();
+        assert_eq!(parsed.utc, utc("2019-08-06 04:12:19"));
+        assert_eq!(parsed.offset, cst());
+    }
+
+    #[test]
+    fn test_missing_year() {
+        let jan = NaiveDate::from_ymd_opt(2020, 1, 2).unwrap();
+        let p = TimestampParser::new(vec![TimestampFormat::Rfc3164]).with_reference_date(jan);
+        assert_eq!(
+            p.parse("Dec 31 23:59:59").unwrap().utc,
+            utc("2019-12-31 23:59:59")
+        );
+        assert_eq!(
+            p.parse("Jan  2 08:00:00").unwrap().utc,
+            utc("2020-01-02 08:00:00")
+        );
+
+        let march = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        let p = TimestampParser::new(vec![TimestampFormat::Rfc3164]).with_reference_date(march);
+        assert_eq!(
+            p.parse("Feb 29 10:00:00").unwrap().utc,
+            utc("2024-02-29 10:00:00")
+        );
+        // more than a year after the last Feb 29
+        for (reference, expected) in [((2026, 6, 1), "2024"), ((1904, 1, 1), "1896")] {
+            let (y, m, d) = reference;
+            let p = TimestampParser::new(vec![TimestampFormat::Rfc3164])
+                .with_reference_date(NaiveDate::from_ymd_opt(y, m, d).unwrap());
+            assert_eq!(
+                p.parse("Feb 29 10:00:00").unwrap().utc,
+                utc(&format!("{}-02-29 10:00:00", expected))
+            );
+        }
+    }
+
+    #[test]
+    fn test_ordered_formats() {
+        let p = TimestampParser::new(vec![
+            TimestampFormat::Custom("%Y/%m/%d %H:%M:%S".into()),
+            TimestampFormat::Epoch,
+        ]);
+        let parsed = p.parse("2019/08/06 12:12:19").unwrap();
+        assert_eq!(
+            parsed.format,
+            TimestampFormat::Custom("%Y/%m/%d %H:%M:%S".into())
+        );
+        assert!(p.parse("2019-08-06 12:12:19").is_none());
+        assert_eq!(
+            p.parse("1565093539").unwrap().format,
+            TimestampFormat::Epoch
+        );
+    }
+}
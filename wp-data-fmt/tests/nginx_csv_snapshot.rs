@@ -1,6 +1,6 @@
 use chrono::NaiveDateTime;
 use std::net::{IpAddr, Ipv4Addr};
-use wp_data_fmt::{Csv, DataFormat};
+use wp_data_fmt::{Csv, CsvWriter, DataFormat};
 use wp_model_core::model::{DataField, DataRecord};
 
 // 生成 CSV 文本的快照测试，参考 nginx_proto_txt_snapshot.rs
@@ -14,6 +14,7 @@ fn nginx_access_log_csv_snapshot() {
     let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
 
     let record = DataRecord {
+        id: 0,
         items: vec![
             DataField::from_ip("ip", ip),
             DataField::from_time("time", ts),
@@ -41,6 +42,7 @@ fn nginx_access_log_csv_snapshot() {
 fn csv_string_escape() {
     // 验证引号与分隔符触发正确转义
     let record = DataRecord {
+        id: 0,
         items: vec![
             DataField::from_chars("msg", "He said \"hi\""),
             DataField::from_chars("note", "a,b"),
@@ -53,3 +55,31 @@ fn csv_string_escape() {
     let expected = r#""He said ""hi""","a,b",1"#;
     assert_eq!(out, expected);
 }
+
+#[test]
+fn nginx_csv_writer_snapshot() {
+    // 列集合由第一条记录锁定；后续记录缺失字段填空，字段顺序按表头重排
+    let first = DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_ip("ip", IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2))),
+            DataField::from_chars("http/request", "GET /nginx-logo.png HTTP/1.1"),
+            DataField::from_digit("http/status", 200),
+        ],
+    };
+    let second = DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_digit("http/status", 404),
+            DataField::from_ip("ip", IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))),
+        ],
+    };
+
+    let mut w = CsvWriter::new(Csv::new());
+    let mut out = String::new();
+    w.write_record(&first, &mut out).unwrap();
+    w.write_record(&second, &mut out).unwrap();
+
+    let expected = "ip,http/request,http/status\n192.168.1.2,GET /nginx-logo.png HTTP/1.1,200\n10.0.0.1,,404\n";
+    assert_eq!(out, expected);
+}
@@ -0,0 +1,48 @@
+use chrono::NaiveDateTime;
+use std::net::{IpAddr, Ipv4Addr};
+use wp_data_fmt::{BulkAction, EsBulk};
+use wp_model_core::model::{DataField, DataRecord};
+
+// 生成 Elasticsearch _bulk 请求体的快照测试
+// 关注点：
+// - 每条记录输出 action 行 + 文档行，均以 \n 结尾
+// - _index 按记录时间填充日期模板，_id 取自指定字段
+// - 按文档数拆分成多个请求体
+fn nginx_record(day: u32, id: &str) -> DataRecord {
+    let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2));
+    let ts =
+        NaiveDateTime::parse_from_str(&format!("2019-08-{:02} 12:12:19", day), "%Y-%m-%d %H:%M:%S")
+            .unwrap();
+    DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_chars("req_id", id),
+            DataField::from_ip("ip", ip),
+            DataField::from_time("time", ts),
+            DataField::from_chars("http/request", "GET /nginx-logo.png HTTP/1.1"),
+            DataField::from_digit("http/status", 200),
+        ],
+    }
+}
+
+#[test]
+fn nginx_access_log_es_bulk_snapshot() {
+    let f = EsBulk::new("nginx-%Y.%m.%d")
+        .with_action(BulkAction::Create)
+        .with_id_field("req_id")
+        .with_max_docs(2);
+    let records = vec![
+        nginx_record(6, "a1"),
+        nginx_record(6, "a2"),
+        nginx_record(7, "a3"),
+    ];
+    let bodies = f.format_batch(&records).unwrap();
+    let doc = |day: u32, id: &str| {
+        format!(
+            "{{\"create\":{{\"_index\":\"nginx-2019.08.{:02}\",\"_id\":\"{}\"}}}}\n\
+             {{\"req_id\":\"{}\",\"ip\":\"192.168.1.2\",\"time\":\"2019-08-{:02} 12:12:19\",\"http/request\":\"GET /nginx-logo.png HTTP/1.1\",\"http/status\":200}}\n",
+            day, id, id, day
+        )
+    };
+    assert_eq!(bodies, vec![doc(6, "a1") + &doc(6, "a2"), doc(7, "a3")]);
+}
@@ -0,0 +1,98 @@
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr};
+use wp_data_fmt::{DataFormat, FormatSpec, StreamDataFormat};
+use wp_model_core::model::{DataField, DataRecord};
+
+// 声明式输出格式（FormatSpec）的快照测试
+// 关注点：
+// - sink 配置中以 `{ type = "..", .. }` 声明格式及选项
+// - build() 得到的 FormatType 与 StreamDataFormat 输出一致
+// - 非法选项在 build 时报错
+fn nginx_record() -> DataRecord {
+    let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+    DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_ip("ip", IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2))),
+            DataField::from_time("time", ts),
+            DataField::from_chars("http/request", "GET /nginx-logo.png HTTP/1.1"),
+            DataField::from_digit("http/status", 200),
+        ],
+    }
+}
+
+#[derive(Deserialize)]
+struct Sink {
+    name: String,
+    format: FormatSpec,
+}
+
+#[derive(Deserialize)]
+struct Sinks {
+    sinks: Vec<Sink>,
+}
+
+const SINKS: &str = r#"
+[[sinks]]
+name = "archive"
+format = { type = "csv", delimiter = "|", time = "epoch_secs", time_offset = "+08:00" }
+
+[[sinks]]
+name = "grep"
+format = { type = "kv", quote_strings = false, key_value_separator = "=", pair_separator = " " }
+
+[[sinks]]
+name = "warehouse"
+format = { type = "sql", table = "access_log", dialect = "mysql", time = "rfc3339" }
+
+[[sinks]]
+name = "siem"
+format = { type = "json", time = { strftime = "%d/%b/%Y:%H:%M:%S %z" }, time_offset = "+08:00" }
+"#;
+
+#[test]
+fn nginx_format_spec_toml_snapshot() {
+    let sinks: Sinks = toml::from_str(SINKS).unwrap();
+    let expected = [
+        (
+            "archive",
+            "192.168.1.2|1565064739|GET /nginx-logo.png HTTP/1.1|200",
+        ),
+        (
+            "grep",
+            "ip=192.168.1.2 time=2019-08-06 12:12:19 http/request=GET /nginx-logo.png HTTP/1.1 http/status=200",
+        ),
+        (
+            "warehouse",
+            "INSERT INTO `access_log` (`ip`, `time`, `http/request`, `http/status`) VALUES ('192.168.1.2', '2019-08-06T12:12:19Z', 'GET /nginx-logo.png HTTP/1.1', 200);",
+        ),
+        (
+            "siem",
+            r#"{"ip":"192.168.1.2","time":"06/Aug/2019:12:12:19 +0800","http/request":"GET /nginx-logo.png HTTP/1.1","http/status":200}"#,
+        ),
+    ];
+    assert_eq!(sinks.sinks.len(), expected.len());
+    for (sink, (name, expect)) in sinks.sinks.iter().zip(expected) {
+        assert_eq!(sink.name, name);
+        let fmt = sink.format.build().unwrap();
+        assert_eq!(fmt.format_record(&nginx_record()), expect, "{}", name);
+        let mut buf = String::new();
+        fmt.format_record_into(&nginx_record(), &mut buf).unwrap();
+        assert_eq!(buf, expect, "{}", name);
+    }
+}
+
+#[test]
+fn nginx_format_spec_rejects_invalid() {
+    let sinks: Sinks = toml::from_str(
+        r#"
+[[sinks]]
+name = "bad"
+format = { type = "csv", delimiter = "\"" }
+"#,
+    )
+    .unwrap();
+    let err = sinks.sinks[0].format.build().err().unwrap();
+    assert!(err.contains("delimiter"), "{}", err);
+}
@@ -0,0 +1,53 @@
+use chrono::NaiveDateTime;
+use std::net::{IpAddr, Ipv4Addr};
+use wp_data_fmt::{DataFormat, GELF_WAN_CHUNK_SIZE, Gelf, HeaderSource, StreamDataFormat};
+use wp_model_core::model::{DataField, DataRecord};
+
+// 生成 GELF 1.1 消息的快照测试
+// 关注点：
+// - host/short_message/timestamp 为顶层字段，其余字段加 _ 前缀
+// - 字段名中的 / 替换为 _，字符串沿用 json 的转义
+// - 大消息按 UDP 分块后可以还原
+fn nginx_record() -> DataRecord {
+    let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2));
+    let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+    DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_ip("ip", ip),
+            DataField::from_time("time", ts),
+            DataField::from_chars("http/request", "GET /nginx-logo.png HTTP/1.1"),
+            DataField::from_digit("http/status", 200),
+            DataField::from_digit("length", 368),
+            DataField::from_chars("src_key", "_"),
+        ],
+    }
+}
+
+#[test]
+fn nginx_access_log_gelf_snapshot() {
+    let f = Gelf::new()
+        .with_host("web01")
+        .with_short_message(HeaderSource::field("http/request"));
+    let expected = r#"{"version":"1.1","host":"web01","short_message":"GET /nginx-logo.png HTTP/1.1","timestamp":1565093539,"_ip":"192.168.1.2","_http_status":200,"_length":368,"_src_key":"_"}"#;
+    assert_eq!(f.format_record(&nginx_record()), expected);
+
+    let mut buf = String::new();
+    f.format_record_into(&nginx_record(), &mut buf).unwrap();
+    assert_eq!(buf, expected);
+}
+
+#[test]
+fn nginx_gelf_chunked_roundtrip() {
+    let long = "x".repeat(4000);
+    let record = DataRecord {
+        id: 0,
+        items: vec![DataField::from_chars("msg", long.as_str())],
+    };
+    let bytes = Gelf::new().format_record(&record).into_bytes();
+    let chunks = Gelf::chunk(&bytes, *b"nginx-01", GELF_WAN_CHUNK_SIZE).unwrap();
+    assert_eq!(chunks.len(), 3);
+    assert!(chunks.iter().all(|c| c.len() <= GELF_WAN_CHUNK_SIZE));
+    let joined: Vec<u8> = chunks.iter().flat_map(|c| c[12..].to_vec()).collect();
+    assert_eq!(joined, bytes);
+}
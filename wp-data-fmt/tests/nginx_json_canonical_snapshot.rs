@@ -0,0 +1,75 @@
+use chrono::NaiveDateTime;
+use std::net::{IpAddr, Ipv4Addr};
+use wp_data_fmt::{DataFormat, DataParse, Json, JsonCanonical, KeyValue, StreamDataFormat};
+use wp_model_core::model::types::value::ObjectValue;
+use wp_model_core::model::{DataField, DataRecord};
+
+// 规范化 JSON（RFC 8785）与记录摘要的快照测试
+// 关注点：
+// - 键按 UTF-16 排序、无空白、ECMAScript 数字格式
+// - 同一事件经不同解析路径（字段顺序、类型标注不同）得到相同摘要
+fn nginx_record() -> DataRecord {
+    let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+    let mut upstream = ObjectValue::new();
+    upstream.insert("status", DataField::from_digit("status", 200));
+    upstream.insert(
+        "response_time",
+        DataField::from_float("response_time", 0.05),
+    );
+    DataRecord {
+        id: 7,
+        items: vec![
+            DataField::from_ip("ip", IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2))),
+            DataField::from_time("time", ts),
+            DataField::from_chars("http/request", "GET /nginx-logo.png HTTP/1.1"),
+            DataField::from_digit("http/status", 200),
+            DataField::from_float("bytes_ratio", 1e-7),
+            DataField::from_obj("upstream", upstream),
+        ],
+    }
+}
+
+const CANONICAL: &str = concat!(
+    r#"{"bytes_ratio":1e-7,"http/request":"GET /nginx-logo.png HTTP/1.1","#,
+    r#""http/status":200,"ip":"192.168.1.2","time":"2019-08-06 12:12:19","#,
+    r#""upstream":{"response_time":0.05,"status":200}}"#
+);
+
+#[test]
+fn nginx_json_canonical_snapshot() {
+    let canonical = JsonCanonical::new();
+    assert_eq!(canonical.format_record(&nginx_record()), CANONICAL);
+    let mut buf = String::new();
+    canonical
+        .format_record_into(&nginx_record(), &mut buf)
+        .unwrap();
+    assert_eq!(buf, CANONICAL);
+}
+
+#[test]
+fn nginx_digest_independent_of_producer() {
+    let canonical = JsonCanonical::new();
+    let digest = canonical.digest_hex(&nginx_record());
+    assert_eq!(digest.len(), 64);
+
+    // reversed field order
+    let mut reversed = nginx_record();
+    reversed.items.reverse();
+    assert_eq!(canonical.digest_hex(&reversed), digest);
+
+    // read back from JSON without type hints: ip and time become text
+    let reparsed = Json::new()
+        .parse_record(&Json::new().format_record(&nginx_record()))
+        .unwrap();
+    assert_ne!(reparsed, nginx_record());
+    assert_eq!(canonical.digest_hex(&reparsed), digest);
+
+    // read back from KV
+    let kv = KeyValue::new();
+    let reparsed = kv.parse_record(&kv.format_record(&nginx_record())).unwrap();
+    assert_eq!(canonical.digest_hex(&reparsed), digest);
+
+    let mut changed = nginx_record();
+    changed.items[3] = DataField::from_digit("http/status", 404);
+    assert_ne!(canonical.digest_hex(&changed), digest);
+}
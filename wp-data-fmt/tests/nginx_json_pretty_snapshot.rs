@@ -0,0 +1,103 @@
+use chrono::NaiveDateTime;
+use std::net::{IpAddr, Ipv4Addr};
+use wp_data_fmt::{
+    DataFormat, DataParse, FormatSpec, FormatType, Json, LineEnding, StreamDataFormat,
+};
+use wp_model_core::model::types::value::ObjectValue;
+use wp_model_core::model::{DataField, DataRecord};
+
+// 缩进（pretty）JSON 的快照测试
+// 关注点：
+// - 嵌套对象 / 数组逐层缩进，空容器保持 `{}` / `[]`
+// - 与紧凑输出的值完全一致（含 NaN / Infinity 策略），可被 DataParse 读回
+// - 通过 FormatSpec 配置缩进宽度、键值空格与换行风格
+fn nginx_record() -> DataRecord {
+    let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+    let mut upstream = ObjectValue::new();
+    upstream.insert("addr", DataField::from_chars("addr", "10.0.0.8:8080"));
+    upstream.insert(
+        "response_time",
+        DataField::from_float("response_time", f64::NAN),
+    );
+    DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_ip("ip", IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2))),
+            DataField::from_time("time", ts),
+            DataField::from_chars("http/request", "GET /nginx-logo.png HTTP/1.1"),
+            DataField::from_digit("http/status", 200),
+            DataField::from_obj("upstream", upstream),
+            DataField::from_arr(
+                "tags",
+                vec![
+                    DataField::from_chars("", "static"),
+                    DataField::from_chars("", "cached"),
+                ],
+            ),
+        ],
+    }
+}
+
+const PRETTY: &str = r#"{
+  "ip": "192.168.1.2",
+  "time": "2019-08-06 12:12:19",
+  "http/request": "GET /nginx-logo.png HTTP/1.1",
+  "http/status": 200,
+  "upstream": {
+    "addr": "10.0.0.8:8080",
+    "response_time": null
+  },
+  "tags": [
+    "static",
+    "cached"
+  ]
+}"#;
+
+#[test]
+fn nginx_json_pretty_snapshot() {
+    let record = nginx_record();
+    let pretty = Json::pretty();
+    assert_eq!(pretty.format_record(&record), PRETTY);
+    let mut buf = String::new();
+    pretty.format_record_into(&record, &mut buf).unwrap();
+    assert_eq!(buf, PRETTY);
+
+    // same values as the compact form
+    let json = Json::new();
+    let compact = json.format_record(&record);
+    assert_eq!(
+        json.format_record(&json.parse_record(PRETTY).unwrap()),
+        compact
+    );
+}
+
+#[test]
+fn nginx_json_pretty_from_spec() {
+    let spec: FormatSpec = toml::from_str(
+        r#"type = "json"
+indent = 4
+line_ending = "crlf"
+"#,
+    )
+    .unwrap();
+    let fmt = spec.build().unwrap();
+    assert!(matches!(fmt, FormatType::Json(_)));
+    let record = DataRecord {
+        id: 0,
+        items: nginx_record().items.into_iter().skip(3).take(1).collect(),
+    };
+    assert_eq!(
+        fmt.format_record(&record),
+        "{\r\n    \"http/status\":200\r\n}"
+    );
+    let direct = Json::new()
+        .with_indent(4)
+        .with_line_ending(LineEnding::CrLf);
+    assert_eq!(
+        fmt.format_record(&nginx_record()),
+        direct.format_record(&nginx_record())
+    );
+
+    let err = toml::from_str::<FormatSpec>("type = \"json\"\nline_ending = \"cr\"\n").unwrap_err();
+    assert!(err.to_string().contains("unknown variant `cr`"), "{}", err);
+}
@@ -11,6 +11,7 @@ fn nginx_access_log_kv_snapshot() {
     let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
 
     let record = DataRecord {
+        id: 0,
         items: vec![
             DataField::from_ip("ip", ip),
             DataField::from_time("time", ts),
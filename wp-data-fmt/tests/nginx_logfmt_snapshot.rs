@@ -0,0 +1,53 @@
+use chrono::NaiveDateTime;
+use std::net::{IpAddr, Ipv4Addr};
+use wp_data_fmt::{DataFormat, Logfmt};
+use wp_model_core::model::types::value::ObjectValue;
+use wp_model_core::model::{DataField, DataRecord};
+
+// 生成 logfmt 文本的快照测试，参考 nginx_kv_snapshot.rs
+// 关注点：
+// - 仅在必要时加引号（空格、=、引号、反斜杠、控制字符）
+// - 嵌套对象展开为点号分隔的键
+// - time 含空格，需要加引号
+#[test]
+fn nginx_access_log_logfmt_snapshot() {
+    let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2));
+    let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+    let mut upstream = ObjectValue::new();
+    upstream.insert("addr", DataField::from_chars("addr", "10.0.0.9:80"));
+    upstream.insert("status", DataField::from_digit("status", 200));
+
+    let record = DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_ip("ip", ip),
+            DataField::from_time("time", ts),
+            DataField::from_chars("http/request", "GET /nginx-logo.png HTTP/1.1"),
+            DataField::from_digit("http/status", 200),
+            DataField::from_digit("length", 368),
+            DataField::from_chars("chars", "http://119.122.1.4/"),
+            DataField::from_chars(
+                "http/agent",
+                "Mozilla/5.0(Macintosh; Intel Mac OS X 10_14_5) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/75.0.3770.142 Safari/537.36 ",
+            ),
+            DataField::from_chars("src_key", "_"),
+            DataField::from_obj("upstream", upstream),
+        ],
+    };
+
+    let out = Logfmt::new().format_record(&record);
+    let expected = [
+        "ip=192.168.1.2",
+        "time=\"2019-08-06 12:12:19\"",
+        "http/request=\"GET /nginx-logo.png HTTP/1.1\"",
+        "http/status=200",
+        "length=368",
+        "chars=http://119.122.1.4/",
+        "http/agent=\"Mozilla/5.0(Macintosh; Intel Mac OS X 10_14_5) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/75.0.3770.142 Safari/537.36 \"",
+        "src_key=_",
+        "upstream.addr=10.0.0.9:80",
+        "upstream.status=200",
+    ]
+    .join(" ");
+    assert_eq!(out, expected);
+}
@@ -0,0 +1,69 @@
+use chrono::NaiveDateTime;
+use std::net::{IpAddr, Ipv4Addr};
+use wp_data_fmt::{DataFormat, FieldMapper, FormatType, StreamDataFormat};
+use wp_model_core::model::fmt_def::TextFmt;
+use wp_model_core::model::{DataField, DataRecord};
+
+// 字段投影（FieldMapper）的快照测试
+// 关注点：
+// - 通过 TOML 配置 include / exclude / rename / order / constants
+// - 可包装任意 FormatType，DataFormat 与 StreamDataFormat 输出一致
+fn nginx_record() -> DataRecord {
+    let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+    DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_ip("ip", IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2))),
+            DataField::from_time("time", ts),
+            DataField::from_chars("http/request", "GET /nginx-logo.png HTTP/1.1"),
+            DataField::from_digit("http/status", 200),
+            DataField::from_digit("length", 368),
+            DataField::from_chars("http/agent", "curl/7.64"),
+            DataField::from_chars("src_key", "_"),
+        ],
+    }
+}
+
+const WAREHOUSE: &str = r#"
+include = ["time", "ip", "http/status", "length", "http/agent", "site"]
+exclude = ["http/agent*"]
+rename = { "http/status" = "status", length = "bytes" }
+constants = { site = "edge-01", version = 3 }
+"#;
+
+#[test]
+fn nginx_mapper_toml_snapshot() {
+    let mapper: FieldMapper = toml::from_str(WAREHOUSE).unwrap();
+    assert_eq!(
+        mapper.output_names(&nginx_record()),
+        ["time", "ip", "status", "bytes", "site", "version"]
+    );
+
+    let json = mapper.clone().wrap(FormatType::from(&TextFmt::Json));
+    assert_eq!(
+        json.format_record(&nginx_record()),
+        r#"{"time":"2019-08-06 12:12:19","ip":"192.168.1.2","status":200,"bytes":368,"site":"edge-01","version":3}"#
+    );
+    let csv = mapper.wrap(FormatType::from(&TextFmt::Csv));
+    let mut buf = String::new();
+    csv.format_record_into(&nginx_record(), &mut buf).unwrap();
+    assert_eq!(buf, "2019-08-06 12:12:19,192.168.1.2,200,368,edge-01,3");
+    assert_eq!(buf, csv.format_record(&nginx_record()));
+}
+
+#[test]
+fn nginx_mapper_reorder_snapshot() {
+    let mapper: FieldMapper = toml::from_str(
+        r#"
+exclude = ["http/*", "src_*"]
+order = ["length", "time"]
+"#,
+    )
+    .unwrap();
+    let kv = mapper.wrap(FormatType::from(&TextFmt::Kv));
+    assert_eq!(
+        kv.format_record(&nginx_record()),
+        "length: 368, time: 2019-08-06 12:12:19, ip: 192.168.1.2"
+    );
+    assert!(toml::from_str::<FieldMapper>("unknown = 1").is_err());
+}
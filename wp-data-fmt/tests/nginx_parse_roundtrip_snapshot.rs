@@ -0,0 +1,130 @@
+use chrono::NaiveDateTime;
+use std::net::{IpAddr, Ipv4Addr};
+use wp_data_fmt::{
+    Csv, DataFormat, DataParse, Json, KeyValue, ParseHints, ProtoTxt, TimeFormat, TimeStyle,
+};
+use wp_model_core::model::types::value::ObjectValue;
+use wp_model_core::model::{DataField, DataRecord, DataType};
+
+// 文本回读（DataParse）的往返测试
+// 关注点：
+// - parse(format(r)) == r：Json / KeyValue / ProtoTxt / Csv
+// - 类型提示恢复 IpAddr / Time / Digit，嵌套字段按点路径提示
+// - 解析器沿用格式化器自身的分隔符、引号与时间格式
+fn ts() -> NaiveDateTime {
+    NaiveDateTime::parse_from_str("2019-08-06 12:12:19.250", "%Y-%m-%d %H:%M:%S%.f").unwrap()
+}
+
+fn flat_record() -> DataRecord {
+    DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_ip("ip", IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2))),
+            DataField::from_time("time", ts()),
+            DataField::from_chars("http/request", "GET /nginx-logo.png HTTP/1.1"),
+            DataField::from_digit("http/status", 200),
+            DataField::from_float("rt", 0.125),
+            DataField::from_bool("cached", false),
+            DataField::from_chars("http/agent", "Mozilla/5.0 \"quoted\", with; separators"),
+            DataField::from_chars("code", "007"),
+        ],
+    }
+}
+
+fn nested_record() -> DataRecord {
+    let mut upstream = ObjectValue::new();
+    upstream.insert(
+        "addr",
+        DataField::from_ip("addr", IpAddr::V4(Ipv4Addr::new(10, 0, 0, 7))),
+    );
+    upstream.insert("status", DataField::from_digit("status", 502));
+    upstream.insert("note", DataField::from_chars("note", "line\nbreak\ttab"));
+    let mut items = flat_record().items;
+    items.push(DataField::from_obj("upstream", upstream));
+    items.push(DataField::from_arr(
+        "tags",
+        vec![
+            DataField::from_chars("", "edge"),
+            DataField::from_chars("", "cn-north"),
+        ],
+    ));
+    DataRecord { id: 0, items }
+}
+
+fn hints() -> ParseHints {
+    ParseHints::new()
+        .with_type("ip", DataType::IP)
+        .with_type("time", DataType::Time)
+        .with_type("code", DataType::Chars)
+        .with_type("upstream.addr", DataType::IP)
+}
+
+#[test]
+fn nginx_json_roundtrip() {
+    let record = nested_record();
+    for json in [
+        Json::new(),
+        Json::new().with_time_format(TimeFormat::new(TimeStyle::EpochNanos)),
+    ] {
+        let text = json.format_record(&record);
+        assert_eq!(json.parse_record_with(&text, &hints()).unwrap(), record);
+    }
+    // without hints, ips and times read back as the text that was written
+    let plain = Json::new()
+        .parse_record(&Json::new().format_record(&record))
+        .unwrap();
+    assert_eq!(plain.items[0], DataField::from_chars("ip", "192.168.1.2"));
+    assert_eq!(
+        plain.items[1],
+        DataField::from_chars("time", "2019-08-06 12:12:19.250")
+    );
+}
+
+#[test]
+fn nginx_kv_roundtrip() {
+    let record = nested_record();
+    let kv = KeyValue::new();
+    let text = kv.format_record(&record);
+    assert_eq!(kv.parse_record_with(&text, &hints()).unwrap(), record);
+
+    let logfmt_like = KeyValue::new()
+        .with_pair_separator(" | ")
+        .with_key_value_separator("=")
+        .with_time_format(TimeStyle::Rfc3339);
+    let text = logfmt_like.format_record(&record);
+    assert!(text.contains("time=2019-08-06T12:12:19.250Z"), "{}", text);
+    assert_eq!(
+        logfmt_like.parse_record_with(&text, &hints()).unwrap(),
+        record
+    );
+}
+
+#[test]
+fn nginx_proto_txt_roundtrip() {
+    let record = nested_record();
+    for proto in [ProtoTxt::new(), ProtoTxt::new().with_multiline(true)] {
+        let text = proto.format_record(&record);
+        assert_eq!(proto.parse_record_with(&text, &hints()).unwrap(), record);
+    }
+}
+
+#[test]
+fn nginx_csv_roundtrip() {
+    let record = flat_record();
+    let columns: Vec<&str> = record.items.iter().map(|f| f.get_name()).collect();
+    let hints = hints().with_columns(columns);
+    for csv in [
+        Csv::new(),
+        Csv::new()
+            .with_delimiter(';')
+            .with_quote_char('\'')
+            .with_escape_char('\\'),
+    ] {
+        let text = csv.format_record(&record);
+        assert_eq!(csv.parse_record_with(&text, &hints).unwrap(), record);
+    }
+    let err = Csv::new()
+        .parse_record_with("a,b", &ParseHints::new().with_columns(["a"]))
+        .unwrap_err();
+    assert_eq!(err.to_string(), "2 values for 1 columns");
+}
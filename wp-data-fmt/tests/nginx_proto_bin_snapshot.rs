@@ -0,0 +1,160 @@
+use chrono::NaiveDateTime;
+use std::net::{IpAddr, Ipv4Addr};
+use wp_data_fmt::{DataFormat, FormatType, ProtoBin, ProtoSchema};
+use wp_model_core::model::fmt_def::TextFmt;
+use wp_model_core::model::types::value::ObjectValue;
+use wp_model_core::model::{DataField, DataRecord};
+
+// 二进制 protobuf 编码的快照测试
+// 关注点：
+// - 字段号来自 schema；不在给定 schema 中的字段报错而不是丢弃
+// - 未给 schema 时按记录推导，后出现的字段追加新字段号
+// - 字符串/ip 为 length-delimited，数字为 varint，时间为 Timestamp 子消息
+// - 对象编码为子消息，数组编码为 repeated 字段
+#[derive(Debug, PartialEq)]
+enum Wire {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(Vec<u8>),
+}
+
+fn read_varint(buf: &[u8], pos: &mut usize) -> u64 {
+    let mut v = 0u64;
+    let mut shift = 0;
+    loop {
+        let b = buf[*pos];
+        *pos += 1;
+        v |= ((b & 0x7f) as u64) << shift;
+        if b < 0x80 {
+            return v;
+        }
+        shift += 7;
+    }
+}
+
+fn decode(buf: &[u8]) -> Vec<(u64, Wire)> {
+    let mut pos = 0;
+    let mut out = Vec::new();
+    while pos < buf.len() {
+        let key = read_varint(buf, &mut pos);
+        let wire = match key & 7 {
+            0 => Wire::Varint(read_varint(buf, &mut pos)),
+            1 => {
+                let v = u64::from_le_bytes(buf[pos..pos + 8].try_into().unwrap());
+                pos += 8;
+                Wire::Fixed64(v)
+            }
+            2 => {
+                let len = read_varint(buf, &mut pos) as usize;
+                let v = buf[pos..pos + len].to_vec();
+                pos += len;
+                Wire::Bytes(v)
+            }
+            t => panic!("unexpected wire type {}", t),
+        };
+        out.push((key >> 3, wire));
+    }
+    out
+}
+
+fn bytes(s: &str) -> Wire {
+    Wire::Bytes(s.as_bytes().to_vec())
+}
+
+#[test]
+fn nginx_access_log_proto_bin_snapshot() {
+    let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2));
+    let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+    let mut upstream = ObjectValue::new();
+    upstream.insert("addr", DataField::from_chars("addr", "10.0.0.9:80"));
+
+    let record = DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_ip("ip", ip),
+            DataField::from_time("time", ts),
+            DataField::from_chars("http/request", "GET /nginx-logo.png HTTP/1.1"),
+            DataField::from_digit("http/status", 200),
+            DataField::from_digit("length", 368),
+            DataField::from_chars("src_key", "_"),
+            DataField::from_obj("upstream", upstream),
+            DataField::from_arr(
+                "tags",
+                vec![
+                    DataField::from_chars("", "a"),
+                    DataField::from_chars("", "b"),
+                ],
+            ),
+        ],
+    };
+
+    let schema = ProtoSchema::new()
+        .with_field("ip", 1)
+        .with_field("time", 2)
+        .with_field("http/request", 3)
+        .with_field("http/status", 4)
+        .with_field("length", 5)
+        .with_message("upstream", 6, ProtoSchema::new().with_field("addr", 1))
+        .with_field("tags", 7);
+    let proto = ProtoBin::new().with_schema(schema);
+    let err = proto.try_format_record(&record).unwrap_err();
+    assert!(err.contains("src_key"), "{}", err);
+    let mut record = record;
+    record.items.retain(|f| f.get_name() != "src_key");
+    let out = proto.format_record(&record);
+
+    let mut timestamp = Vec::new();
+    timestamp.push(0x08);
+    let mut secs = 1565093539u64;
+    while secs >= 0x80 {
+        timestamp.push((secs as u8) | 0x80);
+        secs >>= 7;
+    }
+    timestamp.push(secs as u8);
+
+    assert_eq!(
+        decode(&out),
+        vec![
+            (1, bytes("192.168.1.2")),
+            (2, Wire::Bytes(timestamp)),
+            (3, bytes("GET /nginx-logo.png HTTP/1.1")),
+            (4, Wire::Varint(200)),
+            (5, Wire::Varint(368)),
+            (
+                6,
+                Wire::Bytes(vec![
+                    0x0a, 11, b'1', b'0', b'.', b'0', b'.', b'0', b'.', b'9', b':', b'8', b'0'
+                ])
+            ),
+            (7, bytes("a")),
+            (7, bytes("b")),
+        ]
+    );
+}
+
+#[test]
+fn text_fmt_proto_produces_wire_format() {
+    let record = DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_chars("msg", "hi"),
+            DataField::from_float("ratio", 0.5),
+        ],
+    };
+    let fmt = FormatType::from(&TextFmt::Proto);
+    let out = fmt.format_record_bytes(&record).unwrap();
+    assert_eq!(
+        decode(&out),
+        vec![(1, bytes("hi")), (2, Wire::Fixed64(0.5f64.to_bits()))]
+    );
+    // a field first seen later gets the next free number
+    let later = DataRecord {
+        id: 1,
+        items: vec![
+            DataField::from_chars("status", "ok"),
+            DataField::from_chars("msg", "bye"),
+        ],
+    };
+    let out = fmt.format_record_bytes(&later).unwrap();
+    assert_eq!(decode(&out), vec![(3, bytes("ok")), (1, bytes("bye"))]);
+}
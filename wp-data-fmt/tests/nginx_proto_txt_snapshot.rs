@@ -1,19 +1,21 @@
 use chrono::NaiveDateTime;
 use std::net::{IpAddr, Ipv4Addr};
 use wp_data_fmt::{DataFormat, ProtoTxt};
+use wp_model_core::model::types::value::ObjectValue;
 use wp_model_core::model::{DataField, DataRecord};
 
 // 生成 proto-text 文本的快照测试，参考 nginx_kv_snapshot.rs
 // 关注点：
 // - 字符串需要使用双引号并转义内部的引号
 // - ip/time 以字符串输出（带引号）
-// - 字段之间使用换行分隔，不带逗号
+// - 字段之间使用空格分隔，不带逗号，顶层不带花括号
 #[test]
 fn nginx_access_log_proto_text_snapshot() {
     let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2));
     let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
 
     let record = DataRecord {
+        id: 0,
         items: vec![
             DataField::from_ip("ip", ip),
             DataField::from_time("time", ts),
@@ -32,7 +34,7 @@ fn nginx_access_log_proto_text_snapshot() {
     let f = ProtoTxt::new();
     let out = f.format_record(&record);
 
-    let expected = format!("{{ {} }}", [
+    let expected = [
         "ip: \"192.168.1.2\"",
         "time: \"2019-08-06 12:12:19\"",
         "http/request: \"GET /nginx-logo.png HTTP/1.1\"",
@@ -42,7 +44,7 @@ fn nginx_access_log_proto_text_snapshot() {
         "http/agent: \"Mozilla/5.0(Macintosh; Intel Mac OS X 10_14_5) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/75.0.3770.142 Safari/537.36 \"",
         "src_key: \"_\"",
     ]
-    .join(" "));
+    .join(" ");
 
     assert_eq!(out, expected);
 }
@@ -51,6 +53,7 @@ fn nginx_access_log_proto_text_snapshot() {
 fn proto_text_string_escape() {
     // 验证内部双引号被正确转义
     let record = DataRecord {
+        id: 0,
         items: vec![
             DataField::from_chars("msg", "He said \"hi\""),
             DataField::from_digit("n", 1),
@@ -58,9 +61,51 @@ fn proto_text_string_escape() {
     };
     let f = ProtoTxt::new();
     let out = f.format_record(&record);
-    let expected = format!(
-        "{{ {} }}",
-        ["msg: \"He said \\\"hi\\\"\"", "n: 1",].join(" ")
-    );
+    let expected = ["msg: \"He said \\\"hi\\\"\"", "n: 1"].join(" ");
     assert_eq!(out, expected);
 }
+
+// 嵌套消息与 repeated 字段
+// 关注点：
+// - 对象输出为 `name { .. }` 消息块
+// - 数组展开为重复的 `name: v`
+// - 多行模式按层级缩进，顶层不带花括号
+#[test]
+fn nginx_proto_text_nested_snapshot() {
+    let mut upstream = ObjectValue::new();
+    upstream.insert("addr", DataField::from_chars("addr", "10.0.0.9:80"));
+    upstream.insert("time", DataField::from_float("time", 0.25));
+    let record = DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_digit("status", 200),
+            DataField::from_obj("upstream", upstream),
+            DataField::from_arr(
+                "tags",
+                vec![
+                    DataField::from_chars("", "a\\b"),
+                    DataField::from_chars("", "line\n"),
+                ],
+            ),
+        ],
+    };
+
+    let single = ProtoTxt::new().format_record(&record);
+    assert_eq!(
+        single,
+        r#"status: 200 upstream { addr: "10.0.0.9:80" time: 0.25 } tags: "a\\b" tags: "line\n""#
+    );
+
+    let multi = ProtoTxt::new().with_multiline(true).format_record(&record);
+    let expected = [
+        "status: 200",
+        "upstream {",
+        "  addr: \"10.0.0.9:80\"",
+        "  time: 0.25",
+        "}",
+        "tags: \"a\\\\b\"",
+        "tags: \"line\\n\"",
+    ]
+    .join("\n");
+    assert_eq!(multi, expected);
+}
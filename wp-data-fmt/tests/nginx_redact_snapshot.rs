@@ -0,0 +1,105 @@
+use chrono::NaiveDateTime;
+use std::net::{IpAddr, Ipv4Addr};
+use wp_data_fmt::{DataFormat, FormatType, RedactPolicy, StreamDataFormat};
+use wp_model_core::model::fmt_def::TextFmt;
+use wp_model_core::model::types::value::ObjectValue;
+use wp_model_core::model::{DataField, DataRecord};
+
+// 敏感字段脱敏（RedactPolicy）的快照测试
+// 关注点：
+// - 通过 TOML 配置规则：按字段名通配 / 按值类型匹配
+// - drop / mask / partial / hash / truncate_ip 五种动作
+// - 规则作用于嵌套 Obj / Array 内容
+// - 未知配置项报错，避免合规规则因拼写错误静默失效
+fn nginx_record() -> DataRecord {
+    let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+    let mut user = ObjectValue::new();
+    user.insert("name", DataField::from_chars("name", "alice"));
+    user.insert("phone", DataField::from_chars("phone", "13812345678"));
+    user.insert("password", DataField::from_chars("password", "s3cret"));
+    DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_ip("ip", IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2))),
+            DataField::from_time("time", ts),
+            DataField::from_chars("http/request", "GET /nginx-logo.png HTTP/1.1"),
+            DataField::from_digit("http/status", 200),
+            DataField::from_chars("auth_token", "eyJhbGciOi"),
+            DataField::from_chars("session", "f00dfeed"),
+            DataField::from_obj("user", user),
+            DataField::from_arr(
+                "forwarded",
+                vec![
+                    DataField::from_chars("", "10.20.30.40"),
+                    DataField::from_chars("", "2001:db8:aa:bb::1"),
+                ],
+            ),
+        ],
+    }
+}
+
+const POLICY: &str = r#"
+[[rules]]
+fields = ["*token*", "password"]
+action = "drop"
+
+[[rules]]
+fields = ["session"]
+action = "hash"
+key = "rotate-me"
+
+[[rules]]
+fields = ["user.phone"]
+action = "partial"
+
+[[rules]]
+fields = ["user.name"]
+action = "mask"
+mask = "<redacted>"
+
+[[rules]]
+types = ["ip"]
+action = "truncate_ip"
+
+[[rules]]
+fields = ["forwarded"]
+action = "truncate_ip"
+v4_prefix = 16
+"#;
+
+#[test]
+fn nginx_redact_toml_snapshot() {
+    let policy: RedactPolicy = toml::from_str(POLICY).unwrap();
+    let json = policy.clone().wrap(FormatType::from(&TextFmt::Json));
+    let expect = concat!(
+        r#"{"ip":"192.168.1.0","time":"2019-08-06 12:12:19","#,
+        r#""http/request":"GET /nginx-logo.png HTTP/1.1","http/status":200,"#,
+        r#""session":"589de93c8f0fad49bc7c3d489391cacd3103ebe9875f500c112bc1adcf055588","#,
+        r#""user":{"name":"<redacted>","phone":"*******5678"},"#,
+        r#""forwarded":["10.20.0.0","2001:db8:aa::"]}"#
+    );
+    assert_eq!(json.format_record(&nginx_record()), expect);
+    let mut buf = String::new();
+    json.format_record_into(&nginx_record(), &mut buf).unwrap();
+    assert_eq!(buf, expect);
+
+    let kv = policy.wrap(FormatType::from(&TextFmt::Kv));
+    assert_eq!(
+        kv.format_record(&nginx_record()),
+        concat!(
+            r#"ip: 192.168.1.0, time: 2019-08-06 12:12:19, "#,
+            r#"http/request: "GET /nginx-logo.png HTTP/1.1", http/status: 200, "#,
+            r#"session: "589de93c8f0fad49bc7c3d489391cacd3103ebe9875f500c112bc1adcf055588", "#,
+            r#"user: {"name": "<redacted>", "phone": "*******5678"}, "#,
+            r#"forwarded: ["10.20.0.0", "2001:db8:aa::"]"#
+        )
+    );
+}
+
+#[test]
+fn nginx_redact_rejects_unknown_keys() {
+    let typo = "[[rules]]\nfields = [\"password\"]\naction = \"mask\"\nmaks = \"x\"\n";
+    assert!(toml::from_str::<RedactPolicy>(typo).is_err());
+    let missing_key = "[[rules]]\nfields = [\"session\"]\naction = \"hash\"\n";
+    assert!(toml::from_str::<RedactPolicy>(missing_key).is_err());
+}
@@ -0,0 +1,66 @@
+use chrono::NaiveDateTime;
+use std::net::{IpAddr, Ipv4Addr};
+use wp_data_fmt::{Cef, DataFormat, HeaderSource, Leef, LeefVersion};
+use wp_model_core::model::{DataField, DataRecord};
+
+// 生成 CEF / LEEF 事件的快照测试
+// 关注点：
+// - 头部字段可以是固定值或取自记录字段，被头部使用的字段不再出现在扩展中
+// - CEF 扩展值转义 \ 和 =，时间输出为毫秒时间戳（rt）
+// - LEEF 时间输出为 devTime + devTimeFormat，2.0 在头部声明分隔符
+fn nginx_record() -> DataRecord {
+    let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2));
+    let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+    DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_ip("src", ip),
+            DataField::from_time("time", ts),
+            DataField::from_chars("request", "GET /index.php?a=1 HTTP/1.1"),
+            DataField::from_digit("status", 200),
+            DataField::from_digit("length", 368),
+        ],
+    }
+}
+
+#[test]
+fn nginx_access_log_cef_snapshot() {
+    let f = Cef::new()
+        .with_vendor("nginx")
+        .with_product("access")
+        .with_version("1.0")
+        .with_signature_id(HeaderSource::field("status"))
+        .with_name("http request")
+        .with_severity("3");
+    let expected = concat!(
+        "CEF:0|nginx|access|1.0|200|http request|3|",
+        "src=192.168.1.2 rt=1565093539000 request=GET /index.php?a\\=1 HTTP/1.1 length=368"
+    );
+    assert_eq!(f.format_record(&nginx_record()), expected);
+}
+
+#[test]
+fn nginx_access_log_leef_snapshot() {
+    let f = Leef::new()
+        .with_vendor("nginx")
+        .with_product("access")
+        .with_product_version("1.0")
+        .with_event_id(HeaderSource::field("status"));
+    let attrs = [
+        "src=192.168.1.2",
+        "devTime=Aug 06 2019 12:12:19.000",
+        "devTimeFormat=MMM dd yyyy HH:mm:ss.SSS",
+        "request=GET /index.php?a=1 HTTP/1.1",
+        "length=368",
+    ]
+    .join("\t");
+    assert_eq!(
+        f.clone().format_record(&nginx_record()),
+        format!("LEEF:2.0|nginx|access|1.0|200|x09|{}", attrs)
+    );
+    assert_eq!(
+        f.with_version(LeefVersion::V1)
+            .format_record(&nginx_record()),
+        format!("LEEF:1.0|nginx|access|1.0|200|{}", attrs)
+    );
+}
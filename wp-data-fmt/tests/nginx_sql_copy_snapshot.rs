@@ -0,0 +1,77 @@
+use chrono::NaiveDateTime;
+use std::net::{IpAddr, Ipv4Addr};
+use wp_data_fmt::{CopyFormat, SqlCopy};
+use wp_model_core::model::{DataField, DataRecord};
+
+// 批量导入（COPY / LOAD DATA）文本的快照测试
+// 关注点：
+// - 列顺序由锁定的 schema 决定，缺失字段输出 NULL（\N 或空）
+// - text 格式转义 \ 与制表符，CSV 格式仅在必要时加引号
+// - 同时生成对应的 COPY / LOAD DATA 语句
+fn nginx_records() -> Vec<DataRecord> {
+    let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+    vec![
+        DataRecord {
+            id: 0,
+            items: vec![
+                DataField::from_ip("ip", IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2))),
+                DataField::from_time("time", ts),
+                DataField::from_chars("http/request", "GET /a,b\t\\c HTTP/1.1"),
+                DataField::from_digit("http/status", 200),
+            ],
+        },
+        DataRecord {
+            id: 0,
+            items: vec![
+                DataField::from_digit("http/status", 404),
+                DataField::from_ip("ip", IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))),
+            ],
+        },
+    ]
+}
+
+#[test]
+fn nginx_pg_copy_text_snapshot() {
+    let records = nginx_records();
+    let f = SqlCopy::from_record("nginx_access", &records[0]);
+    assert_eq!(
+        f.copy_statement(),
+        r#"COPY "nginx_access" ("ip", "time", "http/request", "http/status") FROM STDIN;"#
+    );
+    assert_eq!(
+        f.format_batch(&records),
+        "192.168.1.2\t2019-08-06 12:12:19\tGET /a,b\\t\\\\c HTTP/1.1\t200\n\
+         10.0.0.1\t\\N\t\\N\t404\n"
+    );
+}
+
+#[test]
+fn nginx_pg_copy_csv_snapshot() {
+    let records = nginx_records();
+    let f = SqlCopy::from_record("nginx_access", &records[0]).with_format(CopyFormat::PgCsv);
+    assert_eq!(
+        f.copy_statement(),
+        r#"COPY "nginx_access" ("ip", "time", "http/request", "http/status") FROM STDIN WITH (FORMAT csv);"#
+    );
+    assert_eq!(
+        f.format_batch(&records),
+        "192.168.1.2,2019-08-06 12:12:19,\"GET /a,b\t\\c HTTP/1.1\",200\n10.0.0.1,,,404\n"
+    );
+}
+
+#[test]
+fn nginx_mysql_load_data_snapshot() {
+    let records = nginx_records();
+    let f = SqlCopy::from_record("nginx_access", &records[0]).with_format(CopyFormat::MySqlTsv);
+    assert_eq!(
+        f.load_data_statement("nginx.tsv"),
+        "LOAD DATA LOCAL INFILE 'nginx.tsv' INTO TABLE `nginx_access` CHARACTER SET utf8mb4 \
+         FIELDS TERMINATED BY '\\t' ESCAPED BY '\\\\' LINES TERMINATED BY '\\n' \
+         (`ip`, `time`, `http/request`, `http/status`);"
+    );
+    assert_eq!(
+        f.format_batch(&records),
+        "192.168.1.2\t2019-08-06 12:12:19\tGET /a,b\\t\\\\c HTTP/1.1\t200\n\
+         10.0.0.1\t\\N\t\\N\t404\n"
+    );
+}
@@ -0,0 +1,137 @@
+use chrono::NaiveDateTime;
+use std::net::{IpAddr, Ipv4Addr};
+use wp_data_fmt::{ColumnKind, DataFormat, SqlColumn, SqlDialect, SqlInsert, SqlSchema};
+use wp_model_core::model::{DataField, DataRecord};
+
+// 各 SQL 方言的快照测试，参考 nginx_sql_snapshot.rs
+// 关注点：
+// - 标识符引用：PostgreSQL/SQLite 双引号，MySQL/ClickHouse 反引号
+// - 字符串转义：MySQL/ClickHouse 需要转义反斜杠，布尔字面量随方言变化
+// - CREATE TABLE 类型映射与 upsert 语法
+// - ALTER TABLE 差异：新增列与放宽类型，破坏性变更只报告
+fn nginx_record() -> DataRecord {
+    let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2));
+    let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+    DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_ip("ip", ip),
+            DataField::from_time("time", ts),
+            DataField::from_chars("http/request", "GET /a\\b?q='x' HTTP/1.1"),
+            DataField::from_digit("http/status", 200),
+            DataField::from_float("rt", 0.25),
+            DataField::from_bool("cached", false),
+        ],
+    }
+}
+
+fn sql(dialect: SqlDialect) -> SqlInsert {
+    SqlInsert::new_with_json("nginx_access").with_dialect(dialect)
+}
+
+#[test]
+fn nginx_sql_postgres_snapshot() {
+    let f = sql(SqlDialect::Postgres);
+    assert_eq!(
+        f.format_record(&nginx_record()),
+        r#"INSERT INTO "nginx_access" ("ip", "time", "http/request", "http/status", "rt", "cached") VALUES ('192.168.1.2', '2019-08-06 12:12:19', 'GET /a\b?q=''x'' HTTP/1.1', 200, 0.25, FALSE);"#
+    );
+    assert_eq!(
+        f.generate_create_table(&[nginx_record()]),
+        "CREATE TABLE IF NOT EXISTS \"nginx_access\" (\n  \"ip\" INET,\n  \"time\" TIMESTAMP,\n  \"http/request\" TEXT,\n  \"http/status\" BIGINT,\n  \"rt\" DOUBLE PRECISION,\n  \"cached\" BOOLEAN\n);"
+    );
+    assert!(
+        f.format_upsert(&nginx_record(), &["ip", "time"])
+            .ends_with(r#"ON CONFLICT ("ip", "time") DO UPDATE SET "http/request" = EXCLUDED."http/request", "http/status" = EXCLUDED."http/status", "rt" = EXCLUDED."rt", "cached" = EXCLUDED."cached";"#)
+    );
+}
+
+#[test]
+fn nginx_sql_mysql_snapshot() {
+    let f = sql(SqlDialect::MySql);
+    assert_eq!(
+        f.format_record(&nginx_record()),
+        r#"INSERT INTO `nginx_access` (`ip`, `time`, `http/request`, `http/status`, `rt`, `cached`) VALUES ('192.168.1.2', '2019-08-06 12:12:19', 'GET /a\\b?q=''x'' HTTP/1.1', 200, 0.25, 0);"#
+    );
+    assert_eq!(
+        f.generate_create_table(&[nginx_record()]),
+        "CREATE TABLE IF NOT EXISTS `nginx_access` (\n  `ip` VARCHAR(45),\n  `time` DATETIME(6),\n  `http/request` TEXT,\n  `http/status` BIGINT,\n  `rt` DOUBLE,\n  `cached` BOOLEAN\n);"
+    );
+    assert!(
+        f.format_upsert(&nginx_record(), &["ip", "time"])
+            .ends_with(" AS new ON DUPLICATE KEY UPDATE `http/request` = new.`http/request`, `http/status` = new.`http/status`, `rt` = new.`rt`, `cached` = new.`cached`;")
+    );
+}
+
+#[test]
+fn nginx_sql_sqlite_snapshot() {
+    let f = sql(SqlDialect::Sqlite);
+    assert_eq!(
+        f.format_record(&nginx_record()),
+        r#"INSERT INTO "nginx_access" ("ip", "time", "http/request", "http/status", "rt", "cached") VALUES ('192.168.1.2', '2019-08-06 12:12:19', 'GET /a\b?q=''x'' HTTP/1.1', 200, 0.25, 0);"#
+    );
+    assert_eq!(
+        f.generate_create_table(&[nginx_record()]),
+        "CREATE TABLE IF NOT EXISTS \"nginx_access\" (\n  \"ip\" TEXT,\n  \"time\" TEXT,\n  \"http/request\" TEXT,\n  \"http/status\" INTEGER,\n  \"rt\" REAL,\n  \"cached\" INTEGER\n);"
+    );
+    assert!(
+        f.format_upsert(&nginx_record(), &["ip"])
+            .starts_with("INSERT OR REPLACE INTO \"nginx_access\" (")
+    );
+}
+
+#[test]
+fn nginx_sql_clickhouse_snapshot() {
+    let f = sql(SqlDialect::ClickHouse);
+    assert_eq!(
+        f.format_record(&nginx_record()),
+        r#"INSERT INTO `nginx_access` (`ip`, `time`, `http/request`, `http/status`, `rt`, `cached`) VALUES ('192.168.1.2', '2019-08-06 12:12:19', 'GET /a\\b?q=\'x\' HTTP/1.1', 200, 0.25, false);"#
+    );
+    assert_eq!(
+        f.generate_create_table(&[nginx_record()]),
+        "CREATE TABLE IF NOT EXISTS `nginx_access` (\n  `ip` String,\n  `time` DateTime64(6),\n  `http/request` String,\n  `http/status` Int64,\n  `rt` Float64,\n  `cached` Bool\n) ENGINE = MergeTree ORDER BY tuple();"
+    );
+    assert_eq!(
+        f.format_upsert(&nginx_record(), &["ip"]),
+        f.format_record(&nginx_record())
+    );
+}
+
+#[test]
+fn nginx_sql_alter_table_snapshot() {
+    // 旧表只有 ip / http/status，且 status 为整数
+    let table = SqlSchema::new(vec![
+        SqlColumn::new("ip", ColumnKind::Ip, false),
+        SqlColumn::new("http/status", ColumnKind::Int, false),
+        SqlColumn::new("upstream", ColumnKind::Text, true),
+    ]);
+    let mut record = nginx_record();
+    record.items[3] = DataField::from_chars("http/status", "-");
+    let target = SqlSchema::infer(&[record]);
+
+    let pg = sql(SqlDialect::Postgres).generate_alter_table(&table, &target, true);
+    assert_eq!(
+        pg.statements,
+        vec![
+            r#"ALTER TABLE "nginx_access" ADD COLUMN "time" TIMESTAMP;"#,
+            r#"ALTER TABLE "nginx_access" ADD COLUMN "http/request" TEXT;"#,
+            r#"ALTER TABLE "nginx_access" ALTER COLUMN "http/status" TYPE TEXT USING "http/status"::TEXT;"#,
+            r#"ALTER TABLE "nginx_access" ADD COLUMN "rt" DOUBLE PRECISION;"#,
+            r#"ALTER TABLE "nginx_access" ADD COLUMN "cached" BOOLEAN;"#,
+        ]
+    );
+    assert!(pg.skipped.iter().all(|c| c.is_destructive()));
+
+    let ch = sql(SqlDialect::ClickHouse).generate_alter_table(&table, &target, true);
+    assert_eq!(
+        ch.statements,
+        vec![
+            "ALTER TABLE `nginx_access` ADD COLUMN `time` Nullable(DateTime64(6));",
+            "ALTER TABLE `nginx_access` ADD COLUMN `http/request` Nullable(String);",
+            "ALTER TABLE `nginx_access` MODIFY COLUMN `http/status` String;",
+            "ALTER TABLE `nginx_access` ADD COLUMN `rt` Nullable(Float64);",
+            "ALTER TABLE `nginx_access` ADD COLUMN `cached` Nullable(Bool);",
+        ]
+    );
+    assert_eq!(ch.skipped.len(), 1);
+}
@@ -1,6 +1,6 @@
 use chrono::NaiveDateTime;
 use std::net::{IpAddr, Ipv4Addr};
-use wp_data_fmt::{DataFormat, SqlInsert};
+use wp_data_fmt::{DataFormat, SqlDialect, SqlInsert, SqlParam};
 use wp_model_core::model::{DataField, DataRecord};
 
 // 生成 SQL INSERT 文本的快照测试
@@ -13,6 +13,7 @@ fn nginx_access_log_sql_insert_snapshot() {
     let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
 
     let record = DataRecord {
+        id: 0,
         items: vec![
             DataField::from_ip("ip", ip),
             DataField::from_time("time", ts),
@@ -39,6 +40,7 @@ fn nginx_access_log_sql_insert_snapshot() {
 fn sql_string_escape() {
     // 验证单引号转义（重复一个单引号）
     let record = DataRecord {
+        id: 0,
         items: vec![
             DataField::from_chars("msg", "O'Reilly"),
             DataField::from_digit("n", 1),
@@ -53,6 +55,7 @@ fn sql_string_escape() {
 #[test]
 fn sql_upsert_quotes_conflict_columns() {
     let record = DataRecord {
+        id: 0,
         items: vec![
             DataField::from_chars("http/request", "GET /"),
             DataField::from_chars("user", "alice"),
@@ -72,6 +75,7 @@ fn sql_upsert_quotes_conflict_columns() {
 fn sql_batch_insert_snapshot() {
     let records = vec![
         DataRecord {
+            id: 0,
             items: vec![
                 DataField::from_ip("ip", IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2))),
                 DataField::from_time(
@@ -88,6 +92,7 @@ fn sql_batch_insert_snapshot() {
             ],
         },
         DataRecord {
+            id: 0,
             items: vec![
                 DataField::from_ip("ip", IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))),
                 DataField::from_time(
@@ -115,6 +120,7 @@ fn sql_batch_insert_snapshot() {
 #[test]
 fn sql_generate_create_table_snapshot() {
     let record = DataRecord {
+        id: 0,
         items: vec![
             DataField::from_ip("ip", IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))),
             DataField::from_time(
@@ -139,3 +145,42 @@ fn sql_generate_create_table_snapshot() {
 );"#;
     assert_eq!(ddl, expected);
 }
+
+#[test]
+fn sql_batch_params_snapshot() {
+    // 参数化批量插入：一条语句 + 多行绑定参数，值不再内联
+    let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+    let records: Vec<DataRecord> = ["GET /a", "GET /b'; DROP TABLE x; --"]
+        .iter()
+        .map(|req| DataRecord {
+            id: 0,
+            items: vec![
+                DataField::from_ip("ip", IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))),
+                DataField::from_time("time", ts),
+                DataField::from_chars("http/request", *req),
+                DataField::from_digit("http/status", 200),
+            ],
+        })
+        .collect();
+
+    let batch = SqlInsert::new_with_json("nginx_access").format_batch_params(&records);
+    assert_eq!(
+        batch.sql,
+        r#"INSERT INTO "nginx_access" ("ip", "time", "http/request", "http/status") VALUES ($1, $2, $3, $4)"#
+    );
+    assert_eq!(batch.rows.len(), 2);
+    assert_eq!(
+        batch.rows[1],
+        vec![
+            SqlParam::Inet(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))),
+            SqlParam::Timestamp(ts),
+            SqlParam::Text("GET /b'; DROP TABLE x; --".to_string()),
+            SqlParam::Int(200),
+        ]
+    );
+
+    let sqlite = SqlInsert::new_with_json("nginx_access")
+        .with_dialect(SqlDialect::Sqlite)
+        .format_batch_params(&records);
+    assert!(sqlite.sql.ends_with("VALUES (?, ?, ?, ?)"));
+}
@@ -0,0 +1,87 @@
+use chrono::NaiveDateTime;
+use std::net::{IpAddr, Ipv4Addr};
+use wp_data_fmt::{
+    Csv, DataFormat, FormatType, Json, KeyValue, Logfmt, ProtoTxt, Raw, SqlInsert, StreamDataFormat,
+};
+use wp_model_core::model::fmt_def::TextFmt;
+use wp_model_core::model::types::value::ObjectValue;
+use wp_model_core::model::{DataField, DataRecord};
+
+// 流式写入接口的快照测试
+// 关注点：
+// - format_record_into 与 format_record 输出逐字节一致
+// - 同一个缓冲区可以跨多条记录复用
+// - io::Write 目标（Vec<u8>）得到相同的字节
+fn nginx_record() -> DataRecord {
+    let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2));
+    let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+    let mut obj = ObjectValue::new();
+    obj.insert("inner", DataField::from_chars("inner", "a \"q\", b"));
+    DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_ip("ip", ip),
+            DataField::from_time("time", ts),
+            DataField::from_chars("http/request", "GET /nginx-logo.png HTTP/1.1"),
+            DataField::from_digit("http/status", 200),
+            DataField::from_digit("length", 368),
+            DataField::from_chars("chars", "http://119.122.1.4/"),
+            DataField::from_chars(
+                "http/agent",
+                "Mozilla/5.0(Macintosh; Intel Mac OS X 10_14_5) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/75.0.3770.142 Safari/537.36 ",
+            ),
+            DataField::from_chars("src_key", "_"),
+            DataField::from_float("ratio", 0.5),
+            DataField::from_obj("payload", obj),
+            DataField::from_arr(
+                "list",
+                vec![
+                    DataField::from_chars("", "x,y"),
+                    DataField::from_digit("", 9),
+                ],
+            ),
+        ],
+    }
+}
+
+fn assert_stream_matches<F>(f: &F)
+where
+    F: DataFormat<Output = String> + StreamDataFormat,
+{
+    let record = nginx_record();
+    let expected = f.format_record(&record);
+
+    let mut buf = String::new();
+    f.format_record_into(&record, &mut buf).unwrap();
+    assert_eq!(buf, expected);
+
+    buf.push('\n');
+    f.format_record_into(&record, &mut buf).unwrap();
+    assert_eq!(buf, format!("{}\n{}", expected, expected));
+
+    let mut bytes: Vec<u8> = Vec::new();
+    f.format_record_to_io(&record, &mut bytes).unwrap();
+    assert_eq!(String::from_utf8(bytes).unwrap(), expected);
+}
+
+#[test]
+fn nginx_stream_matches_string_api() {
+    assert_stream_matches(&Json::new());
+    assert_stream_matches(&Csv::new());
+    assert_stream_matches(&KeyValue::new());
+    assert_stream_matches(&Logfmt::new());
+    assert_stream_matches(&Raw::new());
+    assert_stream_matches(&ProtoTxt::new());
+    assert_stream_matches(&SqlInsert::new_with_json("nginx_access"));
+    assert_stream_matches(&FormatType::from(&TextFmt::Json));
+}
+
+#[test]
+fn nginx_json_stream_snapshot() {
+    let mut buf = String::new();
+    Json::new()
+        .format_record_into(&nginx_record(), &mut buf)
+        .unwrap();
+    let expected = r#"{"ip":"192.168.1.2","time":"2019-08-06 12:12:19","http/request":"GET /nginx-logo.png HTTP/1.1","http/status":200,"length":368,"chars":"http://119.122.1.4/","http/agent":"Mozilla/5.0(Macintosh; Intel Mac OS X 10_14_5) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/75.0.3770.142 Safari/537.36 ","src_key":"_","ratio":0.5,"payload":{"inner":"a \"q\", b"},"list":["x,y",9]}"#;
+    assert_eq!(buf, expected);
+}
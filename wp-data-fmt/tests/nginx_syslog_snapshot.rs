@@ -0,0 +1,60 @@
+use chrono::NaiveDateTime;
+use std::net::{IpAddr, Ipv4Addr};
+use wp_data_fmt::{DataFormat, Syslog5424, SyslogFraming};
+use wp_model_core::model::{DataField, DataRecord};
+
+// 生成 syslog 报文的快照测试
+// 关注点：
+// - 头部字段（时间/主机/应用/消息）来自记录中的指定字段
+// - 其余字段进入 SD-PARAM，值中的 " \ ] 需要转义
+// - RFC 3164 兼容模式与 TCP 的 octet-counting 分帧
+fn nginx_record() -> DataRecord {
+    let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2));
+    let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+    DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_ip("ip", ip),
+            DataField::from_time("time", ts),
+            DataField::from_chars("host", "web01"),
+            DataField::from_chars("http/request", "GET /nginx-logo.png HTTP/1.1"),
+            DataField::from_digit("http/status", 200),
+            DataField::from_digit("length", 368),
+            DataField::from_chars("src_key", "_"),
+            DataField::from_chars("msg", "GET /nginx-logo.png 200"),
+        ],
+    }
+}
+
+#[test]
+fn nginx_access_log_syslog5424_snapshot() {
+    let f = Syslog5424::new()
+        .with_facility(16)
+        .with_host_field("host")
+        .with_app_name("nginx");
+    let expected = concat!(
+        "<134>1 2019-08-06T12:12:19Z web01 nginx - - ",
+        r#"[wp@32473 ip="192.168.1.2" http/request="GET /nginx-logo.png HTTP/1.1" http/status="200" length="368" src_key="_"] "#,
+        "GET /nginx-logo.png 200"
+    );
+    assert_eq!(f.format_record(&nginx_record()), expected);
+
+    let framed = f
+        .with_framing(SyslogFraming::OctetCounting)
+        .format_record(&nginx_record());
+    assert_eq!(framed, format!("{} {}", expected.len(), expected));
+}
+
+#[test]
+fn nginx_access_log_syslog3164_snapshot() {
+    let f = Syslog5424::new()
+        .with_rfc3164(true)
+        .with_facility(16)
+        .with_host_field("host")
+        .with_app_name("nginx");
+    let expected = concat!(
+        "<134>Aug  6 12:12:19 web01 nginx: GET /nginx-logo.png 200 ",
+        r#"ip="192.168.1.2" http/request="GET /nginx-logo.png HTTP/1.1" http/status="200" length="368" src_key="_""#,
+    );
+    assert_eq!(f.format_record(&nginx_record()), expected);
+}
@@ -0,0 +1,70 @@
+use chrono::NaiveDateTime;
+use std::net::{IpAddr, Ipv4Addr};
+use wp_data_fmt::{DataFormat, FormatSpec, FormatType, Table, TableStyle};
+use wp_model_core::model::fmt_def::TextFmt;
+use wp_model_core::model::types::value::ObjectValue;
+use wp_model_core::model::{DataField, DataRecord};
+
+// 表格预览（Table / TextFmt::Show）的快照测试
+// 关注点：
+// - 单条记录输出为纵向 key | value 列表，嵌套对象缩进显示
+// - 批量记录输出为对齐的 ASCII / Unicode 表格，嵌套对象展开为点号列
+// - 超宽值按字素（grapheme）截断，不切断组合字符
+fn nginx_record(ip: u8, status: i64, request: &str) -> DataRecord {
+    let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+    let mut upstream = ObjectValue::new();
+    upstream.insert("addr", DataField::from_chars("addr", "10.0.0.8:8080"));
+    upstream.insert("status", DataField::from_digit("status", status));
+    DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_ip("ip", IpAddr::V4(Ipv4Addr::new(192, 168, 1, ip))),
+            DataField::from_time("time", ts),
+            DataField::from_chars("http/request", request),
+            DataField::from_digit("http/status", status),
+            DataField::from_obj("upstream", upstream),
+        ],
+    }
+}
+
+#[test]
+fn nginx_table_show_record() {
+    let fmt = FormatType::from(&TextFmt::Show);
+    assert!(matches!(fmt, FormatType::Table(_)));
+    let record = nginx_record(2, 200, "GET /nginx-logo.png HTTP/1.1");
+    let expected = "\
+ip           | 192.168.1.2
+time         | 2019-08-06 12:12:19
+http/request | GET /nginx-logo.png HTTP/1.1
+http/status  | 200
+upstream
+  addr       | 10.0.0.8:8080
+  status     | 200";
+    assert_eq!(fmt.format_record(&record), expected);
+}
+
+#[test]
+fn nginx_table_batch_unicode() {
+    let records = [
+        nginx_record(2, 200, "GET /nginx-logo.png HTTP/1.1"),
+        nginx_record(10, 404, "GET /下载/文件名很长很长很长.zip HTTP/1.1"),
+    ];
+    let spec: FormatSpec =
+        toml::from_str("type = \"table\"\nstyle = \"unicode\"\nmax_width = 24\n").unwrap();
+    let FormatType::Table(table) = spec.build().unwrap() else {
+        panic!("expected a table");
+    };
+    let expected = "\
+┌──────────────┬─────────────────────┬──────────────────────────┬─────────────┬───────────────┬─────────────────┐
+│ ip           │ time                │ http/request             │ http/status │ upstream.addr │ upstream.status │
+├──────────────┼─────────────────────┼──────────────────────────┼─────────────┼───────────────┼─────────────────┤
+│ 192.168.1.2  │ 2019-08-06 12:12:19 │ GET /nginx-logo.png HTT… │         200 │ 10.0.0.8:8080 │             200 │
+│ 192.168.1.10 │ 2019-08-06 12:12:19 │ GET /下载/文件名很长很长很长.zip H… │         404 │ 10.0.0.8:8080 │             404 │
+└──────────────┴─────────────────────┴──────────────────────────┴─────────────┴───────────────┴─────────────────┘";
+    assert_eq!(table.format_batch(&records), expected);
+
+    let ascii = Table::new().with_style(TableStyle::Ascii).with_max_width(0);
+    let out = ascii.format_batch(&records[..1]);
+    assert!(out.starts_with("+-------------+"), "{}", out);
+    assert!(out.contains("| GET /nginx-logo.png HTTP/1.1 |"), "{}", out);
+}
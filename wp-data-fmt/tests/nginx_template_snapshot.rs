@@ -0,0 +1,79 @@
+use chrono::{FixedOffset, NaiveDateTime};
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr};
+use wp_data_fmt::{DataFormat, FormatSpec, FormatType, StreamDataFormat, Template, TimeFormat};
+use wp_model_core::model::types::value::ObjectValue;
+use wp_model_core::model::{DataField, DataRecord};
+
+// 模板格式（Template）的快照测试
+// 关注点：
+// - 还原 nginx access 日志行（strftime 时间、引号转义、缺省值）
+// - 旧系统的竖线分隔格式：宽度 / 精度 / 嵌套字段路径
+// - 通过 FormatSpec 配置，模板语法错误在 build 时报出位置
+fn nginx_record() -> DataRecord {
+    let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+    let mut upstream = ObjectValue::new();
+    upstream.insert("addr", DataField::from_chars("addr", "10.0.0.8:8080"));
+    upstream.insert(
+        "response_time",
+        DataField::from_float("response_time", 0.0476),
+    );
+    DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_ip("ip", IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2))),
+            DataField::from_time("time", ts),
+            DataField::from_chars("http/request", "GET /nginx-logo.png HTTP/1.1"),
+            DataField::from_digit("http/status", 200),
+            DataField::from_digit("length", 368),
+            DataField::from_chars("http/referer", ""),
+            DataField::from_chars("http/agent", "Mozilla/5.0 \"curl\""),
+            DataField::from_obj("upstream", upstream),
+        ],
+    }
+}
+
+const NGINX: &str = concat!(
+    r#"{ip} - - [{time:%d/%b/%Y:%H:%M:%S %z}] "{http/request|escape=nginx}" "#,
+    r#"{http/status} {length} "{http/referer|default=-}" "{http/agent|escape=nginx}""#
+);
+
+#[test]
+fn nginx_template_access_line() {
+    let template = Template::compile(NGINX).unwrap().with_time_format(
+        TimeFormat::default().with_offset(FixedOffset::east_opt(8 * 3600).unwrap()),
+    );
+    let expected = concat!(
+        r#"192.168.1.2 - - [06/Aug/2019:12:12:19 +0800] "GET /nginx-logo.png HTTP/1.1" "#,
+        r#"200 368 "-" "Mozilla/5.0 \x22curl\x22""#
+    );
+    assert_eq!(template.format_record(&nginx_record()), expected);
+    let mut buf = String::new();
+    template
+        .format_record_into(&nginx_record(), &mut buf)
+        .unwrap();
+    assert_eq!(buf, expected);
+}
+
+#[derive(Deserialize)]
+struct Sink {
+    format: FormatSpec,
+}
+
+#[test]
+fn nginx_template_from_spec() {
+    let sink: Sink = toml::from_str(
+        r#"format = { type = "template", pattern = "{http/status:03}|{ip:<15}|{upstream.addr}|{upstream.response_time:.3}|{upstream.cache|default=MISS}|{time}", time = "epoch_secs" }"#,
+    )
+    .unwrap();
+    let fmt = sink.format.build().unwrap();
+    assert!(matches!(fmt, FormatType::Template(_)));
+    assert_eq!(
+        fmt.format_record(&nginx_record()),
+        "200|192.168.1.2    |10.0.0.8:8080|0.048|MISS|1565093539"
+    );
+
+    let sink: Sink =
+        toml::from_str(r#"format = { type = "template", pattern = "{ip} {time:%d/%b" }"#).unwrap();
+    assert_eq!(sink.format.build().err().unwrap(), "unclosed '{' at byte 5");
+}
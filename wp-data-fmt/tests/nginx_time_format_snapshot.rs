@@ -0,0 +1,84 @@
+use chrono::{FixedOffset, NaiveDateTime};
+use wp_data_fmt::{
+    Csv, DataFormat, Json, KeyValue, ProtoTxt, Raw, SqlInsert, TimeFormat, TimeStyle,
+};
+use wp_model_core::model::types::value::ObjectValue;
+use wp_model_core::model::{DataField, DataRecord};
+
+// 时间格式配置的快照测试
+// 关注点：
+// - 同一 TimeFormat 在 Json/Csv/KV/Raw/ProtoTxt/SQL 中一致生效
+// - 嵌套对象与数组中的时间同样使用配置
+// - 源时区（+08:00）影响 RFC 3339 偏移与 epoch 值
+fn nginx_record() -> DataRecord {
+    let ts = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+    let mut upstream = ObjectValue::new();
+    upstream.insert("start", DataField::from_time("start", ts));
+    DataRecord {
+        id: 0,
+        items: vec![
+            DataField::from_time("time", ts),
+            DataField::from_obj("upstream", upstream),
+            DataField::from_arr("retries", vec![DataField::from_time("", ts)]),
+        ],
+    }
+}
+
+fn cst() -> TimeFormat {
+    TimeFormat::new(TimeStyle::Rfc3339).with_offset(FixedOffset::east_opt(8 * 3600).unwrap())
+}
+
+#[test]
+fn nginx_time_rfc3339_snapshot() {
+    let r = nginx_record();
+    assert_eq!(
+        Json::new().with_time_format(cst()).format_record(&r),
+        r#"{"time":"2019-08-06T12:12:19+08:00","upstream":{"start":"2019-08-06T12:12:19+08:00"},"retries":["2019-08-06T12:12:19+08:00"]}"#
+    );
+    assert_eq!(
+        Csv::new().with_time_format(cst()).format_record(&r),
+        r#"2019-08-06T12:12:19+08:00,start:2019-08-06T12:12:19+08:00,2019-08-06T12:12:19+08:00"#
+    );
+    assert_eq!(
+        KeyValue::new().with_time_format(cst()).format_record(&r),
+        r#"time: 2019-08-06T12:12:19+08:00, upstream: {"start": 2019-08-06T12:12:19+08:00}, retries: [2019-08-06T12:12:19+08:00]"#
+    );
+    assert_eq!(
+        Raw::new().with_time_format(cst()).format_record(&r),
+        "2019-08-06T12:12:19+08:00 {start=2019-08-06T12:12:19+08:00} [2019-08-06T12:12:19+08:00]"
+    );
+    assert_eq!(
+        ProtoTxt::new().with_time_format(cst()).format_record(&r),
+        r#"time: "2019-08-06T12:12:19+08:00" upstream { start: "2019-08-06T12:12:19+08:00" } retries: "2019-08-06T12:12:19+08:00""#
+    );
+    assert_eq!(
+        SqlInsert::new_with_json("nginx")
+            .with_time_format(cst())
+            .format_record(&r),
+        r#"INSERT INTO "nginx" ("time", "upstream", "retries") VALUES ('2019-08-06T12:12:19+08:00', '{"start":"2019-08-06T12:12:19+08:00"}', '["2019-08-06T12:12:19+08:00"]');"#
+    );
+}
+
+#[test]
+fn nginx_time_epoch_snapshot() {
+    let r = nginx_record();
+    let millis = TimeFormat::new(TimeStyle::EpochMillis);
+    assert_eq!(
+        Json::new()
+            .with_time_format(millis.clone())
+            .format_record(&r),
+        r#"{"time":1565093539000,"upstream":{"start":1565093539000},"retries":[1565093539000]}"#
+    );
+    assert_eq!(
+        ProtoTxt::new()
+            .with_time_format(millis.clone())
+            .format_record(&r),
+        "time: 1565093539000 upstream { start: 1565093539000 } retries: 1565093539000"
+    );
+    assert_eq!(
+        SqlInsert::new_with_json("nginx")
+            .with_time_format(millis)
+            .format_record(&r),
+        r#"INSERT INTO "nginx" ("time", "upstream", "retries") VALUES (1565093539000, '{"start":1565093539000}', '[1565093539000]');"#
+    );
+}